@@ -0,0 +1,201 @@
+//! ICMP socket for executors driven by `async-io`
+
+use std::io::{ErrorKind, Result};
+use std::net::IpAddr;
+use std::task::{Context, Poll};
+
+use async_io::Async;
+
+use crate::socket::IcmpSocket;
+
+/// An [`IcmpSocket`][socket] driven by the `async-io` reactor, as used by smol and
+/// async-std.
+///
+/// It offers the same calls as [`AsyncIcmpSocket`][tokio] does for tokio, so code written
+/// against either only differs in the type it names. No runtime has to be running: the
+/// reactor of `async-io` starts on first use, and the futures can be polled by any
+/// executor.
+///
+/// Options are set on the wrapped socket, see [`get_ref`][get_ref]; read and write
+/// timeouts do not apply here.
+///
+/// ```rust,no_run
+/// use std::net::{IpAddr, Ipv4Addr};
+/// use icmp::{AsyncIoIcmpSocket, IcmpSocket};
+/// use icmp::v4::Icmpv4Message;
+///
+/// # async fn ping() -> std::io::Result<()> {
+/// let localhost = IpAddr::V4(Ipv4Addr::LOCALHOST);
+/// let socket = AsyncIoIcmpSocket::new(IcmpSocket::connect(localhost)?)?;
+///
+/// let request = Icmpv4Message::Echo {
+///     identifier: 1,
+///     sequence: 1,
+///     payload: b"ping".to_vec(),
+/// };
+/// socket.send(&request.to_bytes()?).await?;
+///
+/// let mut buf = [0; 1024];
+/// let (size, source) = socket.recv_from(&mut buf).await?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [socket]: struct.IcmpSocket.html
+/// [tokio]: struct.AsyncIcmpSocket.html
+/// [get_ref]: #method.get_ref
+pub struct AsyncIoIcmpSocket {
+    inner: Async<IcmpSocket>,
+}
+
+impl AsyncIoIcmpSocket {
+    /// Moves `socket` into nonblocking mode and registers it with the `async-io` reactor.
+    pub fn new(socket: IcmpSocket) -> Result<AsyncIoIcmpSocket> {
+        Ok(AsyncIoIcmpSocket {
+            inner: Async::new(socket)?,
+        })
+    }
+
+    /// Returns a reference to the wrapped socket.
+    pub fn get_ref(&self) -> &IcmpSocket {
+        self.inner.get_ref()
+    }
+
+    /// Deregisters the socket from the reactor and returns it, still in nonblocking
+    /// mode.
+    pub fn into_inner(self) -> Result<IcmpSocket> {
+        self.inner.into_inner()
+    }
+
+    /// Receives data from the socket. On success, returns the number of bytes read.
+    ///
+    /// See [`IcmpSocket::recv`][recv].
+    ///
+    /// [recv]: struct.IcmpSocket.html#method.recv
+    pub async fn recv(&self, buf: &mut [u8]) -> Result<usize> {
+        self.inner.read_with(|socket| socket.recv(buf)).await
+    }
+
+    /// Receives data from the socket. On success, returns the number of bytes read and
+    /// the address from whence the data came.
+    ///
+    /// See [`IcmpSocket::recv_from`][recv_from].
+    ///
+    /// [recv_from]: struct.IcmpSocket.html#method.recv_from
+    pub async fn recv_from(&self, buf: &mut [u8]) -> Result<(usize, IpAddr)> {
+        self.inner.read_with(|socket| socket.recv_from(buf)).await
+    }
+
+    /// Sends data on the socket to the remote address to which it is connected.
+    ///
+    /// See [`IcmpSocket::send`][send].
+    ///
+    /// [send]: struct.IcmpSocket.html#method.send
+    pub async fn send(&self, buf: &[u8]) -> Result<usize> {
+        loop {
+            match self.inner.get_ref().send(buf) {
+                Err(ref err) if err.kind() == ErrorKind::WouldBlock => {},
+                result => return result,
+            }
+            self.inner.writable().await?;
+        }
+    }
+
+    /// Sends data on the socket to the given address. On success, returns the number of
+    /// bytes written.
+    ///
+    /// See [`IcmpSocket::send_to`][send_to].
+    ///
+    /// [send_to]: struct.IcmpSocket.html#method.send_to
+    pub async fn send_to(&self, buf: &[u8], addr: IpAddr) -> Result<usize> {
+        loop {
+            match self.inner.get_ref().send_to(buf, addr) {
+                Err(ref err) if err.kind() == ErrorKind::WouldBlock => {},
+                result => return result,
+            }
+            self.inner.writable().await?;
+        }
+    }
+
+    /// Polls for a message to be ready to receive.
+    ///
+    /// Returns `Poll::Ready(Ok(()))` once the reactor saw the socket become readable
+    /// since this task last got `Poll::Pending`; a receive may still fail with
+    /// `WouldBlock`.
+    pub fn poll_recv_ready(&self, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        self.inner.poll_readable(cx)
+    }
+
+    /// Polls for the socket to be ready to send.
+    ///
+    /// Returns `Poll::Ready(Ok(()))` once the reactor saw the socket become writable
+    /// since this task last got `Poll::Pending`.
+    pub fn poll_send_ready(&self, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        self.inner.poll_writable(cx)
+    }
+
+    /// Attempts to receive data from the socket, like [`recv`][recv].
+    ///
+    /// [recv]: #method.recv
+    pub fn poll_recv(&self, cx: &mut Context<'_>, buf: &mut [u8]) -> Poll<Result<usize>> {
+        loop {
+            match self.inner.get_ref().recv(buf) {
+                Err(ref err) if err.kind() == ErrorKind::WouldBlock => {},
+                result => return Poll::Ready(result),
+            }
+            match self.inner.poll_readable(cx) {
+                Poll::Ready(ready) => ready?,
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+
+    /// Attempts to receive data from the socket, like [`recv_from`][recv_from].
+    ///
+    /// [recv_from]: #method.recv_from
+    pub fn poll_recv_from(&self, cx: &mut Context<'_>, buf: &mut [u8]) -> Poll<Result<(usize, IpAddr)>> {
+        loop {
+            match self.inner.get_ref().recv_from(buf) {
+                Err(ref err) if err.kind() == ErrorKind::WouldBlock => {},
+                result => return Poll::Ready(result),
+            }
+            match self.inner.poll_readable(cx) {
+                Poll::Ready(ready) => ready?,
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+
+    /// Attempts to send data on the socket, like [`send`][send].
+    ///
+    /// [send]: #method.send
+    pub fn poll_send(&self, cx: &mut Context<'_>, buf: &[u8]) -> Poll<Result<usize>> {
+        loop {
+            match self.inner.get_ref().send(buf) {
+                Err(ref err) if err.kind() == ErrorKind::WouldBlock => {},
+                result => return Poll::Ready(result),
+            }
+            match self.inner.poll_writable(cx) {
+                Poll::Ready(ready) => ready?,
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+
+    /// Attempts to send data on the socket to the given address, like
+    /// [`send_to`][send_to].
+    ///
+    /// [send_to]: #method.send_to
+    pub fn poll_send_to(&self, cx: &mut Context<'_>, buf: &[u8], addr: IpAddr) -> Poll<Result<usize>> {
+        loop {
+            match self.inner.get_ref().send_to(buf, addr) {
+                Err(ref err) if err.kind() == ErrorKind::WouldBlock => {},
+                result => return Poll::Ready(result),
+            }
+            match self.inner.poll_writable(cx) {
+                Poll::Ready(ready) => ready?,
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
@@ -0,0 +1,130 @@
+//! Internet checksum (RFC 1071)
+//!
+//! ICMPv4 checksums cover the ICMP message only, ICMPv6 checksums additionally cover an
+//! IPv6 pseudo-header (RFC 8200, section 8.1) built from the source and destination
+//! addresses and the message length.
+//!
+//! ```rust
+//! use icmp::checksum::{self, Checksum};
+//!
+//! let mut sum = Checksum::new();
+//! sum.add_bytes(&[0x08, 0x00, 0x00, 0x00]);
+//! sum.add_bytes(&[0x00, 0x01, 0x00, 0x07]);
+//!
+//! assert_eq!(sum.finish(), checksum::checksum(&[0x08, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x07]));
+//! ```
+
+use std::net::Ipv6Addr;
+
+const IPPROTO_ICMPV6: u8 = 58;
+
+/// Incremental one's-complement checksum computation.
+///
+/// Data may be added in chunks of any length, including odd ones: the result is the same
+/// as computing the checksum of all chunks concatenated.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Checksum {
+    sum: u32,
+    pending: Option<u8>,
+}
+
+impl Checksum {
+    /// Creates a checksum over no data.
+    pub fn new() -> Checksum {
+        Checksum::default()
+    }
+
+    /// Adds `data` to the checksum.
+    pub fn add_bytes(&mut self, mut data: &[u8]) {
+        if let Some(high) = self.pending.take() {
+            match data.split_first() {
+                Some((low, rest)) => {
+                    self.add_word(u16::from_be_bytes([high, *low]));
+                    data = rest;
+                },
+                None => {
+                    self.pending = Some(high);
+                    return;
+                },
+            }
+        }
+
+        let mut chunks = data.chunks_exact(2);
+        for chunk in &mut chunks {
+            self.add_word(u16::from_be_bytes([chunk[0], chunk[1]]));
+        }
+        if let [last] = chunks.remainder() {
+            self.pending = Some(*last);
+        }
+    }
+
+    /// Adds a 16-bit value to the checksum, as if added in network byte order.
+    pub fn add_u16(&mut self, value: u16) {
+        self.add_bytes(&value.to_be_bytes());
+    }
+
+    /// Adds a 32-bit value to the checksum, as if added in network byte order.
+    pub fn add_u32(&mut self, value: u32) {
+        self.add_bytes(&value.to_be_bytes());
+    }
+
+    fn add_word(&mut self, word: u16) {
+        self.sum += u32::from(word);
+        // Fold eagerly, so the accumulator never overflows.
+        self.sum = (self.sum & 0xffff) + (self.sum >> 16);
+    }
+
+    /// Returns the checksum of all data added so far, ready to be stored in network byte order.
+    pub fn finish(&self) -> u16 {
+        let mut sum = self.sum;
+        if let Some(high) = self.pending {
+            sum += u32::from(high) << 8;
+        }
+        while sum >> 16 != 0 {
+            sum = (sum & 0xffff) + (sum >> 16);
+        }
+
+        !(sum as u16)
+    }
+}
+
+/// Computes the one's-complement checksum of `data`.
+///
+/// When `data` already contains a valid checksum, the result is zero.
+pub fn checksum(data: &[u8]) -> u16 {
+    let mut sum = Checksum::new();
+    sum.add_bytes(data);
+    sum.finish()
+}
+
+/// Updates `checksum` after a 16-bit field changed from `old` to `new` (RFC 1624, eqn. 3).
+///
+/// Allows rewriting identifier or sequence numbers without recomputing the checksum over
+/// the whole message.
+pub fn update(checksum: u16, old: u16, new: u16) -> u16 {
+    let mut sum = Checksum::new();
+    sum.add_u16(!checksum);
+    sum.add_u16(!old);
+    sum.add_u16(new);
+    sum.finish()
+}
+
+/// Returns the checksum state after adding the ICMPv6 pseudo-header for a message of `len`
+/// bytes sent from `source` to `destination`.
+pub fn pseudo_header_v6(source: &Ipv6Addr, destination: &Ipv6Addr, len: u32) -> Checksum {
+    let mut sum = Checksum::new();
+    sum.add_bytes(&source.octets());
+    sum.add_bytes(&destination.octets());
+    sum.add_u32(len);
+    sum.add_u32(u32::from(IPPROTO_ICMPV6));
+    sum
+}
+
+/// Computes the ICMPv6 checksum of `message` sent from `source` to `destination`.
+///
+/// When `message` already contains a valid checksum, the result is zero.
+pub fn icmpv6_checksum(source: &Ipv6Addr, destination: &Ipv6Addr, message: &[u8]) -> u16 {
+    let mut sum = pseudo_header_v6(source, destination, message.len() as u32);
+    sum.add_bytes(message);
+    sum.finish()
+}
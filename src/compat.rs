@@ -1,11 +1,9 @@
-/// Few copy-pasted things from the private Rust modules to mimic core behaviour
-/// See `std::sys_common` at https://github.com/rust-lang/rust/tree/master/src/libstd/sys/common
+//! Few copy-pasted things from the private Rust modules to mimic core behaviour
+//! See `std::sys_common` at https://github.com/rust-lang/rust/tree/master/src/libstd/sys/common
 
-use std::u32;
 use std::mem;
 use std::io;
-use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
-use std::convert::From;
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6};
 use std::time::Duration;
 
 use crate::sys::Socket;
@@ -37,68 +35,58 @@ pub trait AsInner<Inner: ?Sized> {
     fn as_inner(&self) -> &Inner;
 }
 
-#[doc(hidden)]
-pub trait FromInner<Inner> {
-    fn from_inner(inner: Inner) -> Self;
-}
-
 #[doc(hidden)]
 pub trait IntoInner<Inner> {
     fn into_inner(self) -> Inner;
 }
 
-impl FromInner<libc::sockaddr> for IpAddr {
-
-    fn from_inner(inner: libc::sockaddr) -> IpAddr {
-        match inner.sa_family as i32 {
-            libc::AF_INET => {
-                // TODO: probably `ref` can be used here
-                let addr: libc::sockaddr_in = unsafe {
-                    *(&inner as *const _ as *const libc::sockaddr_in) as libc::sockaddr_in
-                };
-                IpAddr::V4(Ipv4Addr::from(u32::from_be(addr.sin_addr.s_addr)))
-            },
-            libc::AF_INET6 => {
-                // TODO: probably `ref` can be used here
-                let addr: libc::sockaddr_in6 = unsafe {
-                    *(&inner as *const _ as *const libc::sockaddr_in6) as libc::sockaddr_in6
-                };
-                IpAddr::V6(Ipv6Addr::from(addr.sin6_addr.s6_addr))
-            },
-            _ => unreachable!(),
-        }
-    }
-
-}
-
-impl IntoInner<libc::sockaddr> for IpAddr {
-    fn into_inner(self) -> libc::sockaddr {
-        match self {
-            IpAddr::V4(ref a) => {
-                let ip: u32 = From::from(*a);
-
-                let mut addr: libc::sockaddr_in = unsafe { mem::zeroed() };
+impl IntoInner<(libc::sockaddr_storage, libc::socklen_t)> for SocketAddr {
+    fn into_inner(self) -> (libc::sockaddr_storage, libc::socklen_t) {
+        let mut storage: libc::sockaddr_storage = unsafe { mem::zeroed() };
+        let len = match self {
+            SocketAddr::V4(ref a) => {
+                let addr = unsafe { &mut *(&mut storage as *mut _ as *mut libc::sockaddr_in) };
                 addr.sin_family = libc::AF_INET as libc::sa_family_t;
-                addr.sin_port = 0 as libc::in_port_t;
+                addr.sin_port = a.port().to_be();
                 addr.sin_addr = libc::in_addr {
-                    s_addr: ip.to_be() as libc::uint32_t
+                    s_addr: u32::from(*a.ip()).to_be(),
                 };
-
-                unsafe {
-                    *(&addr as *const _ as *const libc::sockaddr) as libc::sockaddr
-                }
+                mem::size_of::<libc::sockaddr_in>()
             },
-            IpAddr::V6(ref a) => {
-                let mut addr: libc::sockaddr_in6 = unsafe { mem::zeroed() };
+            SocketAddr::V6(ref a) => {
+                let addr = unsafe { &mut *(&mut storage as *mut _ as *mut libc::sockaddr_in6) };
                 addr.sin6_family = libc::AF_INET6 as libc::sa_family_t;
-                addr.sin6_addr = unsafe { mem::zeroed() };
-                addr.sin6_addr.s6_addr = a.octets();
+                addr.sin6_port = a.port().to_be();
+                addr.sin6_flowinfo = a.flowinfo();
+                addr.sin6_addr.s6_addr = a.ip().octets();
+                addr.sin6_scope_id = a.scope_id();
+                mem::size_of::<libc::sockaddr_in6>()
+            },
+        };
 
-                unsafe {
-                    *(&addr as *const _ as *const libc::sockaddr) as libc::sockaddr
-                }
-            }
-        }
+        (storage, len as libc::socklen_t)
+    }
+}
+
+/// Based on the rust' `std/sys_common/net.rs`
+pub fn sockaddr_to_addr(storage: &libc::sockaddr_storage, len: usize) -> io::Result<SocketAddr> {
+    match storage.ss_family as libc::c_int {
+        libc::AF_INET if len >= mem::size_of::<libc::sockaddr_in>() => {
+            let addr = unsafe { &*(storage as *const _ as *const libc::sockaddr_in) };
+            let ip = Ipv4Addr::from(u32::from_be(addr.sin_addr.s_addr));
+            Ok(SocketAddr::V4(SocketAddrV4::new(ip, u16::from_be(addr.sin_port))))
+        },
+        libc::AF_INET6 if len >= mem::size_of::<libc::sockaddr_in6>() => {
+            let addr = unsafe { &*(storage as *const _ as *const libc::sockaddr_in6) };
+            let ip = Ipv6Addr::from(addr.sin6_addr.s6_addr);
+            Ok(SocketAddr::V6(SocketAddrV6::new(
+                ip,
+                u16::from_be(addr.sin6_port),
+                addr.sin6_flowinfo,
+                addr.sin6_scope_id,
+            )))
+        },
+        _ => Err(io::Error::new(io::ErrorKind::InvalidInput, "invalid argument")),
     }
 }
 
@@ -132,14 +120,14 @@ pub fn set_timeout(sock: &Socket, dur: Option<Duration>, kind: libc::c_int) -> i
                                           "cannot set a 0 duration timeout"));
             }
 
-            let secs = if dur.as_secs() > libc::time_t::max_value() as u64 {
-                libc::time_t::max_value()
+            let secs = if dur.as_secs() > libc::time_t::MAX as u64 {
+                libc::time_t::MAX
             } else {
                 dur.as_secs() as libc::time_t
             };
             let mut timeout = libc::timeval {
                 tv_sec: secs,
-                tv_usec: (dur.subsec_nanos() / 1000) as libc::suseconds_t,
+                tv_usec: dur.subsec_micros() as libc::suseconds_t,
             };
             if timeout.tv_sec == 0 && timeout.tv_usec == 0 {
                 timeout.tv_usec = 1;
@@ -0,0 +1,604 @@
+//! ICMP multi-part message extensions (RFC 4884)
+//!
+//! Destination Unreachable, Time Exceeded and (ICMPv4 only) Parameter Problem messages may
+//! append an extension structure after the quoted datagram. The structure starts with a
+//! version and checksum header and carries objects such as the MPLS label stack of the
+//! probe (RFC 4950) or the interface it was received on (RFC 5837). Extended Echo
+//! requests (RFC 8335) carry the probed interface in such a structure as well.
+
+use std::cmp;
+use std::io::{Error, ErrorKind, Result};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+use crate::checksum::checksum;
+
+const VERSION: u8 = 2;
+const HEADER_LEN: usize = 4;
+const OBJECT_HEADER_LEN: usize = 4;
+
+const CLASS_MPLS_LABEL_STACK: u8 = 1;
+const C_TYPE_INCOMING_MPLS_LABEL_STACK: u8 = 1;
+const CLASS_INTERFACE_INFORMATION: u8 = 2;
+const CLASS_INTERFACE_IDENTIFICATION: u8 = 3;
+
+// Interface Identification Object C-Types
+const C_TYPE_BY_NAME: u8 = 1;
+const C_TYPE_BY_INDEX: u8 = 2;
+const C_TYPE_BY_ADDRESS: u8 = 3;
+
+// Interface Information Object C-Type flags
+const IF_INDEX: u8 = 0x08;
+const IP_ADDRESS: u8 = 0x04;
+const NAME: u8 = 0x02;
+const MTU: u8 = 0x01;
+
+const AFI_IPV4: u16 = 1;
+const AFI_IPV6: u16 = 2;
+const MAX_NAME_LEN: usize = 63;
+
+/// Minimum length of the original datagram field when extensions follow it.
+pub const MIN_DATAGRAM_LEN: usize = 128;
+
+/// An MPLS label stack entry (RFC 3032).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MplsLabel {
+    /// Label value, 20 bits
+    pub label: u32,
+    /// Traffic class, 3 bits
+    pub traffic_class: u8,
+    /// Bottom of stack flag
+    pub bottom_of_stack: bool,
+    /// Time to live
+    pub ttl: u8,
+}
+
+impl MplsLabel {
+    fn decode(buf: &[u8]) -> MplsLabel {
+        let entry = u32::from_be_bytes([buf[0], buf[1], buf[2], buf[3]]);
+
+        MplsLabel {
+            label: entry >> 12,
+            traffic_class: ((entry >> 9) & 0x07) as u8,
+            bottom_of_stack: entry & 0x100 != 0,
+            ttl: entry as u8,
+        }
+    }
+
+    fn encode(&self) -> [u8; 4] {
+        let entry = (self.label & 0x000f_ffff) << 12
+            | u32::from(self.traffic_class & 0x07) << 9
+            | u32::from(self.bottom_of_stack) << 8
+            | u32::from(self.ttl);
+        entry.to_be_bytes()
+    }
+}
+
+/// Role of the interface described by an Interface Information Object.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum InterfaceRole {
+    /// Interface upon which the probe arrived
+    Incoming,
+    /// Sub-IP component of the interface upon which the probe arrived
+    SubIpComponent,
+    /// Interface through which the probe would have been forwarded
+    Outgoing,
+    /// Next hop to which the probe would have been forwarded
+    NextHop,
+}
+
+/// An Interface Information Object (RFC 5837).
+///
+/// Identifies an interface of the reporting router by any combination of its ifIndex,
+/// IP address, name and MTU.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InterfaceInformation {
+    /// Which interface is described
+    pub role: InterfaceRole,
+    /// SNMP ifIndex of the interface
+    pub if_index: Option<u32>,
+    /// An IP address of the interface
+    pub address: Option<IpAddr>,
+    /// Interface name, at most 63 bytes of UTF-8
+    pub name: Option<String>,
+    /// MTU of the interface
+    pub mtu: Option<u32>,
+}
+
+impl InterfaceInformation {
+    fn c_type(&self) -> u8 {
+        let role = match self.role {
+            InterfaceRole::Incoming => 0,
+            InterfaceRole::SubIpComponent => 1,
+            InterfaceRole::Outgoing => 2,
+            InterfaceRole::NextHop => 3,
+        };
+        let mut c_type = role << 6;
+        if self.if_index.is_some() {
+            c_type |= IF_INDEX;
+        }
+        if self.address.is_some() {
+            c_type |= IP_ADDRESS;
+        }
+        if self.name.is_some() {
+            c_type |= NAME;
+        }
+        if self.mtu.is_some() {
+            c_type |= MTU;
+        }
+        c_type
+    }
+
+    fn name_field_len(name: &str) -> usize {
+        // Length octet, name and zero padding up to a multiple of four
+        (1 + name.len()).div_ceil(4) * 4
+    }
+
+    fn payload_len(&self) -> usize {
+        let address = match self.address {
+            Some(IpAddr::V4(..)) => 8,
+            Some(IpAddr::V6(..)) => 20,
+            None => 0,
+        };
+        let name = self.name.as_ref().map_or(0, |name| InterfaceInformation::name_field_len(name));
+
+        self.if_index.map_or(0, |_| 4) + address + name + self.mtu.map_or(0, |_| 4)
+    }
+
+    fn decode(c_type: u8, payload: &[u8]) -> Result<InterfaceInformation> {
+        let truncated = || Error::new(ErrorKind::InvalidData, "interface information object is too short");
+        let mut rest = payload;
+        let mut take = |len: usize| -> Result<&[u8]> {
+            if rest.len() < len {
+                return Err(truncated());
+            }
+            let (head, tail) = rest.split_at(len);
+            rest = tail;
+            Ok(head)
+        };
+
+        let role = match c_type >> 6 {
+            0 => InterfaceRole::Incoming,
+            1 => InterfaceRole::SubIpComponent,
+            2 => InterfaceRole::Outgoing,
+            _ => InterfaceRole::NextHop,
+        };
+
+        let if_index = match c_type & IF_INDEX {
+            0 => None,
+            _ => {
+                let bytes = take(4)?;
+                Some(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+            },
+        };
+
+        let address = match c_type & IP_ADDRESS {
+            0 => None,
+            _ => {
+                let header = take(4)?;
+                match u16::from_be_bytes([header[0], header[1]]) {
+                    AFI_IPV4 => {
+                        let bytes = take(4)?;
+                        Some(IpAddr::V4(Ipv4Addr::new(bytes[0], bytes[1], bytes[2], bytes[3])))
+                    },
+                    AFI_IPV6 => {
+                        let mut octets = [0u8; 16];
+                        octets.copy_from_slice(take(16)?);
+                        Some(IpAddr::V6(Ipv6Addr::from(octets)))
+                    },
+                    _ => return Err(Error::new(ErrorKind::InvalidData, "unknown interface address family")),
+                }
+            },
+        };
+
+        let name = match c_type & NAME {
+            0 => None,
+            _ => {
+                let len = usize::from(take(1)?[0]);
+                if len == 0 || len % 4 != 0 || len > MAX_NAME_LEN + 1 {
+                    return Err(Error::new(ErrorKind::InvalidData, "invalid interface name length"));
+                }
+                let bytes = take(len - 1)?;
+                let end = bytes.iter().position(|byte| *byte == 0).unwrap_or(bytes.len());
+                match String::from_utf8(bytes[..end].to_vec()) {
+                    Ok(name) => Some(name),
+                    Err(..) => return Err(Error::new(ErrorKind::InvalidData, "interface name is not UTF-8")),
+                }
+            },
+        };
+
+        let mtu = match c_type & MTU {
+            0 => None,
+            _ => {
+                let bytes = take(4)?;
+                Some(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+            },
+        };
+
+        Ok(InterfaceInformation {
+            role,
+            if_index,
+            address,
+            name,
+            mtu,
+        })
+    }
+
+    fn encode(&self, buf: &mut [u8]) -> Result<()> {
+        let mut offset = 0;
+        if let Some(if_index) = self.if_index {
+            buf[offset..offset + 4].copy_from_slice(&if_index.to_be_bytes());
+            offset += 4;
+        }
+        match self.address {
+            Some(IpAddr::V4(address)) => {
+                buf[offset..offset + 4].copy_from_slice(&[0, AFI_IPV4 as u8, 0, 0]);
+                buf[offset + 4..offset + 8].copy_from_slice(&address.octets());
+                offset += 8;
+            },
+            Some(IpAddr::V6(address)) => {
+                buf[offset..offset + 4].copy_from_slice(&[0, AFI_IPV6 as u8, 0, 0]);
+                buf[offset + 4..offset + 20].copy_from_slice(&address.octets());
+                offset += 20;
+            },
+            None => {},
+        }
+        if let Some(ref name) = self.name {
+            if name.len() > MAX_NAME_LEN {
+                return Err(Error::new(ErrorKind::InvalidInput, "interface name is too long"));
+            }
+            let len = InterfaceInformation::name_field_len(name);
+            buf[offset] = len as u8;
+            buf[offset + 1..offset + 1 + name.len()].copy_from_slice(name.as_bytes());
+            for byte in &mut buf[offset + 1 + name.len()..offset + len] {
+                *byte = 0;
+            }
+            offset += len;
+        }
+        if let Some(mtu) = self.mtu {
+            buf[offset..offset + 4].copy_from_slice(&mtu.to_be_bytes());
+        }
+
+        Ok(())
+    }
+}
+
+/// An Interface Identification Object (RFC 8335), naming the interface probed by an
+/// Extended Echo request.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum InterfaceIdentifier {
+    /// Interface name, e.g. `eth0`
+    Name(String),
+    /// Interface ifIndex
+    Index(u32),
+    /// An address of the interface
+    Address(IpAddr),
+}
+
+impl InterfaceIdentifier {
+    fn c_type(&self) -> u8 {
+        match *self {
+            InterfaceIdentifier::Name(..) => C_TYPE_BY_NAME,
+            InterfaceIdentifier::Index(..) => C_TYPE_BY_INDEX,
+            InterfaceIdentifier::Address(..) => C_TYPE_BY_ADDRESS,
+        }
+    }
+
+    fn payload_len(&self) -> usize {
+        match *self {
+            InterfaceIdentifier::Name(ref name) => name.len().div_ceil(4) * 4,
+            InterfaceIdentifier::Index(..) => 4,
+            InterfaceIdentifier::Address(IpAddr::V4(..)) => 8,
+            InterfaceIdentifier::Address(IpAddr::V6(..)) => 20,
+        }
+    }
+
+    fn decode(c_type: u8, payload: &[u8]) -> Result<InterfaceIdentifier> {
+        let truncated = || Error::new(ErrorKind::InvalidData, "interface identification object is too short");
+
+        match c_type {
+            C_TYPE_BY_NAME => {
+                let end = payload.iter().position(|byte| *byte == 0).unwrap_or(payload.len());
+                match String::from_utf8(payload[..end].to_vec()) {
+                    Ok(name) => Ok(InterfaceIdentifier::Name(name)),
+                    Err(..) => Err(Error::new(ErrorKind::InvalidData, "interface name is not UTF-8")),
+                }
+            },
+            C_TYPE_BY_INDEX => match *payload {
+                [a, b, c, d] => Ok(InterfaceIdentifier::Index(u32::from_be_bytes([a, b, c, d]))),
+                _ => Err(truncated()),
+            },
+            C_TYPE_BY_ADDRESS => {
+                if payload.len() < 4 {
+                    return Err(truncated());
+                }
+                let afi = u16::from_be_bytes([payload[0], payload[1]]);
+                let address = &payload[4..];
+                match (afi, payload[2]) {
+                    (AFI_IPV4, 4) if address.len() >= 4 => {
+                        Ok(InterfaceIdentifier::Address(IpAddr::V4(Ipv4Addr::new(address[0], address[1], address[2], address[3]))))
+                    },
+                    (AFI_IPV6, 16) if address.len() >= 16 => {
+                        let mut octets = [0u8; 16];
+                        octets.copy_from_slice(&address[..16]);
+                        Ok(InterfaceIdentifier::Address(IpAddr::V6(Ipv6Addr::from(octets))))
+                    },
+                    _ => Err(Error::new(ErrorKind::InvalidData, "unsupported interface address")),
+                }
+            },
+            _ => Err(Error::new(ErrorKind::InvalidData, "unknown interface identification object type")),
+        }
+    }
+
+    fn encode(&self, buf: &mut [u8]) {
+        match *self {
+            InterfaceIdentifier::Name(ref name) => {
+                buf[..name.len()].copy_from_slice(name.as_bytes());
+                for byte in &mut buf[name.len()..] {
+                    *byte = 0;
+                }
+            },
+            InterfaceIdentifier::Index(index) => buf.copy_from_slice(&index.to_be_bytes()),
+            InterfaceIdentifier::Address(IpAddr::V4(address)) => {
+                buf[..4].copy_from_slice(&[0, AFI_IPV4 as u8, 4, 0]);
+                buf[4..].copy_from_slice(&address.octets());
+            },
+            InterfaceIdentifier::Address(IpAddr::V6(address)) => {
+                buf[..4].copy_from_slice(&[0, AFI_IPV6 as u8, 16, 0]);
+                buf[4..].copy_from_slice(&address.octets());
+            },
+        }
+    }
+}
+
+/// An object of the extension structure.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExtensionObject {
+    /// Incoming MPLS label stack of the probe, as received by the reporting router (RFC 4950)
+    MplsLabelStack(Vec<MplsLabel>),
+    /// Interface of the reporting router (RFC 5837)
+    InterfaceInformation(InterfaceInformation),
+    /// Interface probed by an Extended Echo request (RFC 8335)
+    InterfaceIdentification(InterfaceIdentifier),
+    /// Any other object, kept as-is
+    Unknown {
+        /// Object class
+        class_num: u8,
+        /// Object class sub-type
+        c_type: u8,
+        /// Object payload following the object header
+        payload: Vec<u8>,
+    },
+}
+
+impl ExtensionObject {
+    fn decode(class_num: u8, c_type: u8, payload: &[u8]) -> Result<ExtensionObject> {
+        let object = match (class_num, c_type) {
+            (CLASS_MPLS_LABEL_STACK, C_TYPE_INCOMING_MPLS_LABEL_STACK) => {
+                if payload.len() % 4 != 0 {
+                    return Err(Error::new(ErrorKind::InvalidData, "invalid MPLS label stack object length"));
+                }
+                ExtensionObject::MplsLabelStack(payload.chunks(4).map(MplsLabel::decode).collect())
+            },
+            (CLASS_INTERFACE_INFORMATION, c_type) => {
+                ExtensionObject::InterfaceInformation(InterfaceInformation::decode(c_type, payload)?)
+            },
+            (CLASS_INTERFACE_IDENTIFICATION, c_type) => {
+                ExtensionObject::InterfaceIdentification(InterfaceIdentifier::decode(c_type, payload)?)
+            },
+            _ => ExtensionObject::Unknown {
+                class_num,
+                c_type,
+                payload: payload.to_vec(),
+            },
+        };
+
+        Ok(object)
+    }
+
+    fn class(&self) -> (u8, u8) {
+        match *self {
+            ExtensionObject::MplsLabelStack(..) => (CLASS_MPLS_LABEL_STACK, C_TYPE_INCOMING_MPLS_LABEL_STACK),
+            ExtensionObject::InterfaceInformation(ref info) => (CLASS_INTERFACE_INFORMATION, info.c_type()),
+            ExtensionObject::InterfaceIdentification(ref interface) => {
+                (CLASS_INTERFACE_IDENTIFICATION, interface.c_type())
+            },
+            ExtensionObject::Unknown { class_num, c_type, .. } => (class_num, c_type),
+        }
+    }
+
+    fn payload_len(&self) -> usize {
+        match *self {
+            ExtensionObject::MplsLabelStack(ref labels) => labels.len() * 4,
+            ExtensionObject::InterfaceInformation(ref info) => info.payload_len(),
+            ExtensionObject::InterfaceIdentification(ref interface) => interface.payload_len(),
+            ExtensionObject::Unknown { ref payload, .. } => payload.len(),
+        }
+    }
+
+    fn encode_payload(&self, buf: &mut [u8]) -> Result<()> {
+        match *self {
+            ExtensionObject::MplsLabelStack(ref labels) => {
+                for (label, chunk) in labels.iter().zip(buf.chunks_mut(4)) {
+                    chunk.copy_from_slice(&label.encode());
+                }
+            },
+            ExtensionObject::InterfaceInformation(ref info) => info.encode(buf)?,
+            ExtensionObject::InterfaceIdentification(ref interface) => interface.encode(buf),
+            ExtensionObject::Unknown { ref payload, .. } => buf.copy_from_slice(payload),
+        }
+
+        Ok(())
+    }
+}
+
+/// An ICMP extension structure.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Extensions {
+    /// Objects in order of appearance
+    pub objects: Vec<ExtensionObject>,
+}
+
+impl Extensions {
+    /// Returns the MPLS label stack carried by these extensions, if any.
+    pub fn mpls_label_stack(&self) -> Option<&[MplsLabel]> {
+        self.objects.iter().find_map(|object| match *object {
+            ExtensionObject::MplsLabelStack(ref labels) => Some(labels.as_slice()),
+            _ => None,
+        })
+    }
+
+    /// Returns the Interface Information Objects carried by these extensions.
+    pub fn interface_information(&self) -> impl Iterator<Item = &InterfaceInformation> {
+        self.objects.iter().filter_map(|object| match *object {
+            ExtensionObject::InterfaceInformation(ref info) => Some(info),
+            _ => None,
+        })
+    }
+
+    /// Returns the interface identified by these extensions, if any.
+    pub fn interface_identification(&self) -> Option<&InterfaceIdentifier> {
+        self.objects.iter().find_map(|object| match *object {
+            ExtensionObject::InterfaceIdentification(ref interface) => Some(interface),
+            _ => None,
+        })
+    }
+
+    /// Returns the number of bytes [`encode`][encode] writes for these extensions.
+    ///
+    /// [encode]: #method.encode
+    pub fn encoded_len(&self) -> usize {
+        HEADER_LEN + self.objects.iter().map(|object| OBJECT_HEADER_LEN + object.payload_len()).sum::<usize>()
+    }
+
+    /// Encodes the extension structure into `buf`, computing its checksum.
+    ///
+    /// On success, returns the number of bytes written. Fails with `InvalidInput` for a
+    /// buffer shorter than [`encoded_len`][encoded_len], or for objects which cannot be
+    /// encoded, e.g. an interface name longer than 63 bytes.
+    ///
+    /// [encoded_len]: #method.encoded_len
+    pub fn encode(&self, buf: &mut [u8]) -> Result<usize> {
+        let len = self.encoded_len();
+        if buf.len() < len {
+            return Err(Error::new(ErrorKind::InvalidInput, "buffer is too small for ICMP extensions"));
+        }
+        let buf = &mut buf[..len];
+
+        buf[..HEADER_LEN].copy_from_slice(&[VERSION << 4, 0, 0, 0]);
+        let mut offset = HEADER_LEN;
+        for object in &self.objects {
+            let object_len = OBJECT_HEADER_LEN + object.payload_len();
+            if object_len > usize::from(u16::MAX) {
+                return Err(Error::new(ErrorKind::InvalidInput, "ICMP extension object is too long"));
+            }
+            let (class_num, c_type) = object.class();
+            buf[offset..offset + 2].copy_from_slice(&(object_len as u16).to_be_bytes());
+            buf[offset + 2] = class_num;
+            buf[offset + 3] = c_type;
+            object.encode_payload(&mut buf[offset + OBJECT_HEADER_LEN..offset + object_len])?;
+            offset += object_len;
+        }
+
+        let sum = checksum(buf);
+        buf[2..4].copy_from_slice(&sum.to_be_bytes());
+
+        Ok(len)
+    }
+
+    /// Encodes the extension structure into a newly allocated buffer.
+    ///
+    /// Fails like [`encode`][encode] does for objects which cannot be encoded.
+    ///
+    /// [encode]: #method.encode
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        let mut buf = vec![0; self.encoded_len()];
+        self.encode(&mut buf)?;
+        Ok(buf)
+    }
+
+    /// Decodes an extension structure, verifying its version and checksum.
+    pub fn decode(buf: &[u8]) -> Result<Extensions> {
+        if buf.len() < HEADER_LEN {
+            return Err(Error::new(ErrorKind::InvalidData, "ICMP extension structure is too short"));
+        }
+        if buf[0] >> 4 != VERSION {
+            return Err(Error::new(ErrorKind::InvalidData, "unsupported ICMP extension version"));
+        }
+        if checksum(buf) != 0 {
+            return Err(Error::new(ErrorKind::InvalidData, "ICMP extension checksum mismatch"));
+        }
+
+        let mut objects = Vec::new();
+        let mut rest = &buf[HEADER_LEN..];
+        while !rest.is_empty() {
+            if rest.len() < OBJECT_HEADER_LEN {
+                return Err(Error::new(ErrorKind::InvalidData, "ICMP extension object is too short"));
+            }
+            let len = usize::from(u16::from_be_bytes([rest[0], rest[1]]));
+            if len < OBJECT_HEADER_LEN || len > rest.len() {
+                return Err(Error::new(ErrorKind::InvalidData, "invalid ICMP extension object length"));
+            }
+            objects.push(ExtensionObject::decode(rest[2], rest[3], &rest[OBJECT_HEADER_LEN..len])?);
+            rest = &rest[len..];
+        }
+
+        Ok(Extensions {
+            objects,
+        })
+    }
+}
+
+/// Splits an error message body into the original datagram and the extensions.
+///
+/// `length` is the length attribute of the message, converted to bytes. Non-compliant
+/// messages without the attribute are probed for extensions at the fixed 128 byte offset
+/// (RFC 4884, section 5). Invalid extensions leave the whole body as the datagram.
+pub(crate) fn split(body: &[u8], length: usize) -> (&[u8], Option<Extensions>) {
+    let offset = match length {
+        0 if body.len() > MIN_DATAGRAM_LEN => MIN_DATAGRAM_LEN,
+        0 => return (body, None),
+        length if length < body.len() => length,
+        _ => return (body, None),
+    };
+
+    match Extensions::decode(&body[offset..]) {
+        Ok(extensions) => (&body[..offset], Some(extensions)),
+        Err(..) => (body, None),
+    }
+}
+
+/// Returns the length of the original datagram field, padded to `word` bytes when
+/// extensions follow it.
+pub(crate) fn datagram_field_len(datagram: &[u8], extensions: Option<&Extensions>, word: usize) -> usize {
+    match extensions {
+        Some(..) => cmp::max(MIN_DATAGRAM_LEN, datagram.len().div_ceil(word) * word),
+        None => datagram.len(),
+    }
+}
+
+/// Returns the length of an error message body carrying `datagram` and `extensions`.
+pub(crate) fn body_len(datagram: &[u8], extensions: Option<&Extensions>, word: usize) -> usize {
+    datagram_field_len(datagram, extensions, word) + extensions.map_or(0, Extensions::encoded_len)
+}
+
+/// Writes an error message body into `body`, which must be exactly `body_len` bytes long.
+///
+/// Returns the length attribute in `word` units, zero without extensions.
+pub(crate) fn encode_body(body: &mut [u8], datagram: &[u8], extensions: Option<&Extensions>, word: usize) -> Result<u8> {
+    body[..datagram.len()].copy_from_slice(datagram);
+
+    match extensions {
+        Some(extensions) => {
+            let field_len = datagram_field_len(datagram, Some(extensions), word);
+            if field_len / word > usize::from(u8::MAX) {
+                return Err(Error::new(ErrorKind::InvalidInput, "quoted datagram is too long for ICMP extensions"));
+            }
+            for byte in &mut body[datagram.len()..field_len] {
+                *byte = 0;
+            }
+            extensions.encode(&mut body[field_len..])?;
+            Ok((field_len / word) as u8)
+        },
+        None => Ok(0),
+    }
+}
@@ -0,0 +1,309 @@
+//! Kernel filters for raw sockets
+//!
+//! A raw ICMP socket receives a copy of every ICMP message the host receives. Filtering
+//! in the kernel keeps the unwanted ones from waking the socket up at all.
+//!
+//! [`IcmpTypeFilter`][type_filter] selects messages by type only. A classic BPF program
+//! built with [`BpfBuilder`][builder] also matches the code, the echo identifier and the
+//! source address, so probers sharing a host only see their own replies:
+//!
+//! ```rust
+//! use icmp::AddressFamily;
+//! use icmp::filter::BpfBuilder;
+//!
+//! let program = BpfBuilder::new(AddressFamily::V4)
+//!     .message_type(0)  // Echo Reply
+//!     .identifier(0x1234)
+//!     .build()
+//!     .unwrap();
+//! assert!(!program.instructions().is_empty());
+//! ```
+//!
+//! [type_filter]: struct.IcmpTypeFilter.html
+//! [builder]: struct.BpfBuilder.html
+
+use std::io::{Error, ErrorKind, Result};
+use std::net::IpAddr;
+
+use crate::AddressFamily;
+
+// Classic BPF opcodes, from `linux/bpf_common.h`
+const BPF_LD: u16 = 0x00;
+const BPF_LDX: u16 = 0x01;
+const BPF_JMP: u16 = 0x05;
+const BPF_RET: u16 = 0x06;
+const BPF_W: u16 = 0x00;
+const BPF_H: u16 = 0x08;
+const BPF_B: u16 = 0x10;
+const BPF_ABS: u16 = 0x20;
+const BPF_IND: u16 = 0x40;
+const BPF_MSH: u16 = 0xa0;
+const BPF_JEQ: u16 = 0x10;
+const BPF_K: u16 = 0x00;
+
+/// Base of the offsets reaching into the network header, whatever the data starts with.
+const SKF_NET_OFF: i32 = -0x10_0000;
+
+const IPV4_SOURCE: u32 = 12;
+const IPV6_SOURCE: i32 = 8;
+
+/// Set of ICMP or ICMPv6 message types passed to a raw socket, through the Linux
+/// `ICMP_FILTER` and `ICMP6_FILTER` options.
+///
+/// ```rust
+/// use icmp::filter::IcmpTypeFilter;
+///
+/// let mut filter = IcmpTypeFilter::block_all();
+/// filter.pass(0);  // Echo Reply
+/// filter.pass(3);  // Destination Unreachable
+///
+/// assert!(filter.passes(0));
+/// assert!(!filter.passes(8));
+/// ```
+///
+/// `ICMP_FILTER` only covers the ICMPv4 types 0 to 31; the others always pass.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct IcmpTypeFilter {
+    // One bit per type, set for blocked types, in the layout of `struct icmp6_filter`
+    blocked: [u32; 8],
+}
+
+impl IcmpTypeFilter {
+    /// Returns a filter passing every type.
+    pub fn pass_all() -> IcmpTypeFilter {
+        IcmpTypeFilter {
+            blocked: [0; 8],
+        }
+    }
+
+    /// Returns a filter blocking every type.
+    pub fn block_all() -> IcmpTypeFilter {
+        IcmpTypeFilter {
+            blocked: [u32::MAX; 8],
+        }
+    }
+
+    /// Passes messages of type `message_type`.
+    pub fn pass(&mut self, message_type: u8) {
+        self.blocked[usize::from(message_type >> 5)] &= !(1 << (message_type & 31));
+    }
+
+    /// Blocks messages of type `message_type`.
+    pub fn block(&mut self, message_type: u8) {
+        self.blocked[usize::from(message_type >> 5)] |= 1 << (message_type & 31);
+    }
+
+    /// Returns `true` if messages of type `message_type` pass the filter.
+    pub fn passes(&self, message_type: u8) -> bool {
+        self.blocked[usize::from(message_type >> 5)] & (1 << (message_type & 31)) == 0
+    }
+
+    /// Returns the `ICMP_FILTER` value, which leaves out the types above 31.
+    pub(crate) fn to_icmp_filter(self) -> u32 {
+        self.blocked[0]
+    }
+
+    pub(crate) fn from_icmp_filter(bits: u32) -> IcmpTypeFilter {
+        let mut filter = IcmpTypeFilter::pass_all();
+        filter.blocked[0] = bits;
+        filter
+    }
+
+    pub(crate) fn to_icmp6_filter(self) -> [u32; 8] {
+        self.blocked
+    }
+
+    pub(crate) fn from_icmp6_filter(blocked: [u32; 8]) -> IcmpTypeFilter {
+        IcmpTypeFilter {
+            blocked,
+        }
+    }
+}
+
+impl Default for IcmpTypeFilter {
+    fn default() -> IcmpTypeFilter {
+        IcmpTypeFilter::pass_all()
+    }
+}
+
+/// A classic BPF instruction, laid out as `struct sock_filter`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Instruction {
+    /// Opcode
+    pub code: u16,
+    /// Jump offset if the condition holds
+    pub jt: u8,
+    /// Jump offset if the condition does not hold
+    pub jf: u8,
+    /// Constant operand
+    pub k: u32,
+}
+
+impl Instruction {
+    fn load(size: u16, mode: u16, k: u32) -> Instruction {
+        Instruction {
+            code: BPF_LD | size | mode,
+            jt: 0,
+            jf: 0,
+            k,
+        }
+    }
+
+    fn ret(k: u32) -> Instruction {
+        Instruction {
+            code: BPF_RET | BPF_K,
+            jt: 0,
+            jf: 0,
+            k,
+        }
+    }
+}
+
+/// A classic BPF program, attached to a socket with
+/// [`IcmpSocket::attach_filter`][attach].
+///
+/// The program sees what the socket would receive: the IPv4 header followed by the ICMP
+/// message on raw IPv4 sockets, the ICMPv6 message alone on raw IPv6 sockets. It returns
+/// the number of bytes to deliver, 0 to drop the message.
+///
+/// [attach]: ../struct.IcmpSocket.html#method.attach_filter
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct BpfProgram {
+    instructions: Vec<Instruction>,
+}
+
+impl BpfProgram {
+    /// Wraps hand-written instructions.
+    pub fn from_instructions(instructions: Vec<Instruction>) -> BpfProgram {
+        BpfProgram {
+            instructions,
+        }
+    }
+
+    /// Returns the instructions of this program.
+    pub fn instructions(&self) -> &[Instruction] {
+        &self.instructions
+    }
+}
+
+/// Builds a [`BpfProgram`][program] passing the messages that match every condition set,
+/// for raw sockets of the given family.
+///
+/// Datagram sockets already only receive the replies to their own requests, with an
+/// identifier chosen by the kernel, so this is of no use to them.
+///
+/// [program]: struct.BpfProgram.html
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BpfBuilder {
+    family: AddressFamily,
+    message_type: Option<u8>,
+    code: Option<u8>,
+    identifier: Option<u16>,
+    source: Option<IpAddr>,
+}
+
+impl BpfBuilder {
+    /// Starts a program passing every message received by a raw `family` socket.
+    pub fn new(family: AddressFamily) -> BpfBuilder {
+        BpfBuilder {
+            family,
+            message_type: None,
+            code: None,
+            identifier: None,
+            source: None,
+        }
+    }
+
+    /// Only passes messages of type `message_type`.
+    pub fn message_type(mut self, message_type: u8) -> BpfBuilder {
+        self.message_type = Some(message_type);
+        self
+    }
+
+    /// Only passes messages with code `code`.
+    pub fn code(mut self, code: u8) -> BpfBuilder {
+        self.code = Some(code);
+        self
+    }
+
+    /// Only passes messages with `identifier` in the first two bytes of the
+    /// rest-of-header, where echo messages carry their identifier.
+    pub fn identifier(mut self, identifier: u16) -> BpfBuilder {
+        self.identifier = Some(identifier);
+        self
+    }
+
+    /// Only passes messages sent from `source`.
+    pub fn source(mut self, source: IpAddr) -> BpfBuilder {
+        self.source = Some(source);
+        self
+    }
+
+    /// Returns the program.
+    ///
+    /// Fails with `InvalidInput` if the source address is not of the builder family.
+    pub fn build(&self) -> Result<BpfProgram> {
+        let mut program = vec![];
+        // Each condition is a load followed by a comparison with a placeholder jump
+        // to the final drop, fixed up below.
+        let mut conditions = vec![];
+        let mut compare = |program: &mut Vec<Instruction>, load: Instruction, k: u32| {
+            program.push(load);
+            conditions.push(program.len());
+            program.push(Instruction {
+                code: BPF_JMP | BPF_JEQ | BPF_K,
+                jt: 0,
+                jf: 0,
+                k,
+            });
+        };
+
+        // Loads are relative to the ICMP header, which raw IPv4 sockets receive after
+        // the IPv4 header, so its length is kept in X.
+        let mode = match self.family {
+            AddressFamily::V4 => {
+                program.push(Instruction {
+                    code: BPF_LDX | BPF_B | BPF_MSH,
+                    jt: 0,
+                    jf: 0,
+                    k: 0,
+                });
+                BPF_IND
+            },
+            AddressFamily::V6 => BPF_ABS,
+        };
+
+        if let Some(message_type) = self.message_type {
+            compare(&mut program, Instruction::load(BPF_B, mode, 0), u32::from(message_type));
+        }
+        if let Some(code) = self.code {
+            compare(&mut program, Instruction::load(BPF_B, mode, 1), u32::from(code));
+        }
+        if let Some(identifier) = self.identifier {
+            compare(&mut program, Instruction::load(BPF_H, mode, 4), u32::from(identifier));
+        }
+        match (self.family, self.source) {
+            (_, None) => {},
+            (AddressFamily::V4, Some(IpAddr::V4(source))) => {
+                compare(&mut program, Instruction::load(BPF_W, BPF_ABS, IPV4_SOURCE), u32::from(source));
+            },
+            (AddressFamily::V6, Some(IpAddr::V6(source))) => {
+                for (i, chunk) in source.octets().chunks(4).enumerate() {
+                    let offset = (SKF_NET_OFF + IPV6_SOURCE + 4 * i as i32) as u32;
+                    let word = u32::from_be_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+                    compare(&mut program, Instruction::load(BPF_W, BPF_ABS, offset), word);
+                }
+            },
+            _ => return Err(Error::new(ErrorKind::InvalidInput, "source address of the wrong family")),
+        }
+
+        program.push(Instruction::ret(u32::MAX));
+        let reject = program.len();
+        program.push(Instruction::ret(0));
+        for i in conditions {
+            program[i].jf = (reject - i - 1) as u8;
+        }
+
+        Ok(BpfProgram::from_instructions(program))
+    }
+}
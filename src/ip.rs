@@ -0,0 +1,401 @@
+//! IP headers delivered by raw sockets and quoted by ICMP error messages
+
+use std::io::{Error, ErrorKind, Result};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+use crate::checksum::Checksum;
+
+/// Length of the IPv4 header without options.
+pub const IPV4_MIN_HEADER_LEN: usize = 20;
+
+/// Length of the IPv4 header with the most options.
+pub const IPV4_MAX_HEADER_LEN: usize = 60;
+
+const IPPROTO_ICMP: u8 = 1;
+const DEFAULT_TTL: u8 = 64;
+
+/// Length of the fixed IPv6 header.
+pub const IPV6_HEADER_LEN: usize = 40;
+
+/// An IPv4 header (RFC 791).
+///
+/// Raw ICMPv4 sockets deliver it in front of every received ICMP message; see
+/// `IcmpSocket::recv_packet`. In header-included mode, they send it in front of every
+/// message; see [`Ipv4HeaderBuilder`][builder] and `IcmpSocket::set_header_included`.
+///
+/// [builder]: struct.Ipv4HeaderBuilder.html
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ipv4Header {
+    /// Type of service, DSCP and ECN bits
+    pub tos: u8,
+    /// Length of the whole datagram, header included
+    pub total_len: u16,
+    /// Identification, used for fragment reassembly
+    pub identification: u16,
+    /// Don't Fragment flag
+    pub dont_fragment: bool,
+    /// More Fragments flag
+    pub more_fragments: bool,
+    /// Fragment offset in 8-byte units
+    pub fragment_offset: u16,
+    /// Time to live
+    pub ttl: u8,
+    /// Protocol of the payload, `1` for ICMP
+    pub protocol: u8,
+    /// Header checksum
+    pub checksum: u16,
+    /// Source address
+    pub source: Ipv4Addr,
+    /// Destination address
+    pub destination: Ipv4Addr,
+    /// Raw options, padded to a multiple of four bytes
+    pub options: Vec<u8>,
+}
+
+impl Ipv4Header {
+    /// Returns the length of this header in bytes, options included.
+    pub fn header_len(&self) -> usize {
+        IPV4_MIN_HEADER_LEN + self.options.len()
+    }
+
+    /// Returns the checksum this header should carry, computed over all other fields.
+    pub fn compute_checksum(&self) -> u16 {
+        let mut sum = Checksum::new();
+        sum.add_bytes(&[0x40 | (self.header_len() / 4) as u8, self.tos]);
+        sum.add_u16(self.total_len);
+        sum.add_u16(self.identification);
+        sum.add_u16(self.fragment());
+        sum.add_bytes(&[self.ttl, self.protocol]);
+        sum.add_bytes(&self.source.octets());
+        sum.add_bytes(&self.destination.octets());
+        sum.add_bytes(&self.options);
+        sum.finish()
+    }
+
+    /// Returns `true` if the checksum of this header is correct.
+    pub fn verify_checksum(&self) -> bool {
+        self.checksum == self.compute_checksum()
+    }
+
+    fn fragment(&self) -> u16 {
+        let mut fragment = self.fragment_offset & 0x1fff;
+        if self.dont_fragment {
+            fragment |= 0x4000;
+        }
+        if self.more_fragments {
+            fragment |= 0x2000;
+        }
+        fragment
+    }
+
+    /// Returns the number of bytes [`encode`][encode] writes.
+    ///
+    /// [encode]: #method.encode
+    pub fn encoded_len(&self) -> usize {
+        self.header_len()
+    }
+
+    /// Encodes this header into `buf`, checksum as is, returning the number of bytes
+    /// written.
+    ///
+    /// Fails with `InvalidInput` if the options are not padded to a multiple of four
+    /// bytes or longer than 40 bytes, or if `buf` is too small.
+    pub fn encode(&self, buf: &mut [u8]) -> Result<usize> {
+        let len = self.header_len();
+        if self.options.len() % 4 != 0 || len > IPV4_MAX_HEADER_LEN {
+            return Err(Error::new(ErrorKind::InvalidInput, "invalid IPv4 options length"));
+        }
+        if buf.len() < len {
+            return Err(Error::new(ErrorKind::InvalidInput, "buffer too small for IPv4 header"));
+        }
+
+        buf[0] = 0x40 | (len / 4) as u8;
+        buf[1] = self.tos;
+        buf[2..4].copy_from_slice(&self.total_len.to_be_bytes());
+        buf[4..6].copy_from_slice(&self.identification.to_be_bytes());
+        buf[6..8].copy_from_slice(&self.fragment().to_be_bytes());
+        buf[8] = self.ttl;
+        buf[9] = self.protocol;
+        buf[10..12].copy_from_slice(&self.checksum.to_be_bytes());
+        buf[12..16].copy_from_slice(&self.source.octets());
+        buf[16..20].copy_from_slice(&self.destination.octets());
+        buf[IPV4_MIN_HEADER_LEN..len].copy_from_slice(&self.options);
+
+        Ok(len)
+    }
+
+    /// Encodes this header into a newly allocated buffer.
+    ///
+    /// Fails like [`encode`][encode] does for options that cannot be encoded.
+    ///
+    /// [encode]: #method.encode
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        let mut buf = vec![0; self.encoded_len()];
+        self.encode(&mut buf)?;
+        Ok(buf)
+    }
+
+    /// Decodes a header from the start of `buf`.
+    ///
+    /// Use [`header_len`][header_len] to find where the payload starts.
+    ///
+    /// [header_len]: #method.header_len
+    pub fn decode(buf: &[u8]) -> Result<Ipv4Header> {
+        if buf.len() < IPV4_MIN_HEADER_LEN {
+            return Err(Error::new(ErrorKind::InvalidData, "IPv4 header is too short"));
+        }
+        if buf[0] >> 4 != 4 {
+            return Err(Error::new(ErrorKind::InvalidData, "not an IPv4 header"));
+        }
+        let header_len = usize::from(buf[0] & 0x0f) * 4;
+        if header_len < IPV4_MIN_HEADER_LEN || buf.len() < header_len {
+            return Err(Error::new(ErrorKind::InvalidData, "invalid IPv4 header length"));
+        }
+
+        let fragment = u16::from_be_bytes([buf[6], buf[7]]);
+
+        Ok(Ipv4Header {
+            tos: buf[1],
+            total_len: u16::from_be_bytes([buf[2], buf[3]]),
+            identification: u16::from_be_bytes([buf[4], buf[5]]),
+            dont_fragment: fragment & 0x4000 != 0,
+            more_fragments: fragment & 0x2000 != 0,
+            fragment_offset: fragment & 0x1fff,
+            ttl: buf[8],
+            protocol: buf[9],
+            checksum: u16::from_be_bytes([buf[10], buf[11]]),
+            source: Ipv4Addr::new(buf[12], buf[13], buf[14], buf[15]),
+            destination: Ipv4Addr::new(buf[16], buf[17], buf[18], buf[19]),
+            options: buf[IPV4_MIN_HEADER_LEN..header_len].to_vec(),
+        })
+    }
+}
+
+/// Builds the [`Ipv4Header`][header] of a datagram to send in header-included mode.
+///
+/// The total length and the checksum are computed from the payload length; fragment
+/// fields are left clear.
+///
+/// ```rust
+/// use std::net::Ipv4Addr;
+/// use icmp::ip::Ipv4HeaderBuilder;
+/// use icmp::v4::Icmpv4Message;
+///
+/// let echo = Icmpv4Message::Echo {
+///     identifier: 0x1234,
+///     sequence: 1,
+///     payload: b"ping".to_vec(),
+/// };
+/// let datagram = Ipv4HeaderBuilder::new(Ipv4Addr::LOCALHOST, Ipv4Addr::LOCALHOST)
+///     .identification(0x4242)
+///     .dont_fragment(true)
+///     .datagram(&echo.to_bytes().unwrap())
+///     .unwrap();
+/// assert_eq!(datagram.len(), 20 + 12);
+/// ```
+///
+/// [header]: struct.Ipv4Header.html
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ipv4HeaderBuilder {
+    tos: u8,
+    identification: u16,
+    dont_fragment: bool,
+    ttl: u8,
+    protocol: u8,
+    source: Ipv4Addr,
+    destination: Ipv4Addr,
+    options: Vec<u8>,
+}
+
+impl Ipv4HeaderBuilder {
+    /// Starts a header for an ICMP datagram from `source` to `destination`, with a TTL
+    /// of 64 and no options.
+    pub fn new(source: Ipv4Addr, destination: Ipv4Addr) -> Ipv4HeaderBuilder {
+        Ipv4HeaderBuilder {
+            tos: 0,
+            identification: 0,
+            dont_fragment: false,
+            ttl: DEFAULT_TTL,
+            protocol: IPPROTO_ICMP,
+            source,
+            destination,
+            options: vec![],
+        }
+    }
+
+    /// Sets the type of service.
+    pub fn tos(mut self, tos: u8) -> Ipv4HeaderBuilder {
+        self.tos = tos;
+        self
+    }
+
+    /// Sets the identification. Linux picks one when it is left at 0.
+    pub fn identification(mut self, identification: u16) -> Ipv4HeaderBuilder {
+        self.identification = identification;
+        self
+    }
+
+    /// Sets the Don't Fragment flag.
+    pub fn dont_fragment(mut self, dont_fragment: bool) -> Ipv4HeaderBuilder {
+        self.dont_fragment = dont_fragment;
+        self
+    }
+
+    /// Sets the time to live.
+    pub fn ttl(mut self, ttl: u8) -> Ipv4HeaderBuilder {
+        self.ttl = ttl;
+        self
+    }
+
+    /// Sets the protocol of the payload.
+    pub fn protocol(mut self, protocol: u8) -> Ipv4HeaderBuilder {
+        self.protocol = protocol;
+        self
+    }
+
+    /// Sets the raw options, which must be padded to a multiple of four bytes.
+    pub fn options(mut self, options: Vec<u8>) -> Ipv4HeaderBuilder {
+        self.options = options;
+        self
+    }
+
+    /// Returns the header of a datagram carrying `payload_len` bytes.
+    ///
+    /// Fails with `InvalidInput` if the options are not padded to a multiple of four
+    /// bytes or longer than 40 bytes, or if the datagram would be longer than 65535 bytes.
+    pub fn build(&self, payload_len: usize) -> Result<Ipv4Header> {
+        let header_len = IPV4_MIN_HEADER_LEN + self.options.len();
+        if self.options.len() % 4 != 0 || header_len > IPV4_MAX_HEADER_LEN {
+            return Err(Error::new(ErrorKind::InvalidInput, "invalid IPv4 options length"));
+        }
+        if header_len + payload_len > usize::from(u16::MAX) {
+            return Err(Error::new(ErrorKind::InvalidInput, "IPv4 datagram is too long"));
+        }
+
+        let mut header = Ipv4Header {
+            tos: self.tos,
+            total_len: (header_len + payload_len) as u16,
+            identification: self.identification,
+            dont_fragment: self.dont_fragment,
+            more_fragments: false,
+            fragment_offset: 0,
+            ttl: self.ttl,
+            protocol: self.protocol,
+            checksum: 0,
+            source: self.source,
+            destination: self.destination,
+            options: self.options.clone(),
+        };
+        header.checksum = header.compute_checksum();
+
+        Ok(header)
+    }
+
+    /// Returns a datagram carrying `payload`, ready to be sent in header-included mode.
+    ///
+    /// Fails like [`build`][build].
+    ///
+    /// [build]: #method.build
+    pub fn datagram(&self, payload: &[u8]) -> Result<Vec<u8>> {
+        let header = self.build(payload.len())?;
+        let mut datagram = header.to_bytes()?;
+        datagram.extend_from_slice(payload);
+
+        Ok(datagram)
+    }
+}
+
+/// A fixed IPv6 header (RFC 8200).
+///
+/// Raw ICMPv6 sockets never deliver it, but ICMPv6 error messages quote the header of
+/// the offending packet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ipv6Header {
+    /// Traffic class, DSCP and ECN bits
+    pub traffic_class: u8,
+    /// Flow label, 20 bits
+    pub flow_label: u32,
+    /// Length of the payload following this header, extension headers included
+    pub payload_len: u16,
+    /// Type of the header following this one
+    pub next_header: u8,
+    /// Hop limit
+    pub hop_limit: u8,
+    /// Source address
+    pub source: Ipv6Addr,
+    /// Destination address
+    pub destination: Ipv6Addr,
+}
+
+impl Ipv6Header {
+    /// Decodes a header from the start of `buf`.
+    pub fn decode(buf: &[u8]) -> Result<Ipv6Header> {
+        if buf.len() < IPV6_HEADER_LEN {
+            return Err(Error::new(ErrorKind::InvalidData, "IPv6 header is too short"));
+        }
+        if buf[0] >> 4 != 6 {
+            return Err(Error::new(ErrorKind::InvalidData, "not an IPv6 header"));
+        }
+
+        let mut source = [0u8; 16];
+        source.copy_from_slice(&buf[8..24]);
+        let mut destination = [0u8; 16];
+        destination.copy_from_slice(&buf[24..40]);
+
+        Ok(Ipv6Header {
+            traffic_class: (buf[0] << 4) | (buf[1] >> 4),
+            flow_label: u32::from_be_bytes([0, buf[1] & 0x0f, buf[2], buf[3]]),
+            payload_len: u16::from_be_bytes([buf[4], buf[5]]),
+            next_header: buf[6],
+            hop_limit: buf[7],
+            source: Ipv6Addr::from(source),
+            destination: Ipv6Addr::from(destination),
+        })
+    }
+}
+
+/// Either an IPv4 or an IPv6 header.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IpHeader {
+    /// IPv4 header
+    V4(Ipv4Header),
+    /// IPv6 header
+    V6(Ipv6Header),
+}
+
+impl IpHeader {
+    /// Decodes a header from the start of `buf`, picking the version from its first nibble.
+    pub fn decode(buf: &[u8]) -> Result<IpHeader> {
+        match buf.first().map(|byte| byte >> 4) {
+            Some(4) => Ipv4Header::decode(buf).map(IpHeader::V4),
+            Some(6) => Ipv6Header::decode(buf).map(IpHeader::V6),
+            _ => Err(Error::new(ErrorKind::InvalidData, "unknown IP version")),
+        }
+    }
+
+    /// Returns the source address.
+    pub fn source(&self) -> IpAddr {
+        match *self {
+            IpHeader::V4(ref header) => IpAddr::V4(header.source),
+            IpHeader::V6(ref header) => IpAddr::V6(header.source),
+        }
+    }
+
+    /// Returns the destination address.
+    pub fn destination(&self) -> IpAddr {
+        match *self {
+            IpHeader::V4(ref header) => IpAddr::V4(header.destination),
+            IpHeader::V6(ref header) => IpAddr::V6(header.destination),
+        }
+    }
+
+    /// Returns the length of this header in bytes.
+    ///
+    /// IPv6 extension headers are not included.
+    pub fn header_len(&self) -> usize {
+        match *self {
+            IpHeader::V4(ref header) => header.header_len(),
+            IpHeader::V6(..) => IPV6_HEADER_LEN,
+        }
+    }
+}
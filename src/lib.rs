@@ -2,8 +2,26 @@
 
 #![deny(missing_docs)]
 
+#[macro_use]
+mod macros;
+
+#[cfg(all(unix, feature = "async-io"))]
+mod async_io_socket;
+pub mod checksum;
 mod compat;
+pub mod extension;
+pub mod filter;
+pub mod ip;
+pub mod mld;
+pub mod ndp;
+pub mod packet;
+pub mod probe;
+pub mod quote;
 mod socket;
+pub mod v4;
+pub mod v6;
+#[cfg(all(unix, feature = "tokio"))]
+mod tokio_socket;
 
 #[cfg(unix)]
 #[path = "sys/unix.rs"] mod sys;
@@ -11,7 +29,11 @@ mod socket;
 #[cfg(windows)]
 #[path = "sys/mod.rs"] mod sys;
 
-pub use socket::IcmpSocket;
+pub use socket::{AddressFamily, ErrorOrigin, ExtendedError, IcmpSocket, RecvMeta, SendMeta, SocketKind};
+#[cfg(all(unix, feature = "async-io"))]
+pub use async_io_socket::AsyncIoIcmpSocket;
+#[cfg(all(unix, feature = "tokio"))]
+pub use tokio_socket::AsyncIcmpSocket;
 
 #[cfg(test)]
 mod tests;
@@ -0,0 +1,42 @@
+/// Declares an ICMP code enum with a catch-all variant and lossless `u8` conversions,
+/// so decoding a message with an unassigned code never fails.
+macro_rules! icmp_code {
+    (
+        $(#[$meta:meta])*
+        pub enum $name:ident {
+            $(
+                $(#[$vmeta:meta])*
+                $variant:ident = $value:expr,
+            )*
+        }
+    ) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        pub enum $name {
+            $(
+                $(#[$vmeta])*
+                $variant,
+            )*
+            /// Code value not assigned by the relevant RFCs.
+            Unassigned(u8),
+        }
+
+        impl From<u8> for $name {
+            fn from(code: u8) -> $name {
+                match code {
+                    $($value => $name::$variant,)*
+                    other => $name::Unassigned(other),
+                }
+            }
+        }
+
+        impl From<$name> for u8 {
+            fn from(code: $name) -> u8 {
+                match code {
+                    $($name::$variant => $value,)*
+                    $name::Unassigned(other) => other,
+                }
+            }
+        }
+    };
+}
@@ -0,0 +1,304 @@
+//! Multicast Listener Discovery (RFC 2710, RFC 3810)
+//!
+//! Routers send Queries to learn which multicast addresses have listeners on a link, and
+//! listeners answer with Reports. The messages themselves are modelled by
+//! [`Icmpv6Message`][v6]; this module holds their building blocks and helpers to query a
+//! link from an `IcmpSocket`.
+//!
+//! [v6]: ../v6/enum.Icmpv6Message.html
+
+use std::io::{Error, ErrorKind, Result};
+use std::net::{IpAddr, Ipv6Addr};
+use std::time::{Duration, Instant};
+
+use crate::IcmpSocket;
+#[cfg(target_os = "linux")]
+use crate::compat::AsInner;
+use crate::ndp::ipv6_addr;
+use crate::v6::Icmpv6Message;
+
+/// Hop-by-Hop Options header with the Router Alert option for MLD (RFC 2711), padded
+/// with PadN; the kernel fills in the next header field.
+#[cfg(target_os = "linux")]
+const ROUTER_ALERT: [u8; 8] = [0, 0, 0x05, 0x02, 0x00, 0x00, 0x01, 0x00];
+
+const RECORD_HEADER_LEN: usize = 20;
+const SUPPRESS_ROUTER_PROCESSING: u8 = 0x08;
+const DEFAULT_ROBUSTNESS: u8 = 2;
+const DEFAULT_QUERY_INTERVAL_CODE: u8 = 125;
+
+/// Link-local scope all-nodes address, which general queries are sent to.
+pub const ALL_NODES: Ipv6Addr = Ipv6Addr::new(0xff02, 0, 0, 0, 0, 0, 0, 1);
+
+/// Address MLDv2 listeners send their reports to.
+pub const ALL_MLDV2_ROUTERS: Ipv6Addr = Ipv6Addr::new(0xff02, 0, 0, 0, 0, 0, 0, 0x16);
+
+icmp_code! {
+    /// Types of multicast address records in MLDv2 reports.
+    pub enum RecordType {
+        /// Current state: listening to the sources in the record
+        ModeIsInclude = 1,
+        /// Current state: listening to all but the sources in the record
+        ModeIsExclude = 2,
+        /// Filter mode changed to include
+        ChangeToInclude = 3,
+        /// Filter mode changed to exclude
+        ChangeToExclude = 4,
+        /// Started listening to the sources in the record
+        AllowNewSources = 5,
+        /// Stopped listening to the sources in the record
+        BlockOldSources = 6,
+    }
+}
+
+/// Fields only present in MLDv2 queries.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Mldv2Query {
+    /// Suppress router-side processing, the S flag
+    pub suppress_router_processing: bool,
+    /// Querier's robustness variable, 3 bits
+    pub robustness: u8,
+    /// Querier's query interval code; see [`query_interval`][query_interval]
+    ///
+    /// [query_interval]: #method.query_interval
+    pub query_interval_code: u8,
+    /// Sources for a multicast address and source specific query
+    pub sources: Vec<Ipv6Addr>,
+}
+
+impl Mldv2Query {
+    /// Returns the query interval encoded by `query_interval_code`.
+    pub fn query_interval(&self) -> Duration {
+        let code = self.query_interval_code;
+        let seconds = match code {
+            0..=127 => u64::from(code),
+            _ => u64::from((code & 0x0f) | 0x10) << ((code >> 4 & 0x07) + 3),
+        };
+        Duration::from_secs(seconds)
+    }
+}
+
+/// A multicast address record of an MLDv2 report.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MulticastAddressRecord {
+    /// Kind of the record
+    pub record_type: RecordType,
+    /// Multicast address the record is about
+    pub multicast_address: Ipv6Addr,
+    /// Sources, interpreted according to `record_type`
+    pub sources: Vec<Ipv6Addr>,
+    /// Auxiliary data, a multiple of four bytes long
+    pub auxiliary_data: Vec<u8>,
+}
+
+impl MulticastAddressRecord {
+    fn encoded_len(&self) -> usize {
+        RECORD_HEADER_LEN + self.sources.len() * 16 + self.auxiliary_data.len()
+    }
+}
+
+/// Returns the maximum response delay encoded by an MLDv2 Maximum Response Code.
+///
+/// Codes below 32768 are milliseconds; larger ones are a floating point value (RFC 3810,
+/// section 5.1.3). For MLDv1 queries the field is always milliseconds.
+pub fn max_response_delay(code: u16) -> Duration {
+    let millis = match code {
+        0..=0x7fff => u64::from(code),
+        _ => u64::from((code & 0x0fff) | 0x1000) << ((code >> 12 & 0x07) + 3),
+    };
+    Duration::from_millis(millis)
+}
+
+/// Returns the MLDv2 Maximum Response Code closest to, but not above, `delay`.
+///
+/// Delays beyond the largest representable one, about 2 hours and 20 minutes, saturate.
+pub fn max_response_code(delay: Duration) -> u16 {
+    let millis = delay.as_secs().saturating_mul(1000).saturating_add(u64::from(delay.subsec_millis()));
+    if millis < 0x8000 {
+        return millis as u16;
+    }
+    for exp in 0..8u16 {
+        let mant = millis >> (exp + 3);
+        if mant < 0x2000 {
+            return 0x8000 | exp << 12 | (mant as u16 & 0x0fff);
+        }
+    }
+    0xffff
+}
+
+/// Returns the length of a query body following the ICMPv6 header.
+pub(crate) fn query_len(v2: Option<&Mldv2Query>) -> usize {
+    16 + v2.map_or(0, |query| 4 + query.sources.len() * 16)
+}
+
+/// Writes a query body into `buf`, which must be exactly `query_len` bytes long.
+pub(crate) fn encode_query(buf: &mut [u8], multicast_address: &Ipv6Addr, v2: Option<&Mldv2Query>) -> Result<()> {
+    buf[..16].copy_from_slice(&multicast_address.octets());
+    if let Some(query) = v2 {
+        if query.sources.len() > usize::from(u16::MAX) {
+            return Err(Error::new(ErrorKind::InvalidInput, "too many sources for MLD query"));
+        }
+        let mut flags = query.robustness & 0x07;
+        if query.suppress_router_processing {
+            flags |= SUPPRESS_ROUTER_PROCESSING;
+        }
+        buf[16] = flags;
+        buf[17] = query.query_interval_code;
+        buf[18..20].copy_from_slice(&(query.sources.len() as u16).to_be_bytes());
+        for (source, chunk) in query.sources.iter().zip(buf[20..].chunks_mut(16)) {
+            chunk.copy_from_slice(&source.octets());
+        }
+    }
+
+    Ok(())
+}
+
+/// Decodes a query body, telling MLDv1 and MLDv2 queries apart by their length.
+pub(crate) fn decode_query(buf: &[u8]) -> Result<(Ipv6Addr, Option<Mldv2Query>)> {
+    if buf.len() == 16 {
+        return Ok((ipv6_addr(buf), None));
+    }
+    if buf.len() < 20 {
+        return Err(Error::new(ErrorKind::InvalidData, "invalid MLD query length"));
+    }
+    let count = usize::from(u16::from_be_bytes([buf[18], buf[19]]));
+    if buf.len() - 20 < count * 16 {
+        return Err(Error::new(ErrorKind::InvalidData, "MLD query is too short for its sources"));
+    }
+
+    Ok((ipv6_addr(&buf[..16]), Some(Mldv2Query {
+        suppress_router_processing: buf[16] & SUPPRESS_ROUTER_PROCESSING != 0,
+        robustness: buf[16] & 0x07,
+        query_interval_code: buf[17],
+        sources: buf[20..20 + count * 16].chunks(16).map(ipv6_addr).collect(),
+    })))
+}
+
+/// Returns the length of the records of an MLDv2 report.
+pub(crate) fn records_len(records: &[MulticastAddressRecord]) -> usize {
+    records.iter().map(MulticastAddressRecord::encoded_len).sum()
+}
+
+/// Writes `records` into `buf`, which must be exactly `records_len` bytes long.
+pub(crate) fn encode_records(buf: &mut [u8], records: &[MulticastAddressRecord]) -> Result<()> {
+    let mut offset = 0;
+    for record in records {
+        let aux_len = record.auxiliary_data.len();
+        if aux_len % 4 != 0 || aux_len / 4 > usize::from(u8::MAX) {
+            return Err(Error::new(ErrorKind::InvalidInput, "invalid MLD auxiliary data length"));
+        }
+        if record.sources.len() > usize::from(u16::MAX) {
+            return Err(Error::new(ErrorKind::InvalidInput, "too many sources for MLD record"));
+        }
+        let buf = &mut buf[offset..offset + record.encoded_len()];
+        buf[0] = record.record_type.into();
+        buf[1] = (aux_len / 4) as u8;
+        buf[2..4].copy_from_slice(&(record.sources.len() as u16).to_be_bytes());
+        buf[4..20].copy_from_slice(&record.multicast_address.octets());
+        let (sources, auxiliary_data) = buf[RECORD_HEADER_LEN..].split_at_mut(record.sources.len() * 16);
+        for (source, chunk) in record.sources.iter().zip(sources.chunks_mut(16)) {
+            chunk.copy_from_slice(&source.octets());
+        }
+        auxiliary_data.copy_from_slice(&record.auxiliary_data);
+        offset += buf.len();
+    }
+
+    Ok(())
+}
+
+/// Decodes `count` records from the start of `buf`.
+pub(crate) fn decode_records(mut buf: &[u8], count: u16) -> Result<Vec<MulticastAddressRecord>> {
+    let truncated = || Error::new(ErrorKind::InvalidData, "MLD multicast address record is too short");
+
+    let mut records = Vec::with_capacity(usize::from(count));
+    for _ in 0..count {
+        if buf.len() < RECORD_HEADER_LEN {
+            return Err(truncated());
+        }
+        let aux_len = usize::from(buf[1]) * 4;
+        let sources = usize::from(u16::from_be_bytes([buf[2], buf[3]]));
+        let len = RECORD_HEADER_LEN + sources * 16 + aux_len;
+        if buf.len() < len {
+            return Err(truncated());
+        }
+        records.push(MulticastAddressRecord {
+            record_type: buf[0].into(),
+            multicast_address: ipv6_addr(&buf[4..20]),
+            sources: buf[RECORD_HEADER_LEN..len - aux_len].chunks(16).map(ipv6_addr).collect(),
+            auxiliary_data: buf[len - aux_len..len].to_vec(),
+        });
+        buf = &buf[len..];
+    }
+
+    Ok(records)
+}
+
+/// Sends an MLDv2 general query, asking all listeners on the link to report.
+///
+/// `socket` should be connected to [`ALL_NODES`][all_nodes], with the link chosen by
+/// `IcmpSocket::set_multicast_if_v6`. The query carries the Router Alert option listeners
+/// expect; other messages sent from `socket` are left without it.
+///
+/// [all_nodes]: constant.ALL_NODES.html
+#[cfg(target_os = "linux")]
+pub fn send_general_query(socket: &IcmpSocket, max_response_delay: Duration) -> Result<()> {
+    if socket.as_inner().family() != libc::AF_INET6 {
+        return Err(Error::new(ErrorKind::InvalidInput, "MLD requires an IPv6 socket"));
+    }
+
+    let query = Icmpv6Message::MulticastListenerQuery {
+        max_response_code: max_response_code(max_response_delay),
+        multicast_address: Ipv6Addr::UNSPECIFIED,
+        v2: Some(Mldv2Query {
+            suppress_router_processing: false,
+            robustness: DEFAULT_ROBUSTNESS,
+            query_interval_code: DEFAULT_QUERY_INTERVAL_CODE,
+            sources: vec![],
+        }),
+    };
+    socket.as_inner().send_hop_options(&query.to_bytes()?, ROUTER_ALERT)?;
+
+    Ok(())
+}
+
+/// Collects the listener reports received on `socket` until `window` elapses.
+///
+/// Returns MLDv1 and MLDv2 reports along with their senders; other messages are skipped.
+/// MLDv2 reports are only delivered once `socket` has joined
+/// [`ALL_MLDV2_ROUTERS`][all_mldv2_routers] on the link. The read timeout of `socket` is
+/// restored before returning.
+///
+/// [all_mldv2_routers]: constant.ALL_MLDV2_ROUTERS.html
+pub fn collect_reports(socket: &IcmpSocket, window: Duration) -> Result<Vec<(IpAddr, Icmpv6Message)>> {
+    let timeout = socket.read_timeout()?;
+    let result = collect(socket, window);
+    socket.set_read_timeout(timeout)?;
+    result
+}
+
+fn collect(socket: &IcmpSocket, window: Duration) -> Result<Vec<(IpAddr, Icmpv6Message)>> {
+    let deadline = Instant::now() + window;
+    let mut buf = [0u8; 1500];
+    let mut reports = Vec::new();
+
+    loop {
+        let now = Instant::now();
+        if now >= deadline {
+            return Ok(reports);
+        }
+        socket.set_read_timeout(Some(deadline - now))?;
+
+        let (size, source) = match socket.recv_from(&mut buf) {
+            Ok(received) => received,
+            Err(ref err) if err.kind() == ErrorKind::WouldBlock || err.kind() == ErrorKind::TimedOut => {
+                return Ok(reports);
+            },
+            Err(err) => return Err(err),
+        };
+        match Icmpv6Message::decode(&buf[..size]) {
+            Ok(message @ Icmpv6Message::MulticastListenerReport { .. })
+            | Ok(message @ Icmpv6Message::MulticastListenerReportV2 { .. }) => reports.push((source, message)),
+            _ => {},
+        }
+    }
+}
@@ -0,0 +1,428 @@
+//! Neighbor Discovery options (RFC 4861)
+//!
+//! Router and Neighbor Solicitations and Advertisements and Redirect messages are modelled
+//! by [`Icmpv6Message`][v6]. All of them end with a list of type-length-value options, whose
+//! length is given in units of 8 bytes.
+//!
+//! Receivers discard Neighbor Discovery messages with a hop limit other than 255, which is
+//! what the sending socket has to be configured with.
+//!
+//! [v6]: ../v6/enum.Icmpv6Message.html
+
+use std::io::{Error, ErrorKind, Result};
+use std::net::Ipv6Addr;
+
+const SOURCE_LINK_LAYER_ADDRESS: u8 = 1;
+const TARGET_LINK_LAYER_ADDRESS: u8 = 2;
+const PREFIX_INFORMATION: u8 = 3;
+const REDIRECTED_HEADER: u8 = 4;
+const MTU: u8 = 5;
+const ROUTE_INFORMATION: u8 = 24;
+const RECURSIVE_DNS_SERVER: u8 = 25;
+const DNS_SEARCH_LIST: u8 = 31;
+
+const OPTION_UNIT: usize = 8;
+const ETHERNET_ADDRESS_LEN: usize = 6;
+const MAX_LABEL_LEN: usize = 63;
+
+// Prefix Information flags
+const ON_LINK: u8 = 0x80;
+const AUTONOMOUS: u8 = 0x40;
+
+/// Default router or route preference (RFC 4191).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum RoutePreference {
+    /// High preference
+    High,
+    /// Medium preference, the default
+    #[default]
+    Medium,
+    /// Low preference
+    Low,
+    /// Reserved value, treated as medium by receivers
+    Reserved,
+}
+
+impl RoutePreference {
+    pub(crate) fn from_bits(bits: u8) -> RoutePreference {
+        match bits & 0x03 {
+            0 => RoutePreference::Medium,
+            1 => RoutePreference::High,
+            2 => RoutePreference::Reserved,
+            _ => RoutePreference::Low,
+        }
+    }
+
+    pub(crate) fn bits(self) -> u8 {
+        match self {
+            RoutePreference::Medium => 0,
+            RoutePreference::High => 1,
+            RoutePreference::Reserved => 2,
+            RoutePreference::Low => 3,
+        }
+    }
+}
+
+/// A Prefix Information option, announcing an on-link prefix or one for address
+/// autoconfiguration.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PrefixInformation {
+    /// Number of leading bits of `prefix` which are valid
+    pub prefix_len: u8,
+    /// Prefix can be used for on-link determination
+    pub on_link: bool,
+    /// Prefix can be used for stateless address autoconfiguration
+    pub autonomous: bool,
+    /// Seconds the prefix is valid for on-link determination, `u32::MAX` for infinity
+    pub valid_lifetime: u32,
+    /// Seconds addresses generated from the prefix remain preferred
+    pub preferred_lifetime: u32,
+    /// Prefix, bits beyond `prefix_len` are zero
+    pub prefix: Ipv6Addr,
+}
+
+/// A Route Information option (RFC 4191), announcing a more specific route.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RouteInformation {
+    /// Number of leading bits of `prefix` which are valid
+    pub prefix_len: u8,
+    /// Preference of the route
+    pub preference: RoutePreference,
+    /// Seconds the route is valid, `u32::MAX` for infinity
+    pub lifetime: u32,
+    /// Prefix, bits beyond `prefix_len` are zero
+    pub prefix: Ipv6Addr,
+}
+
+impl RouteInformation {
+    /// Returns the number of prefix bytes on the wire: 0, 8 or 16.
+    fn prefix_field_len(&self) -> usize {
+        match self.prefix_len {
+            0 => 0,
+            1..=64 => 8,
+            _ => 16,
+        }
+    }
+}
+
+/// A Neighbor Discovery option.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NdpOption {
+    /// Link-layer address of the sender
+    ///
+    /// Options one unit long decode to 6-byte Ethernet addresses (RFC 2464); longer ones
+    /// keep their zero padding.
+    SourceLinkLayerAddress(Vec<u8>),
+    /// Link-layer address of the target, with the same padding rules as the source one
+    TargetLinkLayerAddress(Vec<u8>),
+    /// On-link or autoconfiguration prefix
+    PrefixInformation(PrefixInformation),
+    /// Leading bytes of the packet which triggered a Redirect, padded to a multiple of 8 bytes
+    RedirectedHeader(Vec<u8>),
+    /// MTU of the link
+    Mtu(u32),
+    /// More specific route (RFC 4191)
+    RouteInformation(RouteInformation),
+    /// Recursive DNS servers (RFC 8106)
+    RecursiveDnsServers {
+        /// Seconds the servers may be used, `u32::MAX` for infinity
+        lifetime: u32,
+        /// Server addresses
+        servers: Vec<Ipv6Addr>,
+    },
+    /// DNS search list (RFC 8106)
+    DnsSearchList {
+        /// Seconds the domains may be used, `u32::MAX` for infinity
+        lifetime: u32,
+        /// Domain names, e.g. `example.com`
+        domains: Vec<String>,
+    },
+    /// Any other option, kept as-is
+    Unknown {
+        /// Option type
+        option_type: u8,
+        /// Option data following the type and length bytes
+        data: Vec<u8>,
+    },
+}
+
+impl NdpOption {
+    /// Returns the type of this option.
+    pub fn option_type(&self) -> u8 {
+        match *self {
+            NdpOption::SourceLinkLayerAddress(..) => SOURCE_LINK_LAYER_ADDRESS,
+            NdpOption::TargetLinkLayerAddress(..) => TARGET_LINK_LAYER_ADDRESS,
+            NdpOption::PrefixInformation(..) => PREFIX_INFORMATION,
+            NdpOption::RedirectedHeader(..) => REDIRECTED_HEADER,
+            NdpOption::Mtu(..) => MTU,
+            NdpOption::RouteInformation(..) => ROUTE_INFORMATION,
+            NdpOption::RecursiveDnsServers { .. } => RECURSIVE_DNS_SERVER,
+            NdpOption::DnsSearchList { .. } => DNS_SEARCH_LIST,
+            NdpOption::Unknown { option_type, .. } => option_type,
+        }
+    }
+
+    /// Returns the number of bytes [`encode`][encode] writes for this option.
+    ///
+    /// [encode]: #method.encode
+    pub fn encoded_len(&self) -> usize {
+        let data_len = match *self {
+            NdpOption::SourceLinkLayerAddress(ref address) | NdpOption::TargetLinkLayerAddress(ref address) => {
+                address.len()
+            },
+            NdpOption::PrefixInformation(..) => 30,
+            NdpOption::RedirectedHeader(ref packet) => 6 + packet.len(),
+            NdpOption::Mtu(..) => 6,
+            NdpOption::RouteInformation(ref route) => 6 + route.prefix_field_len(),
+            NdpOption::RecursiveDnsServers { ref servers, .. } => 6 + servers.len() * 16,
+            NdpOption::DnsSearchList { ref domains, .. } => {
+                6 + domains.iter().map(|domain| domain_len(domain)).sum::<usize>()
+            },
+            NdpOption::Unknown { ref data, .. } => data.len(),
+        };
+
+        (2 + data_len).div_ceil(OPTION_UNIT) * OPTION_UNIT
+    }
+
+    /// Encodes this option into `buf`, padding it with zeros to a multiple of 8 bytes.
+    ///
+    /// On success, returns the number of bytes written. Fails with `InvalidInput` for a
+    /// DNS server or search list option without any entry, which RFC 8106 does not allow.
+    pub fn encode(&self, buf: &mut [u8]) -> Result<usize> {
+        match *self {
+            NdpOption::RecursiveDnsServers { ref servers, .. } if servers.is_empty() => {
+                return Err(Error::new(ErrorKind::InvalidInput, "DNS server option without servers"));
+            },
+            NdpOption::DnsSearchList { ref domains, .. } if domains.is_empty() => {
+                return Err(Error::new(ErrorKind::InvalidInput, "DNS search list option without domains"));
+            },
+            _ => {},
+        }
+        let len = self.encoded_len();
+        if buf.len() < len {
+            return Err(Error::new(ErrorKind::InvalidInput, "buffer is too small for NDP option"));
+        }
+        if len / OPTION_UNIT > usize::from(u8::MAX) {
+            return Err(Error::new(ErrorKind::InvalidInput, "NDP option is too long"));
+        }
+        let buf = &mut buf[..len];
+        for byte in buf.iter_mut() {
+            *byte = 0;
+        }
+
+        buf[0] = self.option_type();
+        buf[1] = (len / OPTION_UNIT) as u8;
+        let data = &mut buf[2..];
+
+        match *self {
+            NdpOption::SourceLinkLayerAddress(ref address) | NdpOption::TargetLinkLayerAddress(ref address) => {
+                data[..address.len()].copy_from_slice(address);
+            },
+            NdpOption::PrefixInformation(ref prefix) => {
+                data[0] = prefix.prefix_len;
+                data[1] = if prefix.on_link { ON_LINK } else { 0 } | if prefix.autonomous { AUTONOMOUS } else { 0 };
+                data[2..6].copy_from_slice(&prefix.valid_lifetime.to_be_bytes());
+                data[6..10].copy_from_slice(&prefix.preferred_lifetime.to_be_bytes());
+                data[14..30].copy_from_slice(&prefix.prefix.octets());
+            },
+            NdpOption::RedirectedHeader(ref packet) => data[6..6 + packet.len()].copy_from_slice(packet),
+            NdpOption::Mtu(mtu) => data[2..6].copy_from_slice(&mtu.to_be_bytes()),
+            NdpOption::RouteInformation(ref route) => {
+                let prefix_len = route.prefix_field_len();
+                data[0] = route.prefix_len;
+                data[1] = route.preference.bits() << 3;
+                data[2..6].copy_from_slice(&route.lifetime.to_be_bytes());
+                data[6..6 + prefix_len].copy_from_slice(&route.prefix.octets()[..prefix_len]);
+            },
+            NdpOption::RecursiveDnsServers { lifetime, ref servers } => {
+                data[2..6].copy_from_slice(&lifetime.to_be_bytes());
+                for (server, chunk) in servers.iter().zip(data[6..].chunks_mut(16)) {
+                    chunk.copy_from_slice(&server.octets());
+                }
+            },
+            NdpOption::DnsSearchList { lifetime, ref domains } => {
+                data[2..6].copy_from_slice(&lifetime.to_be_bytes());
+                let mut offset = 6;
+                for domain in domains {
+                    offset += encode_domain(domain, &mut data[offset..])?;
+                }
+            },
+            NdpOption::Unknown { data: ref option, .. } => data[..option.len()].copy_from_slice(option),
+        }
+
+        Ok(len)
+    }
+
+    /// Decodes the option at the start of `buf`, returning it and its length in bytes.
+    pub fn decode(buf: &[u8]) -> Result<(NdpOption, usize)> {
+        if buf.len() < 2 {
+            return Err(Error::new(ErrorKind::InvalidData, "NDP option is too short"));
+        }
+        let len = usize::from(buf[1]) * OPTION_UNIT;
+        if len == 0 || len > buf.len() {
+            return Err(Error::new(ErrorKind::InvalidData, "invalid NDP option length"));
+        }
+        let data = &buf[2..len];
+        let invalid = || Error::new(ErrorKind::InvalidData, "invalid NDP option length");
+
+        let option = match buf[0] {
+            option_type @ SOURCE_LINK_LAYER_ADDRESS | option_type @ TARGET_LINK_LAYER_ADDRESS => {
+                let address = match len {
+                    OPTION_UNIT => data[..ETHERNET_ADDRESS_LEN].to_vec(),
+                    _ => data.to_vec(),
+                };
+                if option_type == SOURCE_LINK_LAYER_ADDRESS {
+                    NdpOption::SourceLinkLayerAddress(address)
+                } else {
+                    NdpOption::TargetLinkLayerAddress(address)
+                }
+            },
+            PREFIX_INFORMATION => {
+                if len != 32 {
+                    return Err(invalid());
+                }
+                NdpOption::PrefixInformation(PrefixInformation {
+                    prefix_len: data[0],
+                    on_link: data[1] & ON_LINK != 0,
+                    autonomous: data[1] & AUTONOMOUS != 0,
+                    valid_lifetime: u32::from_be_bytes([data[2], data[3], data[4], data[5]]),
+                    preferred_lifetime: u32::from_be_bytes([data[6], data[7], data[8], data[9]]),
+                    prefix: ipv6_addr(&data[14..30]),
+                })
+            },
+            REDIRECTED_HEADER => NdpOption::RedirectedHeader(data[6..].to_vec()),
+            MTU => {
+                if len != 8 {
+                    return Err(invalid());
+                }
+                NdpOption::Mtu(u32::from_be_bytes([data[2], data[3], data[4], data[5]]))
+            },
+            ROUTE_INFORMATION => {
+                let route = RouteInformation {
+                    prefix_len: data[0],
+                    preference: RoutePreference::from_bits(data[1] >> 3),
+                    lifetime: u32::from_be_bytes([data[2], data[3], data[4], data[5]]),
+                    prefix: Ipv6Addr::UNSPECIFIED,
+                };
+                let prefix_len = route.prefix_field_len();
+                if route.prefix_len > 128 || data.len() - 6 < prefix_len {
+                    return Err(invalid());
+                }
+                let mut octets = [0u8; 16];
+                octets[..prefix_len].copy_from_slice(&data[6..6 + prefix_len]);
+                NdpOption::RouteInformation(RouteInformation {
+                    prefix: Ipv6Addr::from(octets),
+                    ..route
+                })
+            },
+            RECURSIVE_DNS_SERVER => {
+                if len < 24 || (len - 8) % 16 != 0 {
+                    return Err(invalid());
+                }
+                NdpOption::RecursiveDnsServers {
+                    lifetime: u32::from_be_bytes([data[2], data[3], data[4], data[5]]),
+                    servers: data[6..].chunks(16).map(ipv6_addr).collect(),
+                }
+            },
+            DNS_SEARCH_LIST => {
+                if len < 16 {
+                    return Err(invalid());
+                }
+                NdpOption::DnsSearchList {
+                    lifetime: u32::from_be_bytes([data[2], data[3], data[4], data[5]]),
+                    domains: decode_domains(&data[6..])?,
+                }
+            },
+            option_type => NdpOption::Unknown {
+                option_type,
+                data: data.to_vec(),
+            },
+        };
+
+        Ok((option, len))
+    }
+}
+
+pub(crate) fn ipv6_addr(buf: &[u8]) -> Ipv6Addr {
+    let mut octets = [0u8; 16];
+    octets.copy_from_slice(buf);
+    Ipv6Addr::from(octets)
+}
+
+/// Returns the length of `domain` in DNS wire format, terminating zero included.
+fn domain_len(domain: &str) -> usize {
+    domain.split('.').filter(|label| !label.is_empty()).map(|label| 1 + label.len()).sum::<usize>() + 1
+}
+
+fn encode_domain(domain: &str, buf: &mut [u8]) -> Result<usize> {
+    let mut offset = 0;
+    for label in domain.split('.').filter(|label| !label.is_empty()) {
+        if label.len() > MAX_LABEL_LEN {
+            return Err(Error::new(ErrorKind::InvalidInput, "domain name label is too long"));
+        }
+        buf[offset] = label.len() as u8;
+        buf[offset + 1..offset + 1 + label.len()].copy_from_slice(label.as_bytes());
+        offset += 1 + label.len();
+    }
+    buf[offset] = 0;
+
+    Ok(offset + 1)
+}
+
+fn decode_domains(mut buf: &[u8]) -> Result<Vec<String>> {
+    let mut domains = Vec::new();
+    let mut labels: Vec<&str> = Vec::new();
+
+    while let Some((&len, rest)) = buf.split_first() {
+        let len = usize::from(len);
+        if len == 0 {
+            // Terminates a name, or pads the option when no name is pending.
+            if !labels.is_empty() {
+                domains.push(labels.join("."));
+                labels.clear();
+            }
+            buf = rest;
+            continue;
+        }
+        if len > MAX_LABEL_LEN || len > rest.len() {
+            return Err(Error::new(ErrorKind::InvalidData, "invalid domain name label"));
+        }
+        match std::str::from_utf8(&rest[..len]) {
+            Ok(label) => labels.push(label),
+            Err(..) => return Err(Error::new(ErrorKind::InvalidData, "domain name label is not UTF-8")),
+        }
+        buf = &rest[len..];
+    }
+    if !labels.is_empty() {
+        return Err(Error::new(ErrorKind::InvalidData, "unterminated domain name"));
+    }
+
+    Ok(domains)
+}
+
+/// Returns the number of bytes `options` occupy on the wire.
+pub(crate) fn options_len(options: &[NdpOption]) -> usize {
+    options.iter().map(NdpOption::encoded_len).sum()
+}
+
+/// Writes `options` into `buf`, which must be exactly `options_len` bytes long.
+pub(crate) fn encode_options(buf: &mut [u8], options: &[NdpOption]) -> Result<()> {
+    let mut offset = 0;
+    for option in options {
+        offset += option.encode(&mut buf[offset..])?;
+    }
+
+    Ok(())
+}
+
+/// Decodes the options filling `buf`.
+pub(crate) fn decode_options(mut buf: &[u8]) -> Result<Vec<NdpOption>> {
+    let mut options = Vec::new();
+    while !buf.is_empty() {
+        let (option, len) = NdpOption::decode(buf)?;
+        options.push(option);
+        buf = &buf[len..];
+    }
+
+    Ok(options)
+}
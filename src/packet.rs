@@ -0,0 +1,180 @@
+//! Borrowed views of ICMP and ICMPv6 messages
+//!
+//! [`IcmpPacket`][packet] reads the fixed header fields of a message in place, without
+//! decoding it into an owned [`Icmpv4Message`][v4] or [`Icmpv6Message`][v6]; those are
+//! decoded through the same view. [`IcmpPacketMut`][packet_mut] edits a message in place,
+//! e.g. to bump the sequence number of a prepared echo request before each send.
+//!
+//! ```rust
+//! use icmp::packet::{IcmpPacket, IcmpPacketMut};
+//! use icmp::v4::Icmpv4Message;
+//!
+//! let echo = Icmpv4Message::Echo {
+//!     identifier: 0x1234,
+//!     sequence: 1,
+//!     payload: b"ping".to_vec(),
+//! };
+//! let mut buf = echo.to_bytes().unwrap();
+//!
+//! let mut packet = IcmpPacketMut::new(&mut buf).unwrap();
+//! packet.rest_of_header_mut()[2..].copy_from_slice(&2u16.to_be_bytes());
+//! packet.fill_checksum();
+//!
+//! let packet = IcmpPacket::new(&buf).unwrap();
+//! assert_eq!(packet.message_type(), 8);
+//! assert_eq!(packet.rest_of_header(), [0x12, 0x34, 0x00, 0x02]);
+//! assert_eq!(packet.payload(), b"ping");
+//! assert!(packet.verify_checksum());
+//! ```
+//!
+//! [packet]: struct.IcmpPacket.html
+//! [packet_mut]: struct.IcmpPacketMut.html
+//! [v4]: ../v4/enum.Icmpv4Message.html
+//! [v6]: ../v6/enum.Icmpv6Message.html
+
+use std::io::{Error, ErrorKind, Result};
+use std::net::Ipv6Addr;
+
+use crate::checksum::{checksum, icmpv6_checksum};
+
+/// Length of the fixed header shared by ICMPv4 and ICMPv6: type, code, checksum and four
+/// bytes of rest-of-header.
+pub const HEADER_LEN: usize = 8;
+
+fn check_len(buf: &[u8]) -> Result<()> {
+    if buf.len() < HEADER_LEN {
+        return Err(Error::new(ErrorKind::InvalidData, "ICMP message is too short"));
+    }
+
+    Ok(())
+}
+
+/// A read-only view of an ICMP or ICMPv6 message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IcmpPacket<'a> {
+    buf: &'a [u8],
+}
+
+impl<'a> IcmpPacket<'a> {
+    /// Creates a view of the message filling `buf`.
+    ///
+    /// Fails with `InvalidData` if `buf` is shorter than the fixed header. The checksum is
+    /// not verified.
+    pub fn new(buf: &'a [u8]) -> Result<IcmpPacket<'a>> {
+        check_len(buf)?;
+
+        Ok(IcmpPacket {
+            buf,
+        })
+    }
+
+    /// Returns the message type.
+    pub fn message_type(&self) -> u8 {
+        self.buf[0]
+    }
+
+    /// Returns the message code.
+    pub fn code(&self) -> u8 {
+        self.buf[1]
+    }
+
+    /// Returns the checksum.
+    pub fn checksum(&self) -> u16 {
+        u16::from_be_bytes([self.buf[2], self.buf[3]])
+    }
+
+    /// Returns the four type-specific bytes following the checksum.
+    pub fn rest_of_header(&self) -> [u8; 4] {
+        [self.buf[4], self.buf[5], self.buf[6], self.buf[7]]
+    }
+
+    /// Returns the message body following the fixed header.
+    pub fn payload(&self) -> &'a [u8] {
+        &self.buf[HEADER_LEN..]
+    }
+
+    /// Returns the whole message.
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.buf
+    }
+
+    /// Returns `true` if the checksum of this ICMPv4 message is correct.
+    pub fn verify_checksum(&self) -> bool {
+        checksum(self.buf) == 0
+    }
+
+    /// Returns `true` if the checksum of this ICMPv6 message sent from `source` to
+    /// `destination` is correct.
+    pub fn verify_checksum_v6(&self, source: &Ipv6Addr, destination: &Ipv6Addr) -> bool {
+        icmpv6_checksum(source, destination, self.buf) == 0
+    }
+}
+
+/// A mutable view of an ICMP or ICMPv6 message.
+#[derive(Debug, PartialEq, Eq)]
+pub struct IcmpPacketMut<'a> {
+    buf: &'a mut [u8],
+}
+
+impl<'a> IcmpPacketMut<'a> {
+    /// Creates a view of the message filling `buf`.
+    ///
+    /// Fails with `InvalidData` if `buf` is shorter than the fixed header.
+    pub fn new(buf: &'a mut [u8]) -> Result<IcmpPacketMut<'a>> {
+        check_len(buf)?;
+
+        Ok(IcmpPacketMut {
+            buf,
+        })
+    }
+
+    /// Returns a read-only view of the message.
+    pub fn as_packet(&self) -> IcmpPacket<'_> {
+        IcmpPacket {
+            buf: self.buf,
+        }
+    }
+
+    /// Sets the message type.
+    pub fn set_message_type(&mut self, message_type: u8) {
+        self.buf[0] = message_type;
+    }
+
+    /// Sets the message code.
+    pub fn set_code(&mut self, code: u8) {
+        self.buf[1] = code;
+    }
+
+    /// Sets the checksum.
+    pub fn set_checksum(&mut self, checksum: u16) {
+        self.buf[2..4].copy_from_slice(&checksum.to_be_bytes());
+    }
+
+    /// Returns the four type-specific bytes following the checksum.
+    pub fn rest_of_header_mut(&mut self) -> &mut [u8] {
+        &mut self.buf[4..HEADER_LEN]
+    }
+
+    /// Returns the message body following the fixed header.
+    pub fn payload_mut(&mut self) -> &mut [u8] {
+        &mut self.buf[HEADER_LEN..]
+    }
+
+    /// Computes and stores the checksum of this ICMPv4 message.
+    pub fn fill_checksum(&mut self) {
+        self.set_checksum(0);
+        let sum = checksum(self.buf);
+        self.set_checksum(sum);
+    }
+
+    /// Computes and stores the checksum of this ICMPv6 message sent from `source` to
+    /// `destination`.
+    ///
+    /// Raw ICMPv6 sockets compute the checksum on send, so this is only needed when the
+    /// message is sent by other means.
+    pub fn fill_checksum_v6(&mut self, source: &Ipv6Addr, destination: &Ipv6Addr) {
+        self.set_checksum(0);
+        let sum = icmpv6_checksum(source, destination, self.buf);
+        self.set_checksum(sum);
+    }
+}
@@ -0,0 +1,151 @@
+//! Interface status probing with Extended Echo (RFC 8335)
+//!
+//! An Extended Echo request asks a node about the status of one of its interfaces, or of
+//! an interface of a directly connected neighbor, identified by name, ifIndex or address.
+//! Linux answers these requests when `net.ipv4.icmp_echo_enable_probe` is set.
+
+use std::io::{Error, ErrorKind, Result};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::{IcmpSocket, SocketKind};
+use crate::compat::AsInner;
+use crate::extension::{ExtensionObject, Extensions, InterfaceIdentifier};
+use crate::v4::Icmpv4Message;
+use crate::v6::Icmpv6Message;
+
+static SEQUENCE: AtomicUsize = AtomicUsize::new(0);
+
+icmp_code! {
+    /// Codes of the `Extended Echo Reply` message.
+    pub enum ExtendedEchoReplyCode {
+        /// No Error
+        NoError = 0,
+        /// Malformed Query
+        MalformedQuery = 1,
+        /// No Such Interface
+        NoSuchInterface = 2,
+        /// No Such Table Entry
+        NoSuchTableEntry = 3,
+        /// Multiple Interfaces Satisfy Query
+        MultipleInterfaces = 4,
+    }
+}
+
+/// Status of a probed interface, as reported by an Extended Echo Reply.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct InterfaceStatus {
+    /// Whether the query could be answered
+    pub code: ExtendedEchoReplyCode,
+    /// Neighbor cache state, for interfaces of a neighbor of the probed node
+    pub state: u8,
+    /// Interface is active
+    pub active: bool,
+    /// IPv4 is running on the interface
+    pub ipv4: bool,
+    /// IPv6 is running on the interface
+    pub ipv6: bool,
+}
+
+/// Probes the status of `interface` via the node `socket` is connected to.
+///
+/// `local` must be set when the interface belongs to the probed node itself, which is
+/// always the case for interfaces identified by name or index. Replies to other requests
+/// are skipped; set a read timeout on the socket to bound the wait. Datagram sockets only
+/// receive replies to their own requests, which carry the identifier of the kernel and
+/// are matched by sequence number alone.
+///
+/// ```rust,no_run
+/// use icmp::IcmpSocket;
+/// use icmp::extension::InterfaceIdentifier;
+/// use std::net::{IpAddr, Ipv4Addr};
+/// use std::time::Duration;
+///
+/// let socket = IcmpSocket::connect(IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1))).unwrap();
+/// socket.set_read_timeout(Some(Duration::from_secs(1))).unwrap();
+///
+/// let interface = InterfaceIdentifier::Name("eth0".to_string());
+/// let status = icmp::probe::probe(&socket, &interface, true).unwrap();
+/// println!("eth0 is {}", if status.active { "up" } else { "down" });
+/// ```
+pub fn probe(socket: &IcmpSocket, interface: &InterfaceIdentifier, local: bool) -> Result<InterfaceStatus> {
+    let identifier = process::id() as u16;
+    let sequence = SEQUENCE.fetch_add(1, Ordering::Relaxed) as u8;
+    let datagram = socket.kind() == SocketKind::Datagram;
+    let mut buf = [0u8; 1500];
+
+    if socket.as_inner().family() == libc::AF_INET {
+        let request = Icmpv4Message::ExtendedEchoRequest {
+            identifier,
+            sequence,
+            local,
+            interface: interface.clone(),
+        };
+        socket.send(&request.to_bytes()?)?;
+
+        loop {
+            let (size, _) = socket.recv_message(&mut buf)?;
+            let message = match Icmpv4Message::decode(&buf[..size]) {
+                Ok(message) => message,
+                Err(..) => continue,
+            };
+            if let Icmpv4Message::ExtendedEchoReply { status, identifier: id, sequence: seq, .. } = message {
+                if (datagram || id == identifier) && seq == sequence {
+                    return Ok(status);
+                }
+            }
+        }
+    } else {
+        let request = Icmpv6Message::ExtendedEchoRequest {
+            identifier,
+            sequence,
+            local,
+            interface: interface.clone(),
+        };
+        socket.send(&request.to_bytes()?)?;
+
+        loop {
+            let (size, _) = socket.recv_message(&mut buf)?;
+            let message = match Icmpv6Message::decode(&buf[..size]) {
+                Ok(message) => message,
+                Err(..) => continue,
+            };
+            if let Icmpv6Message::ExtendedEchoReply { status, identifier: id, sequence: seq, .. } = message {
+                if (datagram || id == identifier) && seq == sequence {
+                    return Ok(status);
+                }
+            }
+        }
+    }
+}
+
+/// Returns the extension structure carried by a request for `interface`.
+pub(crate) fn request_extensions(interface: &InterfaceIdentifier) -> Extensions {
+    Extensions {
+        objects: vec![ExtensionObject::InterfaceIdentification(interface.clone())],
+    }
+}
+
+/// Extracts the probed interface from the body of a request.
+pub(crate) fn decode_request(body: &[u8]) -> Result<InterfaceIdentifier> {
+    match Extensions::decode(body)?.interface_identification() {
+        Some(interface) => Ok(interface.clone()),
+        None => Err(Error::new(ErrorKind::InvalidData, "extended echo request without interface identification")),
+    }
+}
+
+/// Packs the state and A, 4, 6 bits into the last byte of the reply header.
+pub(crate) fn encode_status(status: &InterfaceStatus) -> u8 {
+    (status.state & 0x07) << 5 | u8::from(status.active) << 2 | u8::from(status.ipv4) << 1 | u8::from(status.ipv6)
+}
+
+/// Unpacks the reply code and the last byte of the reply header.
+pub(crate) fn decode_status(code: u8, byte: u8) -> InterfaceStatus {
+    InterfaceStatus {
+        code: code.into(),
+        state: byte >> 5,
+        active: byte & 0x04 != 0,
+        ipv4: byte & 0x02 != 0,
+        ipv6: byte & 0x01 != 0,
+    }
+}
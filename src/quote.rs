@@ -0,0 +1,152 @@
+//! Datagrams quoted by ICMP error messages
+//!
+//! Error messages carry the IP header of the offending packet plus at least the first
+//! eight bytes of its payload (RFC 792), or as much of the packet as fits into the minimum
+//! IPv6 MTU (RFC 4443). Eight bytes are enough to identify UDP ports, TCP ports and
+//! sequence numbers, or an ICMP echo identifier and sequence.
+
+use std::io::Result;
+
+use crate::ip::IpHeader;
+
+const IPPROTO_ICMP: u8 = 1;
+const IPPROTO_TCP: u8 = 6;
+const IPPROTO_UDP: u8 = 17;
+const IPPROTO_ICMPV6: u8 = 58;
+
+// IPv6 extension headers which may precede the transport header.
+const IPV6_HOP_BY_HOP: u8 = 0;
+const IPV6_ROUTING: u8 = 43;
+const IPV6_FRAGMENT: u8 = 44;
+const IPV6_DESTINATION: u8 = 60;
+
+const ICMP_ECHO: u8 = 8;
+const ICMPV6_ECHO_REQUEST: u8 = 128;
+
+/// Leading bytes of a quoted UDP header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct UdpHeader {
+    /// Source port
+    pub source_port: u16,
+    /// Destination port
+    pub destination_port: u16,
+    /// Length of the UDP datagram, header included
+    pub length: u16,
+    /// Checksum
+    pub checksum: u16,
+}
+
+/// Leading bytes of a quoted TCP header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TcpHeader {
+    /// Source port
+    pub source_port: u16,
+    /// Destination port
+    pub destination_port: u16,
+    /// Sequence number
+    pub sequence: u32,
+}
+
+/// Leading bytes of a quoted ICMP or ICMPv6 echo request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct EchoHeader {
+    /// Identifier
+    pub identifier: u16,
+    /// Sequence number
+    pub sequence: u16,
+}
+
+/// Transport header of a quoted datagram.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Transport {
+    /// UDP datagram
+    Udp(UdpHeader),
+    /// TCP segment
+    Tcp(TcpHeader),
+    /// ICMP or ICMPv6 echo request
+    Echo(EchoHeader),
+    /// Any other payload, a non-first fragment or a truncated transport header
+    Other {
+        /// Protocol or IPv6 next header value
+        protocol: u8,
+        /// Quoted bytes following the IP header
+        data: Vec<u8>,
+    },
+}
+
+/// A datagram quoted by an ICMP error message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QuotedDatagram {
+    /// IP header of the offending datagram
+    pub header: IpHeader,
+    /// Leading transport bytes of the offending datagram
+    pub transport: Transport,
+}
+
+impl QuotedDatagram {
+    /// Decodes the quoted datagram from an error message body.
+    pub fn decode(buf: &[u8]) -> Result<QuotedDatagram> {
+        let header = IpHeader::decode(buf)?;
+        let mut offset = header.header_len();
+
+        let (mut protocol, mut first_fragment) = match header {
+            IpHeader::V4(ref header) => (header.protocol, header.fragment_offset == 0),
+            IpHeader::V6(ref header) => (header.next_header, true),
+        };
+
+        if let IpHeader::V6(..) = header {
+            loop {
+                let rest = &buf[offset..];
+                match protocol {
+                    IPV6_HOP_BY_HOP | IPV6_ROUTING | IPV6_DESTINATION if rest.len() >= 2 => {
+                        protocol = rest[0];
+                        offset += (usize::from(rest[1]) + 1) * 8;
+                    },
+                    IPV6_FRAGMENT if rest.len() >= 8 => {
+                        protocol = rest[0];
+                        first_fragment = u16::from_be_bytes([rest[2], rest[3]]) >> 3 == 0;
+                        offset += 8;
+                    },
+                    _ => break,
+                }
+                if offset > buf.len() {
+                    offset = buf.len();
+                    break;
+                }
+            }
+        }
+
+        let data = &buf[offset..];
+        let transport = match protocol {
+            _ if !first_fragment || data.len() < 8 => None,
+            IPPROTO_UDP => Some(Transport::Udp(UdpHeader {
+                source_port: u16::from_be_bytes([data[0], data[1]]),
+                destination_port: u16::from_be_bytes([data[2], data[3]]),
+                length: u16::from_be_bytes([data[4], data[5]]),
+                checksum: u16::from_be_bytes([data[6], data[7]]),
+            })),
+            IPPROTO_TCP => Some(Transport::Tcp(TcpHeader {
+                source_port: u16::from_be_bytes([data[0], data[1]]),
+                destination_port: u16::from_be_bytes([data[2], data[3]]),
+                sequence: u32::from_be_bytes([data[4], data[5], data[6], data[7]]),
+            })),
+            IPPROTO_ICMP if data[0] == ICMP_ECHO => Some(Transport::Echo(EchoHeader {
+                identifier: u16::from_be_bytes([data[4], data[5]]),
+                sequence: u16::from_be_bytes([data[6], data[7]]),
+            })),
+            IPPROTO_ICMPV6 if data[0] == ICMPV6_ECHO_REQUEST => Some(Transport::Echo(EchoHeader {
+                identifier: u16::from_be_bytes([data[4], data[5]]),
+                sequence: u16::from_be_bytes([data[6], data[7]]),
+            })),
+            _ => None,
+        };
+
+        Ok(QuotedDatagram {
+            header,
+            transport: transport.unwrap_or_else(|| Transport::Other {
+                protocol,
+                data: data.to_vec(),
+            }),
+        })
+    }
+}
@@ -1,10 +1,148 @@
 
-use std::net::IpAddr;
-use std::io::{Result};
-use std::time::Duration;
+use std::net::{IpAddr, Ipv6Addr};
+use std::io::{self, Error, ErrorKind, Result};
+#[cfg(unix)]
+use std::os::unix::io::{AsFd, AsRawFd, BorrowedFd, RawFd};
+use std::time::{Duration, SystemTime};
+
+#[cfg(all(unix, feature = "mio"))]
+use mio::unix::SourceFd;
 
 use crate::compat::{AsInner, set_timeout, timeout};
+#[cfg(target_os = "linux")]
+use crate::filter::{BpfProgram, IcmpTypeFilter};
+use crate::ip::Ipv4Header;
 use crate::sys::Socket;
+use crate::v4::Icmpv4Message;
+
+/// Kind of the underlying socket.
+///
+/// Raw sockets need `CAP_NET_RAW` (or root) but send and receive any message. Datagram
+/// sockets, also known as ping sockets, are available to unprivileged users on Linux when
+/// their group is in `net.ipv4.ping_group_range`, but only send echo requests and only
+/// receive replies to them and errors caused by them. The kernel also manages the echo
+/// identifier of datagram sockets: it replaces the identifier of the requests sent, and
+/// replies and quoted requests carry its own one, so they are matched by sequence number
+/// there.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SocketKind {
+    /// A `SOCK_RAW` socket
+    Raw,
+    /// A `SOCK_DGRAM` ping socket
+    Datagram,
+    /// A raw socket if permitted, a datagram socket otherwise
+    Auto,
+}
+
+/// Ancillary data of a message received with
+/// [`IcmpSocket::recv_msg`][recv_msg].
+///
+/// [recv_msg]: struct.IcmpSocket.html#method.recv_msg
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct RecvMeta {
+    /// TTL or hop limit the message arrived with, if enabled with
+    /// [`set_recv_meta`][meta]
+    ///
+    /// [meta]: struct.IcmpSocket.html#method.set_recv_meta
+    pub ttl: Option<u8>,
+    /// Destination address of the message, which may be a broadcast or multicast
+    /// address, if enabled with [`set_recv_meta`][meta]
+    ///
+    /// [meta]: struct.IcmpSocket.html#method.set_recv_meta
+    pub destination: Option<IpAddr>,
+    /// Index of the interface the message arrived on, if enabled with
+    /// [`set_recv_meta`][meta]
+    ///
+    /// [meta]: struct.IcmpSocket.html#method.set_recv_meta
+    pub interface: Option<u32>,
+    /// When the kernel received the message, if timestamps are enabled with
+    /// [`set_timestamp_ns`][ns] or [`set_timestamping`][timestamping]
+    ///
+    /// [ns]: struct.IcmpSocket.html#method.set_timestamp_ns
+    /// [timestamping]: struct.IcmpSocket.html#method.set_timestamping
+    pub timestamp: Option<SystemTime>,
+}
+
+/// Per-message parameters of [`IcmpSocket::send_msg`][send_msg], overriding the socket
+/// options for a single message.
+///
+/// [send_msg]: struct.IcmpSocket.html#method.send_msg
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct SendMeta {
+    /// TTL or hop limit, instead of [`ttl`][ttl]
+    ///
+    /// [ttl]: struct.IcmpSocket.html#method.ttl
+    pub ttl: Option<u8>,
+    /// TOS or traffic class, instead of [`qos`][qos]
+    ///
+    /// [qos]: struct.IcmpSocket.html#method.qos
+    pub tos: Option<u8>,
+    /// Source address, which must be local to the host
+    pub source: Option<IpAddr>,
+    /// Index of the interface to send the message through
+    pub interface: Option<u32>,
+}
+
+icmp_code! {
+    /// Where an error of the socket error queue comes from (`SO_EE_ORIGIN_*`).
+    pub enum ErrorOrigin {
+        /// No origin
+        Unspecified = 0,
+        /// The local network stack, e.g. a message too large for the known path MTU
+        Local = 1,
+        /// An ICMP error message
+        Icmp = 2,
+        /// An ICMPv6 error message
+        Icmp6 = 3,
+        /// A transmit timestamp, see `IcmpSocket::recv_tx_timestamp`
+        TxStatus = 4,
+        /// A zero-copy completion
+        ZeroCopy = 5,
+        /// A packet dropped for missing its transmit time
+        TxTime = 6,
+    }
+}
+
+/// An error of the socket error queue, as reported in `struct sock_extended_err`,
+/// received with [`IcmpSocket::recv_error`][recv_error].
+///
+/// [recv_error]: struct.IcmpSocket.html#method.recv_error
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ExtendedError {
+    /// Error number, e.g. `EHOSTUNREACH` for an ICMP Time Exceeded message
+    pub errno: i32,
+    /// Where the error comes from
+    pub origin: ErrorOrigin,
+    /// Type of the ICMP or ICMPv6 error message
+    pub message_type: u8,
+    /// Code of the ICMP or ICMPv6 error message
+    pub code: u8,
+    /// Additional information, e.g. the next-hop MTU for Fragmentation Needed and
+    /// Packet Too Big
+    pub info: u32,
+    /// Other data, depending on the origin
+    pub data: u32,
+    /// Address of the node which sent the ICMP or ICMPv6 error message
+    pub offender: Option<IpAddr>,
+    /// Destination address of the message which caused the error
+    pub destination: Option<IpAddr>,
+}
+
+impl ExtendedError {
+    /// Returns the error number as an `io::Error`.
+    pub fn error(&self) -> io::Error {
+        io::Error::from_raw_os_error(self.errno)
+    }
+}
+
+/// Address family of an unconnected socket.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AddressFamily {
+    /// ICMP over IPv4
+    V4,
+    /// ICMPv6 over IPv6
+    V6,
+}
 
 /// An Internet Control Message Protocol socket.
 ///
@@ -36,23 +174,131 @@ use crate::sys::Socket;
 /// sudo setcap cap_net_raw+ep ./target/debug/PROJECT_NAME
 /// cargo run
 /// ```
-
-//
+///
+/// Alternatively, on Linux, [`connect_with_kind`][connect_with_kind] opens an unprivileged
+/// datagram socket for members of the groups in `net.ipv4.ping_group_range`.
+///
+/// A socket opened with [`new`][new] instead is not tied to a peer: messages are sent
+/// with [`send_to`][send_to], so one socket can serve many destinations, and
+/// [`recv_from`][recv_from] tells the responders apart.
+///
+/// [connect_with_kind]: #method.connect_with_kind
+/// [new]: #method.new
+/// [send_to]: #method.send_to
+/// [recv_from]: #method.recv_from
 pub struct IcmpSocket {
     inner: Socket,
 }
 
 impl IcmpSocket {
 
+    /// Opens an unconnected raw socket for `family`
+    pub fn new(family: AddressFamily) -> Result<IcmpSocket> {
+        IcmpSocket::new_with_kind(family, SocketKind::Raw)
+    }
+
+    /// Opens an unconnected socket of the given kind for `family`
+    ///
+    /// See [`connect_with_kind`][link] for `SocketKind::Auto`.
+    ///
+    /// [link]: #method.connect_with_kind
+    pub fn new_with_kind(family: AddressFamily, kind: SocketKind) -> Result<IcmpSocket> {
+        let inner = Socket::new(family, kind)?;
+
+        Ok(IcmpSocket {
+            inner,
+        })
+    }
+
     /// Connect socket to `addr`
     pub fn connect(addr: IpAddr) -> Result<IcmpSocket> {
-        let inner = Socket::connect(addr)?;
+        IcmpSocket::connect_with_kind(addr, SocketKind::Raw)
+    }
+
+    /// Connect a socket of the given kind to `addr`
+    ///
+    /// With `SocketKind::Auto`, a datagram socket is opened when opening a raw one fails
+    /// for lack of privileges.
+    pub fn connect_with_kind(addr: IpAddr, kind: SocketKind) -> Result<IcmpSocket> {
+        let inner = Socket::connect(addr, kind)?;
 
         Ok(IcmpSocket {
             inner,
         })
     }
 
+    /// Binds this socket to the local address `addr`.
+    ///
+    /// Messages are then sent from `addr`, and only those sent to `addr` are received.
+    pub fn bind(&self, addr: IpAddr) -> Result<()> {
+        self.inner.bind(addr)
+    }
+
+    /// Binds this socket to the network interface named `interface`, or removes the
+    /// binding with `None`, through the `SO_BINDTODEVICE` option.
+    ///
+    /// Messages are then only sent through and received from this interface, whatever
+    /// the routing table says. Binding to a VRF device makes the socket use the routing
+    /// table of that VRF. Changing the binding needs `CAP_NET_RAW` on most kernels.
+    #[cfg(target_os = "linux")]
+    pub fn bind_device(&self, interface: Option<&str>) -> Result<()> {
+        self.inner.bind_device(interface)
+    }
+
+    /// Gets the name of the interface this socket is bound to.
+    ///
+    /// For more information about this option, see [`bind_device`][link].
+    ///
+    /// [link]: #method.bind_device
+    #[cfg(target_os = "linux")]
+    pub fn device(&self) -> Result<Option<String>> {
+        self.inner.device()
+    }
+
+    /// Binds this socket to the network interface with index `interface`, or removes the
+    /// binding with 0, through the `SO_BINDTOIFINDEX` option (Linux 5.0 and later).
+    ///
+    /// This behaves like [`bind_device`][link]. As `IpAddr` cannot carry a scope, this is
+    /// also how an IPv6 link-local address is reached through a given interface, rather
+    /// than through whichever link the routing table picks.
+    ///
+    /// [link]: #method.bind_device
+    #[cfg(target_os = "linux")]
+    pub fn bind_device_index(&self, interface: u32) -> Result<()> {
+        self.inner.bind_device_index(interface)
+    }
+
+    /// Gets the index of the interface this socket is bound to, 0 if none.
+    ///
+    /// For more information about this option, see
+    /// [`bind_device_index`][link].
+    ///
+    /// [link]: #method.bind_device_index
+    #[cfg(target_os = "linux")]
+    pub fn device_index(&self) -> Result<u32> {
+        self.inner.device_index()
+    }
+
+    /// Returns the local address of this socket, unspecified until it is bound or has
+    /// sent a message.
+    pub fn local_addr(&self) -> Result<IpAddr> {
+        self.inner.local_addr()
+    }
+
+    /// Returns the address this socket is connected to.
+    ///
+    /// Fails with `NotConnected` for sockets opened with [`new`][link].
+    ///
+    /// [link]: #method.new
+    pub fn peer_addr(&self) -> Result<IpAddr> {
+        self.inner.peer_addr()
+    }
+
+    /// Returns the kind of this socket, never `SocketKind::Auto`.
+    pub fn kind(&self) -> SocketKind {
+        self.inner.kind()
+    }
+
     /// Receives data from the socket. On success, returns the number of bytes read.
     pub fn recv(&self, buf: &mut [u8]) -> Result<usize> {
         self.inner.recv(buf)
@@ -64,14 +310,121 @@ impl IcmpSocket {
         self.inner.recv_from(buf)
     }
 
+    /// Receives data from the socket along with its ancillary data. On success, returns
+    /// the number of bytes read, the address from whence the data came and what the
+    /// kernel told about its delivery.
+    ///
+    /// The data is the same as with [`recv_from`][recv_from]. The TTL or hop limit, the
+    /// destination and the interface are only known once enabled with
+    /// [`set_recv_meta`][recv_meta], and are `None` otherwise.
+    ///
+    /// [recv_from]: #method.recv_from
+    /// [recv_meta]: #method.set_recv_meta
+    #[cfg(target_os = "linux")]
+    pub fn recv_msg(&self, buf: &mut [u8]) -> Result<(usize, IpAddr, RecvMeta)> {
+        self.inner.recv_msg(buf)
+    }
+
+    /// Receives the transmit timestamp of a message sent on this socket. On success,
+    /// returns the number of the message and when the kernel handed it to the network
+    /// device.
+    ///
+    /// Timestamps are only taken once enabled with [`set_timestamping`][link], and
+    /// messages are numbered from 0 in the order they are sent from then on. Timestamps
+    /// are queued on the error queue of the socket; this call never blocks, it fails
+    /// with `WouldBlock` when none is queued yet, and with `InvalidData` if the next
+    /// entry of the error queue is not a timestamp.
+    ///
+    /// [link]: #method.set_timestamping
+    #[cfg(target_os = "linux")]
+    pub fn recv_tx_timestamp(&self) -> Result<(u32, SystemTime)> {
+        self.inner.recv_tx_timestamp()
+    }
+
+    /// Receives an error from the error queue of the socket. On success, returns the
+    /// number of bytes of the message which caused the error copied into `buf`, and the
+    /// error.
+    ///
+    /// Errors caused by messages sent on this socket are only queued once enabled with
+    /// [`set_error_queue`][link]; the message is then the ICMP or ICMPv6 message quoted
+    /// by the error, starting with its ICMP header. On datagram sockets, a quoted echo
+    /// request carries the identifier the kernel chose. This call never blocks: it fails
+    /// with `WouldBlock` when the error queue is empty.
+    ///
+    /// [link]: #method.set_error_queue
+    #[cfg(target_os = "linux")]
+    pub fn recv_error(&self, buf: &mut [u8]) -> Result<(usize, ExtendedError)> {
+        self.inner.recv_error(buf)
+    }
+
+    /// Receives an ICMP or ICMPv6 message from the socket. On success, returns the
+    /// number of bytes read and the address from whence the message came.
+    ///
+    /// Unlike [`recv_from`][recv_from], the IPv4 header raw IPv4 sockets deliver is
+    /// stripped, so `buf` starts with the ICMP header for every socket kind and family.
+    ///
+    /// [recv_from]: #method.recv_from
+    pub fn recv_message(&self, buf: &mut [u8]) -> Result<(usize, IpAddr)> {
+        let (size, source) = self.inner.recv_from(buf)?;
+        if size == 0 || self.inner.family() != libc::AF_INET || self.inner.kind() != SocketKind::Raw {
+            return Ok((size, source));
+        }
+
+        let header_len = usize::from(buf[0] & 0x0f) * 4;
+        if header_len > size {
+            return Err(Error::new(ErrorKind::InvalidData, "invalid IPv4 header length"));
+        }
+        buf.copy_within(header_len..size, 0);
+
+        Ok((size - header_len, source))
+    }
+
+    /// Receives an ICMPv4 message from the socket, along with the IPv4 header it was
+    /// delivered in.
+    ///
+    /// `buf` is used as scratch space and must be large enough for the whole datagram.
+    /// This method fails for IPv6 and datagram sockets, which never deliver the IP header.
+    pub fn recv_packet(&self, buf: &mut [u8]) -> Result<(Ipv4Header, Icmpv4Message)> {
+        if self.inner.family() != libc::AF_INET || self.inner.kind() != SocketKind::Raw {
+            return Err(Error::new(ErrorKind::InvalidInput, "recv_packet requires a raw IPv4 socket"));
+        }
+
+        let size = self.inner.recv(buf)?;
+        let header = Ipv4Header::decode(&buf[..size])?;
+        let message = Icmpv4Message::decode(&buf[header.header_len()..size])?;
+
+        Ok((header, message))
+    }
+
     /// Sends data on the socket to the remote address to which it is connected.
     ///
     /// The `connect` method will connect this socket to a remote address. This
     /// method will fail if the socket is not connected.
-    pub fn send(&mut self, buf: &[u8]) -> Result<usize> {
+    pub fn send(&self, buf: &[u8]) -> Result<usize> {
         self.inner.send(buf)
     }
 
+    /// Sends data on the socket to the given address. On success, returns the number
+    /// of bytes written.
+    ///
+    /// This works whether or not the socket is connected; `addr` must belong to the
+    /// family of the socket.
+    pub fn send_to(&self, buf: &[u8], addr: IpAddr) -> Result<usize> {
+        self.inner.send_to(buf, addr)
+    }
+
+    /// Sends data on the socket to the given address with per-message parameters. On
+    /// success, returns the number of bytes written.
+    ///
+    /// The parameters set in `meta` are passed as control messages (`IP_TTL`, `IP_TOS`
+    /// and `IP_PKTINFO`, or `IPV6_HOPLIMIT`, `IPV6_TCLASS` and `IPV6_PKTINFO`), so they
+    /// only apply to this message and the socket options stay as they are. This fails
+    /// with `InvalidInput` if the source address is not of the socket family.
+    #[cfg(target_os = "linux")]
+    pub fn send_msg(&self, buf: &[u8], addr: IpAddr, meta: &SendMeta) -> Result<usize> {
+        self.inner.send_msg(buf, addr, meta)
+    }
+
     /// Sets the read timeout to the timeout specified.
     ///
     /// If the value specified is `None`, then `read` calls will block
@@ -116,6 +469,17 @@ impl IcmpSocket {
         timeout(self.as_inner(), libc::SO_SNDTIMEO)
     }
 
+    /// Moves this socket into or out of nonblocking mode.
+    ///
+    /// In nonblocking mode, receiving and sending return an error of the kind
+    /// `WouldBlock` instead of waiting when no message is queued or the send buffer is
+    /// full; nothing is consumed or recorded by such a call, so it can simply be retried
+    /// once the socket is ready, e.g. after polling it with `mio` when the `mio` feature
+    /// is enabled.
+    pub fn set_nonblocking(&self, nonblocking: bool) -> Result<()> {
+        self.inner.set_nonblocking(nonblocking)
+    }
+
     /// Sets the value for the `IP_TTL` option on this socket.
     ///
     /// This value sets the time-to-live field that is used in every packet sent
@@ -133,6 +497,131 @@ impl IcmpSocket {
         self.inner.ttl()
     }
 
+    /// Sets the value of the `IP_RECVERR`/`IPV6_RECVERR` option for this socket.
+    ///
+    /// When enabled, ICMP and ICMPv6 errors caused by messages sent on this socket are
+    /// queued on its error queue, to be received with [`recv_error`][recv_error]. This
+    /// is the only way datagram sockets learn about them; raw sockets also receive the
+    /// error messages themselves.
+    ///
+    /// [recv_error]: #method.recv_error
+    #[cfg(target_os = "linux")]
+    pub fn set_error_queue(&self, enabled: bool) -> Result<()> {
+        self.inner.set_error_queue(enabled)
+    }
+
+    /// Gets the value of the `IP_RECVERR`/`IPV6_RECVERR` option for this socket.
+    ///
+    /// For more information about this option, see
+    /// [`set_error_queue`][link].
+    ///
+    /// [link]: #method.set_error_queue
+    #[cfg(target_os = "linux")]
+    pub fn error_queue(&self) -> Result<bool> {
+        self.inner.error_queue()
+    }
+
+    /// Enables or disables the ancillary data [`recv_msg`][recv_msg] reports, through the
+    /// `IP_RECVTTL` and `IP_PKTINFO`, or `IPV6_RECVHOPLIMIT` and `IPV6_RECVPKTINFO`,
+    /// options for this socket.
+    ///
+    /// When enabled, received messages carry their TTL or hop limit, their destination
+    /// address and the interface they arrived on. This is disabled by default, so that the
+    /// kernel does not build this data for sockets read with `recv` or `recv_from`.
+    ///
+    /// [recv_msg]: #method.recv_msg
+    #[cfg(target_os = "linux")]
+    pub fn set_recv_meta(&self, enabled: bool) -> Result<()> {
+        self.inner.set_recv_meta(enabled)
+    }
+
+    /// Returns `true` if the ancillary data [`recv_msg`][recv_msg] reports is enabled for
+    /// this socket.
+    ///
+    /// For more information about this option, see
+    /// [`set_recv_meta`][link].
+    ///
+    /// [recv_msg]: #method.recv_msg
+    /// [link]: #method.set_recv_meta
+    #[cfg(target_os = "linux")]
+    pub fn recv_meta(&self) -> Result<bool> {
+        self.inner.recv_meta()
+    }
+
+    /// Sets the value of the `SO_TIMESTAMPNS` option for this socket.
+    ///
+    /// When enabled, messages received with [`recv_msg`][recv_msg] carry the time the
+    /// kernel received them, in nanoseconds, unaffected by scheduling latency.
+    ///
+    /// [recv_msg]: #method.recv_msg
+    #[cfg(target_os = "linux")]
+    pub fn set_timestamp_ns(&self, enabled: bool) -> Result<()> {
+        self.inner.set_timestamp_ns(enabled)
+    }
+
+    /// Gets the value of the `SO_TIMESTAMPNS` option for this socket.
+    ///
+    /// For more information about this option, see
+    /// [`set_timestamp_ns`][link].
+    ///
+    /// [link]: #method.set_timestamp_ns
+    #[cfg(target_os = "linux")]
+    pub fn timestamp_ns(&self) -> Result<bool> {
+        self.inner.timestamp_ns()
+    }
+
+    /// Enables or disables software timestamps with the `SO_TIMESTAMPING` option for
+    /// this socket.
+    ///
+    /// When enabled, messages received with [`recv_msg`][recv_msg] carry the time the
+    /// kernel received them, and the time each sent message left for the network device
+    /// is reported through [`recv_tx_timestamp`][tx]. Both are taken by the same clock,
+    /// so their difference is a round-trip time free of scheduling latency.
+    ///
+    /// [recv_msg]: #method.recv_msg
+    /// [tx]: #method.recv_tx_timestamp
+    #[cfg(target_os = "linux")]
+    pub fn set_timestamping(&self, enabled: bool) -> Result<()> {
+        self.inner.set_timestamping(enabled)
+    }
+
+    /// Returns `true` if software timestamps are enabled with the `SO_TIMESTAMPING`
+    /// option for this socket.
+    ///
+    /// For more information about this option, see
+    /// [`set_timestamping`][link].
+    ///
+    /// [link]: #method.set_timestamping
+    #[cfg(target_os = "linux")]
+    pub fn timestamping(&self) -> Result<bool> {
+        self.inner.timestamping()
+    }
+
+    /// Sets the value of the `IP_HDRINCL` option for this socket.
+    ///
+    /// When enabled, every message sent on this raw IPv4 socket must start with its
+    /// own IPv4 header, e.g. one built with [`Ipv4HeaderBuilder`][builder]. The
+    /// destination passed to [`send_to`][send_to] still decides the route. Linux fills
+    /// in the checksum and total length, and the identification and source address when
+    /// they are zero. This fails for IPv6 sockets.
+    ///
+    /// [builder]: ip/struct.Ipv4HeaderBuilder.html
+    /// [send_to]: #method.send_to
+    pub fn set_header_included(&self, included: bool) -> Result<()> {
+        self.inner.set_header_included(included)
+    }
+
+    /// Gets the value of the `IP_HDRINCL` option for this socket, always `false` for
+    /// IPv6 sockets.
+    ///
+    /// For more information about this option, see
+    /// [`set_header_included`][link].
+    ///
+    /// [link]: #method.set_header_included
+    pub fn header_included(&self) -> Result<bool> {
+        self.inner.header_included()
+    }
+
     /// Sets the value of the SO_BROADCAST option for this socket.
     ///
     /// When enabled, this socket is allowed to send packets to a broadcast address.
@@ -168,6 +657,83 @@ impl IcmpSocket {
         self.inner.qos()
     }
 
+    /// Sets the value of the `ICMP_FILTER`/`ICMP6_FILTER` option for this socket.
+    ///
+    /// Only messages of the types passed by `filter` are then delivered to this raw
+    /// socket. `ICMP_FILTER` only covers the ICMPv4 types 0 to 31: on an IPv4 socket,
+    /// the types above 31 pass whatever `filter` says about them. Datagram sockets do not
+    /// support this option.
+    #[cfg(target_os = "linux")]
+    pub fn set_type_filter(&self, filter: &IcmpTypeFilter) -> Result<()> {
+        self.inner.set_type_filter(filter)
+    }
+
+    /// Gets the value of the `ICMP_FILTER`/`ICMP6_FILTER` option for this socket.
+    ///
+    /// For more information about this option, see
+    /// [`set_type_filter`][link].
+    ///
+    /// [link]: #method.set_type_filter
+    #[cfg(target_os = "linux")]
+    pub fn type_filter(&self) -> Result<IcmpTypeFilter> {
+        self.inner.type_filter()
+    }
+
+    /// Attaches a classic BPF program to this socket with the `SO_ATTACH_FILTER` option,
+    /// replacing any program attached before.
+    ///
+    /// Messages the program drops are never delivered to this socket. See
+    /// [`BpfBuilder`][link] for programs matching ICMP fields.
+    ///
+    /// [link]: filter/struct.BpfBuilder.html
+    #[cfg(target_os = "linux")]
+    pub fn attach_filter(&self, program: &BpfProgram) -> Result<()> {
+        self.inner.attach_filter(program)
+    }
+
+    /// Removes the BPF program attached to this socket.
+    #[cfg(target_os = "linux")]
+    pub fn detach_filter(&self) -> Result<()> {
+        self.inner.detach_filter()
+    }
+
+    /// Executes an operation of the `IPV6_ADD_MEMBERSHIP` type.
+    ///
+    /// This function specifies a new multicast group for this socket to join.
+    /// The address must be a valid multicast address, and `interface` is the
+    /// index of the interface to join/leave (or 0 to indicate any interface).
+    pub fn join_multicast_v6(&self, multiaddr: &Ipv6Addr, interface: u32) -> Result<()> {
+        self.inner.join_multicast_v6(multiaddr, interface)
+    }
+
+    /// Executes an operation of the `IPV6_DROP_MEMBERSHIP` type.
+    ///
+    /// For more information about this option, see
+    /// [`join_multicast_v6`][link].
+    ///
+    /// [link]: #method.join_multicast_v6
+    pub fn leave_multicast_v6(&self, multiaddr: &Ipv6Addr, interface: u32) -> Result<()> {
+        self.inner.leave_multicast_v6(multiaddr, interface)
+    }
+
+    /// Sets the value of the `IPV6_MULTICAST_IF` option for this socket.
+    ///
+    /// This value sets the index of the interface multicast packets are sent
+    /// from, 0 to let the routing table decide.
+    pub fn set_multicast_if_v6(&self, interface: u32) -> Result<()> {
+        self.inner.set_multicast_if_v6(interface)
+    }
+
+    /// Gets the value of the `IPV6_MULTICAST_IF` option for this socket.
+    ///
+    /// For more information about this option, see
+    /// [`set_multicast_if_v6`][link].
+    ///
+    /// [link]: #method.set_multicast_if_v6
+    pub fn multicast_if_v6(&self) -> Result<u32> {
+        self.inner.multicast_if_v6()
+    }
+
 }
 
 impl AsInner<Socket> for IcmpSocket {
@@ -175,3 +741,33 @@ impl AsInner<Socket> for IcmpSocket {
         &self.inner
     }
 }
+
+#[cfg(unix)]
+impl AsRawFd for IcmpSocket {
+    fn as_raw_fd(&self) -> RawFd {
+        *self.inner.as_inner()
+    }
+}
+
+#[cfg(unix)]
+impl AsFd for IcmpSocket {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        // The descriptor stays open for as long as the socket lives.
+        unsafe { BorrowedFd::borrow_raw(self.as_raw_fd()) }
+    }
+}
+
+#[cfg(all(unix, feature = "mio"))]
+impl mio::event::Source for IcmpSocket {
+    fn register(&mut self, registry: &mio::Registry, token: mio::Token, interests: mio::Interest) -> Result<()> {
+        SourceFd(&self.as_raw_fd()).register(registry, token, interests)
+    }
+
+    fn reregister(&mut self, registry: &mio::Registry, token: mio::Token, interests: mio::Interest) -> Result<()> {
+        SourceFd(&self.as_raw_fd()).reregister(registry, token, interests)
+    }
+
+    fn deregister(&mut self, registry: &mio::Registry) -> Result<()> {
+        SourceFd(&self.as_raw_fd()).deregister(registry)
+    }
+}
@@ -1,17 +1,52 @@
 
-use std::net::IpAddr;
-use std::io::{Result, ErrorKind};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::io::{Error, Result, ErrorKind};
 use std::mem;
+#[cfg(target_os = "linux")]
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+#[cfg(target_os = "linux")]
+use std::ptr;
 
-use crate::compat::{IntoInner, FromInner, AsInner, cvt, setsockopt, getsockopt};
+#[cfg(target_os = "linux")]
+use crate::filter::{BpfProgram, IcmpTypeFilter};
+use crate::compat::{IntoInner, AsInner, cvt, setsockopt, getsockopt, sockaddr_to_addr};
+#[cfg(target_os = "linux")]
+use crate::socket::{ErrorOrigin, ExtendedError, RecvMeta, SendMeta};
+use crate::socket::{AddressFamily, SocketKind};
 
 // Following constants are not defined in libc (as for 0.2.17 version)
 const IPPROTO_ICMP: libc::c_int = 1;
+const IPPROTO_ICMPV6: libc::c_int = 58;
 // Ipv4
 const IP_TOS: libc::c_int = 1;
+#[cfg(target_os = "linux")]
+const SOL_RAW: libc::c_int = 255;
+#[cfg(target_os = "linux")]
+const ICMP_FILTER: libc::c_int = 1;
 // Ipv6
 const IPV6_UNICAST_HOPS: libc::c_int = 16;
 const IPV6_TCLASS: libc::c_int = 67;
+#[cfg(target_os = "linux")]
+const ICMP6_FILTER: libc::c_int = 1;
+#[cfg(any(target_os = "linux", target_os = "android"))]
+use libc::{IPV6_ADD_MEMBERSHIP, IPV6_DROP_MEMBERSHIP};
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+use libc::{IPV6_JOIN_GROUP as IPV6_ADD_MEMBERSHIP, IPV6_LEAVE_GROUP as IPV6_DROP_MEMBERSHIP};
+// Socket
+#[cfg(target_os = "linux")]
+const SO_BINDTOIFINDEX: libc::c_int = 62;
+
+// Room for the ancillary data `recv_msg` asks for
+#[cfg(target_os = "linux")]
+const CONTROL_LEN: usize = 256;
+// Software receive and transmit timestamps, reported in `SCM_TIMESTAMPING`
+#[cfg(target_os = "linux")]
+const TIMESTAMPING_SOFTWARE: libc::c_uint = libc::SOF_TIMESTAMPING_RX_SOFTWARE
+    | libc::SOF_TIMESTAMPING_TX_SOFTWARE
+    | libc::SOF_TIMESTAMPING_SOFTWARE
+    // Number transmit timestamps instead of returning a copy of each packet
+    | libc::SOF_TIMESTAMPING_OPT_ID
+    | libc::SOF_TIMESTAMPING_OPT_TSONLY;
 
 #[cfg(target_os = "linux")]
 use libc::SOCK_CLOEXEC;
@@ -22,26 +57,198 @@ const SOCK_CLOEXEC: libc::c_int = 0;
 pub struct Socket {
     fd: libc::c_int,
     family: libc::c_int,
-    peer: libc::sockaddr,
+    kind: SocketKind,
+    peer: Option<SocketAddr>,
+}
+
+/// Buffer for ancillary data, aligned for `struct cmsghdr`
+#[cfg(target_os = "linux")]
+#[repr(C, align(8))]
+struct Control([u8; CONTROL_LEN]);
+
+/// Converts a timestamp of `CLOCK_REALTIME`, `None` if it is zero.
+#[cfg(target_os = "linux")]
+fn system_time(timestamp: libc::timespec) -> Option<SystemTime> {
+    if timestamp.tv_sec == 0 && timestamp.tv_nsec == 0 {
+        return None;
+    }
+    Some(UNIX_EPOCH + Duration::new(timestamp.tv_sec as u64, timestamp.tv_nsec as u32))
+}
+
+/// Ancillary data of a message received with `recvmsg`
+#[cfg(target_os = "linux")]
+struct Ancillary {
+    meta: RecvMeta,
+    // Extended error of a message from the error queue, and the address it names
+    error: Option<(libc::sock_extended_err, Option<IpAddr>)>,
+}
+
+/// Collects the ancillary data of a message received with `recvmsg`.
+#[cfg(target_os = "linux")]
+unsafe fn ancillary(msg: &libc::msghdr) -> Ancillary {
+    let mut meta = RecvMeta::default();
+    let mut error = None;
+    let mut cmsg = libc::CMSG_FIRSTHDR(msg);
+    while !cmsg.is_null() {
+        let data = libc::CMSG_DATA(cmsg);
+        match ((*cmsg).cmsg_level, (*cmsg).cmsg_type) {
+            (libc::IPPROTO_IP, libc::IP_TTL) | (libc::IPPROTO_IPV6, libc::IPV6_HOPLIMIT) => {
+                let ttl = ptr::read_unaligned(data as *const libc::c_int);
+                meta.ttl = Some(ttl as u8);
+            },
+            (libc::SOL_SOCKET, libc::SCM_TIMESTAMPNS) => {
+                meta.timestamp = system_time(ptr::read_unaligned(data as *const libc::timespec));
+            },
+            (libc::SOL_SOCKET, libc::SCM_TIMESTAMPING) => {
+                // Software, deprecated and hardware timestamps, only the first is asked for
+                meta.timestamp = system_time(ptr::read_unaligned(data as *const libc::timespec));
+            },
+            (libc::IPPROTO_IP, libc::IP_PKTINFO) => {
+                let info = ptr::read_unaligned(data as *const libc::in_pktinfo);
+                meta.destination = Some(IpAddr::V4(Ipv4Addr::from(u32::from_be(info.ipi_addr.s_addr))));
+                meta.interface = Some(info.ipi_ifindex as u32);
+            },
+            (libc::IPPROTO_IPV6, libc::IPV6_PKTINFO) => {
+                let info = ptr::read_unaligned(data as *const libc::in6_pktinfo);
+                meta.destination = Some(IpAddr::V6(Ipv6Addr::from(info.ipi6_addr.s6_addr)));
+                meta.interface = Some(info.ipi6_ifindex as u32);
+            },
+            (libc::IPPROTO_IP, libc::IP_RECVERR) | (libc::IPPROTO_IPV6, libc::IPV6_RECVERR) => {
+                let extended = ptr::read_unaligned(data as *const libc::sock_extended_err);
+                // The offender address follows, if the origin has one (`SO_EE_OFFENDER`)
+                let header = libc::CMSG_LEN(mem::size_of::<libc::sock_extended_err>() as libc::c_uint) as usize;
+                let len = ((*cmsg).cmsg_len as usize).saturating_sub(header);
+                let mut storage: libc::sockaddr_storage = mem::zeroed();
+                let len = len.min(mem::size_of_val(&storage));
+                ptr::copy_nonoverlapping(
+                    data.add(mem::size_of::<libc::sock_extended_err>()),
+                    &mut storage as *mut _ as *mut u8,
+                    len,
+                );
+                let offender = sockaddr_to_addr(&storage, len).ok().map(|addr| addr.ip());
+                error = Some((extended, offender));
+            },
+            _ => {},
+        }
+        cmsg = libc::CMSG_NXTHDR(msg, cmsg);
+    }
+
+    Ancillary {
+        meta,
+        error,
+    }
+}
+
+/// Appends a control message carrying `value` to `control`, returning the new length.
+#[cfg(target_os = "linux")]
+unsafe fn push_cmsg<T>(control: &mut Control, len: usize, level: libc::c_int, ty: libc::c_int, value: T) -> usize {
+    let size = mem::size_of::<T>() as libc::c_uint;
+    let cmsg = control.0.as_mut_ptr().add(len) as *mut libc::cmsghdr;
+    (*cmsg).cmsg_level = level;
+    (*cmsg).cmsg_type = ty;
+    (*cmsg).cmsg_len = libc::CMSG_LEN(size) as _;
+    ptr::write_unaligned(libc::CMSG_DATA(cmsg) as *mut T, value);
+    len + libc::CMSG_SPACE(size) as usize
+}
+
+fn socket(family: libc::c_int, ty: libc::c_int, protocol: libc::c_int) -> Result<libc::c_int> {
+    unsafe {
+        cvt(libc::socket(family, ty | SOCK_CLOEXEC, protocol))
+    }
 }
 
 impl Socket {
 
-    pub fn connect(addr: IpAddr) -> Result<Socket> {
-        let family = match addr {
-            IpAddr::V4(..) => libc::AF_INET,
-            IpAddr::V6(..) => libc::AF_INET6,
+    pub fn new(family: AddressFamily, kind: SocketKind) -> Result<Socket> {
+        let (family, protocol) = match family {
+            AddressFamily::V4 => (libc::AF_INET, IPPROTO_ICMP),
+            AddressFamily::V6 => (libc::AF_INET6, IPPROTO_ICMPV6),
+        };
+
+        let (fd, kind) = match kind {
+            SocketKind::Raw => (socket(family, libc::SOCK_RAW, protocol)?, SocketKind::Raw),
+            SocketKind::Datagram => (socket(family, libc::SOCK_DGRAM, protocol)?, SocketKind::Datagram),
+            SocketKind::Auto => match socket(family, libc::SOCK_RAW, protocol) {
+                Ok(fd) => (fd, SocketKind::Raw),
+                Err(ref err) if err.kind() == ErrorKind::PermissionDenied => {
+                    (socket(family, libc::SOCK_DGRAM, protocol)?, SocketKind::Datagram)
+                },
+                Err(err) => return Err(err),
+            },
         };
 
-        let fd = unsafe {
-            cvt(libc::socket(family, libc::SOCK_RAW | SOCK_CLOEXEC, IPPROTO_ICMP))?
+        let socket = Socket {
+            fd,
+            family,
+            kind,
+            peer: None,
         };
+        #[cfg(target_os = "linux")]
+        socket.enable_checksum()?;
 
-        Ok(Socket {
-            fd: fd,
-            family: family,
-            peer: addr.into_inner(),
-        })
+        Ok(socket)
+    }
+
+    /// Makes the kernel fill in the checksum of ICMPv6 messages sent on a raw socket, and
+    /// drop received ones whose checksum is wrong. RFC 3542 has the kernel do so for raw
+    /// ICMPv6 sockets anyway; Linux takes this option at the raw level, not the IPv6 one.
+    #[cfg(target_os = "linux")]
+    fn enable_checksum(&self) -> Result<()> {
+        if self.family != libc::AF_INET6 || self.kind != SocketKind::Raw {
+            return Ok(());
+        }
+        // Offset of the checksum in the ICMPv6 header
+        setsockopt(self, SOL_RAW, libc::IPV6_CHECKSUM, 2 as libc::c_int)
+    }
+
+    pub fn connect(addr: IpAddr, kind: SocketKind) -> Result<Socket> {
+        let family = match addr {
+            IpAddr::V4(..) => AddressFamily::V4,
+            IpAddr::V6(..) => AddressFamily::V6,
+        };
+
+        let mut socket = Socket::new(family, kind)?;
+        socket.peer = Some(SocketAddr::new(addr, 0));
+        Ok(socket)
+    }
+
+    pub fn bind(&self, addr: IpAddr) -> Result<()> {
+        let (addr, len) = SocketAddr::new(addr, 0).into_inner();
+        unsafe {
+            cvt(libc::bind(self.fd, &addr as *const _ as *const libc::sockaddr, len))?;
+        }
+        Ok(())
+    }
+
+    pub fn local_addr(&self) -> Result<IpAddr> {
+        let mut storage: libc::sockaddr_storage = unsafe { mem::zeroed() };
+        let mut len = mem::size_of_val(&storage) as libc::socklen_t;
+        unsafe {
+            cvt(libc::getsockname(self.fd, &mut storage as *mut _ as *mut libc::sockaddr, &mut len))?;
+        }
+        Ok(sockaddr_to_addr(&storage, len as usize)?.ip())
+    }
+
+    pub fn peer_addr(&self) -> Result<IpAddr> {
+        match self.peer {
+            Some(peer) => Ok(peer.ip()),
+            None => Err(Error::new(ErrorKind::NotConnected, "socket is not connected")),
+        }
+    }
+
+    fn unspecified(&self) -> IpAddr {
+        match self.family {
+            libc::AF_INET => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+            _ => IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+        }
+    }
+
+    pub fn family(&self) -> libc::c_int {
+        self.family
+    }
+
+    pub fn kind(&self) -> SocketKind {
+        self.kind
     }
 
     pub fn recv(&self, buf: &mut [u8]) -> Result<usize> {
@@ -62,35 +269,179 @@ impl Socket {
     }
 
     pub fn recv_from(&self, buf: &mut [u8]) -> Result<(usize, IpAddr)> {
-        let mut peer: libc::sockaddr = unsafe { mem::uninitialized() };
+        let mut storage: libc::sockaddr_storage = unsafe { mem::zeroed() };
+        let mut len = mem::size_of_val(&storage) as libc::socklen_t;
         let ret = unsafe {
             cvt(libc::recvfrom(
                     self.fd,
                     buf.as_mut_ptr() as *mut libc::c_void,
                     buf.len() as libc::size_t,
                     0,
-                    &mut peer,
-                    &mut (mem::size_of_val(&peer) as libc::socklen_t)
+                    &mut storage as *mut _ as *mut libc::sockaddr,
+                    &mut len,
                 )
             )
         };
 
         match ret {
-            Ok(size) => Ok((size as usize, IpAddr::from_inner(peer))),
-            Err(ref err) if err.kind() == ErrorKind::Interrupted => Ok((0, IpAddr::from_inner(peer))),
+            Ok(size) => Ok((size as usize, sockaddr_to_addr(&storage, len as usize)?.ip())),
+            Err(ref err) if err.kind() == ErrorKind::Interrupted => {
+                Ok((0, self.peer_addr().unwrap_or_else(|_| self.unspecified())))
+            },
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Receives a message along with its ancillary data, returns its length, the name it
+    /// came with and the length of that name.
+    #[cfg(target_os = "linux")]
+    fn recvmsg(&self, buf: &mut [u8], flags: libc::c_int) -> Result<(usize, libc::sockaddr_storage, usize, Ancillary)> {
+        let mut storage: libc::sockaddr_storage = unsafe { mem::zeroed() };
+        let mut control = Control([0; CONTROL_LEN]);
+        let mut iov = libc::iovec {
+            iov_base: buf.as_mut_ptr() as *mut libc::c_void,
+            iov_len: buf.len(),
+        };
+        let mut msg: libc::msghdr = unsafe { mem::zeroed() };
+        msg.msg_name = &mut storage as *mut _ as *mut libc::c_void;
+        msg.msg_namelen = mem::size_of_val(&storage) as libc::socklen_t;
+        msg.msg_iov = &mut iov;
+        msg.msg_iovlen = 1;
+        msg.msg_control = control.0.as_mut_ptr() as *mut libc::c_void;
+        msg.msg_controllen = CONTROL_LEN as _;
+
+        let size = unsafe {
+            cvt(libc::recvmsg(self.fd, &mut msg, flags))?
+        };
+
+        Ok((size as usize, storage, msg.msg_namelen as usize, unsafe { ancillary(&msg) }))
+    }
+
+    #[cfg(target_os = "linux")]
+    pub fn recv_msg(&self, buf: &mut [u8]) -> Result<(usize, IpAddr, RecvMeta)> {
+        match self.recvmsg(buf, 0) {
+            Ok((size, storage, len, ancillary)) => Ok((size, sockaddr_to_addr(&storage, len)?.ip(), ancillary.meta)),
+            Err(ref err) if err.kind() == ErrorKind::Interrupted => {
+                Ok((0, self.peer_addr().unwrap_or_else(|_| self.unspecified()), RecvMeta::default()))
+            },
             Err(err) => Err(err),
         }
     }
 
-    pub fn send(&mut self, buf: &[u8]) -> Result<usize> {
+    #[cfg(target_os = "linux")]
+    pub fn recv_tx_timestamp(&self) -> Result<(u32, SystemTime)> {
+        let (_, _, _, ancillary) = self.recvmsg(&mut [], libc::MSG_ERRQUEUE)?;
+        match (ancillary.error, ancillary.meta.timestamp) {
+            (Some((error, _)), Some(timestamp)) if error.ee_origin == libc::SO_EE_ORIGIN_TIMESTAMPING => {
+                Ok((error.ee_data, timestamp))
+            },
+            _ => Err(Error::new(ErrorKind::InvalidData, "no transmit timestamp in the error queue")),
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    pub fn recv_error(&self, buf: &mut [u8]) -> Result<(usize, ExtendedError)> {
+        let (size, storage, len, ancillary) = self.recvmsg(buf, libc::MSG_ERRQUEUE)?;
+        let (error, offender) = match ancillary.error {
+            Some(error) => error,
+            None => return Err(Error::new(ErrorKind::InvalidData, "no extended error in the error queue")),
+        };
+
+        Ok((size, ExtendedError {
+            errno: error.ee_errno as i32,
+            origin: ErrorOrigin::from(error.ee_origin),
+            message_type: error.ee_type,
+            code: error.ee_code,
+            info: error.ee_info,
+            data: error.ee_data,
+            offender,
+            destination: sockaddr_to_addr(&storage, len).ok().map(|addr| addr.ip()),
+        }))
+    }
+
+    #[cfg(target_os = "linux")]
+    pub fn set_error_queue(&self, enabled: bool) -> Result<()> {
+        match self.family {
+            libc::AF_INET => setsockopt(self, libc::IPPROTO_IP, libc::IP_RECVERR, enabled as libc::c_int),
+            libc::AF_INET6 => setsockopt(self, libc::IPPROTO_IPV6, libc::IPV6_RECVERR, enabled as libc::c_int),
+            _ => unreachable!(),
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    pub fn error_queue(&self) -> Result<bool> {
+        let raw: libc::c_int = match self.family {
+            libc::AF_INET => getsockopt(self, libc::IPPROTO_IP, libc::IP_RECVERR)?,
+            libc::AF_INET6 => getsockopt(self, libc::IPPROTO_IPV6, libc::IPV6_RECVERR)?,
+            _ => unreachable!(),
+        };
+        Ok(raw != 0)
+    }
+
+    #[cfg(target_os = "linux")]
+    pub fn set_recv_meta(&self, enabled: bool) -> Result<()> {
+        let enabled = enabled as libc::c_int;
+        match self.family {
+            libc::AF_INET => {
+                setsockopt(self, libc::IPPROTO_IP, libc::IP_RECVTTL, enabled)?;
+                setsockopt(self, libc::IPPROTO_IP, libc::IP_PKTINFO, enabled)
+            },
+            libc::AF_INET6 => {
+                setsockopt(self, libc::IPPROTO_IPV6, libc::IPV6_RECVHOPLIMIT, enabled)?;
+                setsockopt(self, libc::IPPROTO_IPV6, libc::IPV6_RECVPKTINFO, enabled)
+            },
+            _ => unreachable!(),
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    pub fn recv_meta(&self) -> Result<bool> {
+        let raw: libc::c_int = match self.family {
+            libc::AF_INET => getsockopt(self, libc::IPPROTO_IP, libc::IP_RECVTTL)?,
+            libc::AF_INET6 => getsockopt(self, libc::IPPROTO_IPV6, libc::IPV6_RECVHOPLIMIT)?,
+            _ => unreachable!(),
+        };
+        Ok(raw != 0)
+    }
+
+    #[cfg(target_os = "linux")]
+    pub fn set_timestamp_ns(&self, enabled: bool) -> Result<()> {
+        setsockopt(self, libc::SOL_SOCKET, libc::SO_TIMESTAMPNS, enabled as libc::c_int)
+    }
+
+    #[cfg(target_os = "linux")]
+    pub fn timestamp_ns(&self) -> Result<bool> {
+        let raw: libc::c_int = getsockopt(self, libc::SOL_SOCKET, libc::SO_TIMESTAMPNS)?;
+        Ok(raw != 0)
+    }
+
+    #[cfg(target_os = "linux")]
+    pub fn set_timestamping(&self, enabled: bool) -> Result<()> {
+        let flags = if enabled { TIMESTAMPING_SOFTWARE } else { 0 };
+        setsockopt(self, libc::SOL_SOCKET, libc::SO_TIMESTAMPING, flags as libc::c_int)
+    }
+
+    #[cfg(target_os = "linux")]
+    pub fn timestamping(&self) -> Result<bool> {
+        let raw: libc::c_int = getsockopt(self, libc::SOL_SOCKET, libc::SO_TIMESTAMPING)?;
+        Ok(raw as libc::c_uint & TIMESTAMPING_SOFTWARE == TIMESTAMPING_SOFTWARE)
+    }
+
+    pub fn send(&self, buf: &[u8]) -> Result<usize> {
+        let peer = self.peer_addr()?;
+        self.send_to(buf, peer)
+    }
+
+    pub fn send_to(&self, buf: &[u8], addr: IpAddr) -> Result<usize> {
+        let (peer, len) = SocketAddr::new(addr, 0).into_inner();
         let ret = unsafe {
             cvt(libc::sendto(
                     self.fd,
-                    buf.as_ptr() as *mut libc::c_void,
+                    buf.as_ptr() as *const libc::c_void,
                     buf.len() as libc::size_t,
                     0,
-                    &self.peer,
-                    mem::size_of_val(&self.peer) as libc::socklen_t,
+                    &peer as *const _ as *const libc::sockaddr,
+                    len,
                 )
             )?
         };
@@ -98,6 +449,171 @@ impl Socket {
         Ok(ret as usize)
     }
 
+    pub fn set_nonblocking(&self, nonblocking: bool) -> Result<()> {
+        let mut nonblocking = nonblocking as libc::c_int;
+        unsafe {
+            cvt(libc::ioctl(self.fd, libc::FIONBIO, &mut nonblocking))?;
+        }
+        Ok(())
+    }
+
+    #[cfg(target_os = "linux")]
+    pub fn bind_device(&self, interface: Option<&str>) -> Result<()> {
+        let name = interface.unwrap_or("").as_bytes();
+        if name.len() >= libc::IFNAMSIZ || name.contains(&0) {
+            return Err(Error::new(ErrorKind::InvalidInput, "invalid interface name"));
+        }
+
+        unsafe {
+            cvt(libc::setsockopt(
+                    self.fd,
+                    libc::SOL_SOCKET,
+                    libc::SO_BINDTODEVICE,
+                    name.as_ptr() as *const libc::c_void,
+                    name.len() as libc::socklen_t,
+                )
+            )?;
+        }
+        Ok(())
+    }
+
+    #[cfg(target_os = "linux")]
+    pub fn device(&self) -> Result<Option<String>> {
+        let mut name = [0u8; libc::IFNAMSIZ];
+        let mut len = name.len() as libc::socklen_t;
+        unsafe {
+            cvt(libc::getsockopt(
+                    self.fd,
+                    libc::SOL_SOCKET,
+                    libc::SO_BINDTODEVICE,
+                    name.as_mut_ptr() as *mut libc::c_void,
+                    &mut len,
+                )
+            )?;
+        }
+
+        let name = &name[..len as usize];
+        let name = match name.iter().position(|&byte| byte == 0) {
+            Some(end) => &name[..end],
+            None => name,
+        };
+        if name.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(String::from_utf8_lossy(name).into_owned()))
+    }
+
+    #[cfg(target_os = "linux")]
+    pub fn bind_device_index(&self, interface: u32) -> Result<()> {
+        setsockopt(self, libc::SOL_SOCKET, SO_BINDTOIFINDEX, interface as libc::c_int)
+    }
+
+    #[cfg(target_os = "linux")]
+    pub fn device_index(&self) -> Result<u32> {
+        let raw: libc::c_int = getsockopt(self, libc::SOL_SOCKET, SO_BINDTOIFINDEX)?;
+        Ok(raw as u32)
+    }
+
+    #[cfg(target_os = "linux")]
+    pub fn send_msg(&self, buf: &[u8], addr: IpAddr, meta: &SendMeta) -> Result<usize> {
+        let mut control = Control([0; CONTROL_LEN]);
+        let mut len = 0;
+        unsafe {
+            match (self.family, meta.source) {
+                (libc::AF_INET, None) | (libc::AF_INET, Some(IpAddr::V4(..))) => {
+                    if let Some(ttl) = meta.ttl {
+                        len = push_cmsg(&mut control, len, libc::IPPROTO_IP, libc::IP_TTL, libc::c_int::from(ttl));
+                    }
+                    if let Some(tos) = meta.tos {
+                        len = push_cmsg(&mut control, len, libc::IPPROTO_IP, IP_TOS, libc::c_int::from(tos));
+                    }
+                    if meta.source.is_some() || meta.interface.is_some() {
+                        let source = match meta.source {
+                            Some(IpAddr::V4(source)) => source,
+                            _ => Ipv4Addr::UNSPECIFIED,
+                        };
+                        let info = libc::in_pktinfo {
+                            ipi_ifindex: meta.interface.unwrap_or(0) as libc::c_int,
+                            ipi_spec_dst: libc::in_addr { s_addr: u32::from(source).to_be() },
+                            ipi_addr: libc::in_addr { s_addr: 0 },
+                        };
+                        len = push_cmsg(&mut control, len, libc::IPPROTO_IP, libc::IP_PKTINFO, info);
+                    }
+                },
+                (libc::AF_INET6, None) | (libc::AF_INET6, Some(IpAddr::V6(..))) => {
+                    if let Some(ttl) = meta.ttl {
+                        len = push_cmsg(&mut control, len, libc::IPPROTO_IPV6, libc::IPV6_HOPLIMIT, libc::c_int::from(ttl));
+                    }
+                    if let Some(tos) = meta.tos {
+                        len = push_cmsg(&mut control, len, libc::IPPROTO_IPV6, IPV6_TCLASS, libc::c_int::from(tos));
+                    }
+                    if meta.source.is_some() || meta.interface.is_some() {
+                        let source = match meta.source {
+                            Some(IpAddr::V6(source)) => source,
+                            _ => Ipv6Addr::UNSPECIFIED,
+                        };
+                        let info = libc::in6_pktinfo {
+                            ipi6_addr: libc::in6_addr { s6_addr: source.octets() },
+                            ipi6_ifindex: meta.interface.unwrap_or(0) as libc::c_uint,
+                        };
+                        len = push_cmsg(&mut control, len, libc::IPPROTO_IPV6, libc::IPV6_PKTINFO, info);
+                    }
+                },
+                _ => return Err(Error::new(ErrorKind::InvalidInput, "source address of the wrong family")),
+            }
+        }
+
+        let (mut peer, peer_len) = SocketAddr::new(addr, 0).into_inner();
+        let mut iov = libc::iovec {
+            iov_base: buf.as_ptr() as *mut libc::c_void,
+            iov_len: buf.len(),
+        };
+        let mut msg: libc::msghdr = unsafe { mem::zeroed() };
+        msg.msg_name = &mut peer as *mut _ as *mut libc::c_void;
+        msg.msg_namelen = peer_len;
+        msg.msg_iov = &mut iov;
+        msg.msg_iovlen = 1;
+        if len > 0 {
+            msg.msg_control = control.0.as_mut_ptr() as *mut libc::c_void;
+            msg.msg_controllen = len as _;
+        }
+
+        let ret = unsafe {
+            cvt(libc::sendmsg(self.fd, &msg, 0))?
+        };
+
+        Ok(ret as usize)
+    }
+
+    /// Sends `buf` to the peer with `options` as the Hop-by-Hop Options header of this
+    /// message only.
+    #[cfg(target_os = "linux")]
+    pub fn send_hop_options(&self, buf: &[u8], options: [u8; 8]) -> Result<usize> {
+        let mut control = Control([0; CONTROL_LEN]);
+        let len = unsafe {
+            push_cmsg(&mut control, 0, libc::IPPROTO_IPV6, libc::IPV6_HOPOPTS, options)
+        };
+
+        let (mut peer, peer_len) = SocketAddr::new(self.peer_addr()?, 0).into_inner();
+        let mut iov = libc::iovec {
+            iov_base: buf.as_ptr() as *mut libc::c_void,
+            iov_len: buf.len(),
+        };
+        let mut msg: libc::msghdr = unsafe { mem::zeroed() };
+        msg.msg_name = &mut peer as *mut _ as *mut libc::c_void;
+        msg.msg_namelen = peer_len;
+        msg.msg_iov = &mut iov;
+        msg.msg_iovlen = 1;
+        msg.msg_control = control.0.as_mut_ptr() as *mut libc::c_void;
+        msg.msg_controllen = len as _;
+
+        let ret = unsafe {
+            cvt(libc::sendmsg(self.fd, &msg, 0))?
+        };
+
+        Ok(ret as usize)
+    }
+
     pub fn set_ttl(&self, ttl: u32) -> Result<()> {
         match self.family {
             libc::AF_INET => setsockopt(self, libc::IPPROTO_IP, libc::IP_TTL, ttl as libc::c_int),
@@ -114,31 +630,113 @@ impl Socket {
         }
     }
 
+    pub fn set_header_included(&self, included: bool) -> Result<()> {
+        match self.family {
+            libc::AF_INET => setsockopt(self, libc::IPPROTO_IP, libc::IP_HDRINCL, included as libc::c_int),
+            _ => Err(Error::new(ErrorKind::InvalidInput, "IP_HDRINCL requires an IPv4 socket")),
+        }
+    }
+
+    pub fn header_included(&self) -> Result<bool> {
+        match self.family {
+            libc::AF_INET => {
+                let raw: libc::c_int = getsockopt(self, libc::IPPROTO_IP, libc::IP_HDRINCL)?;
+                Ok(raw != 0)
+            },
+            _ => Ok(false),
+        }
+    }
+
     pub fn set_broadcast(&self, broadcast: bool) -> Result<()> {
-        setsockopt(&self, libc::SOL_SOCKET, libc::SO_BROADCAST, broadcast as libc::c_int)
+        setsockopt(self, libc::SOL_SOCKET, libc::SO_BROADCAST, broadcast as libc::c_int)
     }
 
     pub fn broadcast(&self) -> Result<bool> {
-        let raw: libc::c_int = getsockopt(&self, libc::SOL_SOCKET, libc::SO_BROADCAST)?;
+        let raw: libc::c_int = getsockopt(self, libc::SOL_SOCKET, libc::SO_BROADCAST)?;
         Ok(raw != 0)
     }
 
     pub fn set_qos(&self, qos: u8) -> Result<()> {
         match self.family {
-            libc::AF_INET => setsockopt(&self, libc::IPPROTO_IP, IP_TOS, qos as libc::c_int),
-            libc::AF_INET6 => setsockopt(&self, libc::IPPROTO_IPV6, IPV6_TCLASS, qos as libc::c_int),
+            libc::AF_INET => setsockopt(self, libc::IPPROTO_IP, IP_TOS, qos as libc::c_int),
+            libc::AF_INET6 => setsockopt(self, libc::IPPROTO_IPV6, IPV6_TCLASS, qos as libc::c_int),
             _ => unreachable!(),
         }
     }
 
     pub fn qos(&self) -> Result<u8> {
         match self.family {
-            libc::AF_INET => getsockopt(&self, libc::IPPROTO_IP, IP_TOS),
-            libc::AF_INET6 => getsockopt(&self, libc::IPPROTO_IPV6, IPV6_TCLASS),
+            libc::AF_INET => getsockopt(self, libc::IPPROTO_IP, IP_TOS),
+            libc::AF_INET6 => getsockopt(self, libc::IPPROTO_IPV6, IPV6_TCLASS),
+            _ => unreachable!(),
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    pub fn set_type_filter(&self, filter: &IcmpTypeFilter) -> Result<()> {
+        match self.family {
+            libc::AF_INET => setsockopt(self, SOL_RAW, ICMP_FILTER, filter.to_icmp_filter()),
+            libc::AF_INET6 => setsockopt(self, libc::IPPROTO_ICMPV6, ICMP6_FILTER, filter.to_icmp6_filter()),
+            _ => unreachable!(),
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    pub fn type_filter(&self) -> Result<IcmpTypeFilter> {
+        match self.family {
+            libc::AF_INET => getsockopt(self, SOL_RAW, ICMP_FILTER).map(IcmpTypeFilter::from_icmp_filter),
+            libc::AF_INET6 => getsockopt(self, libc::IPPROTO_ICMPV6, ICMP6_FILTER).map(IcmpTypeFilter::from_icmp6_filter),
             _ => unreachable!(),
         }
     }
 
+    #[cfg(target_os = "linux")]
+    pub fn attach_filter(&self, program: &BpfProgram) -> Result<()> {
+        let mut filter: Vec<libc::sock_filter> = program.instructions().iter()
+            .map(|instruction| libc::sock_filter {
+                code: instruction.code,
+                jt: instruction.jt,
+                jf: instruction.jf,
+                k: instruction.k,
+            })
+            .collect();
+        let fprog = libc::sock_fprog {
+            len: filter.len() as libc::c_ushort,
+            filter: filter.as_mut_ptr(),
+        };
+        setsockopt(self, libc::SOL_SOCKET, libc::SO_ATTACH_FILTER, fprog)
+    }
+
+    #[cfg(target_os = "linux")]
+    pub fn detach_filter(&self) -> Result<()> {
+        setsockopt(self, libc::SOL_SOCKET, libc::SO_DETACH_FILTER, 0 as libc::c_int)
+    }
+
+    pub fn join_multicast_v6(&self, multiaddr: &Ipv6Addr, interface: u32) -> Result<()> {
+        let mreq = libc::ipv6_mreq {
+            ipv6mr_multiaddr: libc::in6_addr { s6_addr: multiaddr.octets() },
+            ipv6mr_interface: interface as libc::c_uint,
+        };
+        setsockopt(self, libc::IPPROTO_IPV6, IPV6_ADD_MEMBERSHIP, mreq)
+    }
+
+    pub fn leave_multicast_v6(&self, multiaddr: &Ipv6Addr, interface: u32) -> Result<()> {
+        let mreq = libc::ipv6_mreq {
+            ipv6mr_multiaddr: libc::in6_addr { s6_addr: multiaddr.octets() },
+            ipv6mr_interface: interface as libc::c_uint,
+        };
+        setsockopt(self, libc::IPPROTO_IPV6, IPV6_DROP_MEMBERSHIP, mreq)
+    }
+
+    pub fn set_multicast_if_v6(&self, interface: u32) -> Result<()> {
+        setsockopt(self, libc::IPPROTO_IPV6, libc::IPV6_MULTICAST_IF, interface as libc::c_int)
+    }
+
+    pub fn multicast_if_v6(&self) -> Result<u32> {
+        let raw: libc::c_int = getsockopt(self, libc::IPPROTO_IPV6, libc::IPV6_MULTICAST_IF)?;
+        Ok(raw as u32)
+    }
+
 }
 
 impl Drop for Socket {
@@ -1,7 +1,11 @@
-use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+#![allow(clippy::bool_assert_comparison)]
+
+use std::io::{self, Write};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6};
 use std::time::Duration;
 
 use crate::IcmpSocket;
+use crate::compat::{IntoInner, sockaddr_to_addr};
 
 macro_rules! t {
     ($e:expr) => {
@@ -12,6 +16,44 @@ macro_rules! t {
     }
 }
 
+#[cfg(feature = "async-io")]
+mod async_io_socket;
+mod checksum;
+#[cfg(target_os = "linux")]
+mod device;
+#[cfg(target_os = "linux")]
+mod error_queue;
+mod extension;
+#[cfg(target_os = "linux")]
+mod filter;
+mod ip;
+mod mld;
+mod ndp;
+#[cfg(target_os = "linux")]
+mod netns;
+mod nonblocking;
+mod packet;
+mod ping;
+mod probe;
+mod quote;
+#[cfg(target_os = "linux")]
+mod recv_msg;
+#[cfg(target_os = "linux")]
+mod send_msg;
+#[cfg(target_os = "linux")]
+mod timestamp;
+#[cfg(feature = "tokio")]
+mod tokio_socket;
+mod unconnected;
+mod v4;
+mod v6;
+
+/// Tells that `test` did not run, on stderr so that the output capture of the test
+/// harness does not hide it.
+fn skip(test: &str, reason: &str) {
+    let _ = writeln!(io::stderr(), "{} skipped: {}", test, reason);
+}
+
 fn ipv4() -> IpAddr {
     IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1))
 }
@@ -136,3 +178,21 @@ fn broadcast_v6() {
     t!(socket.set_broadcast(true));
     assert_eq!(true, t!(socket.broadcast()));
 }
+
+#[test]
+fn sockaddr_v4() {
+    let addr = SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::new(192, 0, 2, 1), 0));
+    let (storage, len) = addr.into_inner();
+
+    assert_eq!(t!(sockaddr_to_addr(&storage, len as usize)), addr);
+}
+
+#[test]
+fn sockaddr_v6() {
+    let ip = Ipv6Addr::new(0xfe80, 0, 0, 0, 0x1, 0x2, 0x3, 0x4);
+    let addr = SocketAddr::V6(SocketAddrV6::new(ip, 0, 0, 42));
+    let (storage, len) = addr.into_inner();
+
+    assert_eq!(len as usize, std::mem::size_of::<libc::sockaddr_in6>());
+    assert_eq!(t!(sockaddr_to_addr(&storage, len as usize)), addr);
+}
@@ -0,0 +1,115 @@
+use std::future::poll_fn;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::task::Poll;
+
+use crate::{AddressFamily, AsyncIoIcmpSocket, IcmpSocket, SocketKind};
+use crate::v4::Icmpv4Message;
+use crate::v6::Icmpv6Message;
+
+use async_io::block_on;
+
+use super::ping::{kernel_identifier, with_ping_sockets};
+
+/// Opens a datagram socket, which only receives replies to its own requests.
+fn connect(addr: IpAddr) -> AsyncIoIcmpSocket {
+    t!(AsyncIoIcmpSocket::new(t!(IcmpSocket::connect_with_kind(addr, SocketKind::Datagram))))
+}
+
+#[test]
+fn echo_v4() {
+    with_ping_sockets("echo_v4", || {
+        block_on(async {
+            let localhost = IpAddr::V4(Ipv4Addr::LOCALHOST);
+            let socket = connect(localhost);
+
+            let request = Icmpv4Message::Echo {
+                identifier: 0x4131,
+                sequence: 1,
+                payload: b"async_io_echo_v4".to_vec(),
+            };
+            t!(socket.send(&t!(request.to_bytes())).await);
+
+            let mut buf = [0u8; 1024];
+            let (size, source) = t!(socket.recv_from(&mut buf).await);
+            assert_eq!(source, localhost);
+            assert_eq!(t!(Icmpv4Message::decode(&buf[..size])), Icmpv4Message::EchoReply {
+                identifier: kernel_identifier(socket.get_ref()),
+                sequence: 1,
+                payload: b"async_io_echo_v4".to_vec(),
+            });
+        });
+    });
+}
+
+#[test]
+fn echo_v6() {
+    with_ping_sockets("echo_v6", || {
+        block_on(async {
+            let localhost = IpAddr::V6(Ipv6Addr::LOCALHOST);
+            let socket = t!(AsyncIoIcmpSocket::new(t!(IcmpSocket::new_with_kind(
+                AddressFamily::V6,
+                SocketKind::Datagram,
+            ))));
+
+            let request = Icmpv6Message::EchoRequest {
+                identifier: 0x4232,
+                sequence: 2,
+                payload: b"async_io_echo_v6".to_vec(),
+            };
+            t!(socket.send_to(&t!(request.to_bytes()), localhost).await);
+
+            let mut buf = [0u8; 1024];
+            let size = t!(socket.recv(&mut buf).await);
+            assert_eq!(t!(Icmpv6Message::decode(&buf[..size])), Icmpv6Message::EchoReply {
+                identifier: kernel_identifier(socket.get_ref()),
+                sequence: 2,
+                payload: b"async_io_echo_v6".to_vec(),
+            });
+        });
+    });
+}
+
+#[test]
+fn poll_variants() {
+    with_ping_sockets("poll_variants", || {
+        block_on(async {
+            let localhost = IpAddr::V4(Ipv4Addr::LOCALHOST);
+            let socket = connect(localhost);
+            let mut buf = [0u8; 1024];
+
+            // Nothing was sent yet, so there is nothing to receive.
+            poll_fn(|cx| {
+                assert!(socket.poll_recv(cx, &mut buf).is_pending());
+                assert!(socket.poll_recv_from(cx, &mut buf).is_pending());
+                Poll::Ready(())
+            }).await;
+            t!(poll_fn(|cx| socket.poll_send_ready(cx)).await);
+
+            let request = Icmpv4Message::Echo {
+                identifier: 0x4333,
+                sequence: 1,
+                payload: b"async_io_poll".to_vec(),
+            };
+            let bytes = t!(request.to_bytes());
+            assert_eq!(t!(poll_fn(|cx| socket.poll_send(cx, &bytes)).await), bytes.len());
+            t!(poll_fn(|cx| socket.poll_recv_ready(cx)).await);
+            let (size, source) = t!(poll_fn(|cx| socket.poll_recv_from(cx, &mut buf)).await);
+            assert_eq!(source, localhost);
+            assert!(matches!(t!(Icmpv4Message::decode(&buf[..size])), Icmpv4Message::EchoReply { sequence: 1, .. }));
+
+            let request = Icmpv4Message::Echo {
+                identifier: 0x4333,
+                sequence: 2,
+                payload: b"async_io_poll".to_vec(),
+            };
+            let bytes = t!(request.to_bytes());
+            t!(poll_fn(|cx| socket.poll_send_to(cx, &bytes, localhost)).await);
+            let size = t!(poll_fn(|cx| socket.poll_recv(cx, &mut buf)).await);
+            assert!(matches!(t!(Icmpv4Message::decode(&buf[..size])), Icmpv4Message::EchoReply { sequence: 2, .. }));
+
+            // The socket stays usable once taken back out of the reactor.
+            let socket = t!(socket.into_inner());
+            assert_eq!(socket.kind(), SocketKind::Datagram);
+        });
+    });
+}
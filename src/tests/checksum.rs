@@ -0,0 +1,107 @@
+use std::net::Ipv6Addr;
+
+use crate::checksum::{self, Checksum};
+use crate::v4::Icmpv4Message;
+use crate::v6::Icmpv6Message;
+
+/// Example from RFC 1071, section 3.
+const RFC1071: &[u8] = &[0x00, 0x01, 0xf2, 0x03, 0xf4, 0xf5, 0xf6, 0xf7];
+
+#[test]
+fn rfc1071_example() {
+    assert_eq!(checksum::checksum(RFC1071), !0xddf2);
+}
+
+#[test]
+fn odd_length() {
+    assert_eq!(checksum::checksum(&[0x01]), !0x0100);
+    assert_eq!(checksum::checksum(&[0x01, 0x02, 0x03]), !0x0402);
+    assert_eq!(checksum::checksum(&[]), 0xffff);
+}
+
+#[test]
+fn incremental() {
+    let data: Vec<u8> = (0..=255u8).cycle().take(1001).collect();
+    let expected = checksum::checksum(&data);
+
+    for split in &[1, 2, 3, 7, 64, 999] {
+        let mut sum = Checksum::new();
+        for chunk in data.chunks(*split) {
+            sum.add_bytes(chunk);
+        }
+        assert_eq!(sum.finish(), expected, "chunks of {} bytes", split);
+    }
+
+    let mut sum = Checksum::new();
+    sum.add_bytes(&[0x00]);
+    sum.add_u16(0x01f2);
+    sum.add_bytes(&[0x03]);
+    sum.add_u32(0xf4f5_f6f7);
+    assert_eq!(sum.finish(), checksum::checksum(RFC1071));
+}
+
+#[test]
+fn update() {
+    let message = Icmpv4Message::Echo {
+        identifier: 0x1234,
+        sequence: 1,
+        payload: b"rewrite me".to_vec(),
+    };
+    let mut bytes = t!(message.to_bytes());
+
+    for &sequence in &[2u16, 0x00ff, 0xff00, 0xffff, 0] {
+        let old = u16::from_be_bytes([bytes[6], bytes[7]]);
+        let sum = u16::from_be_bytes([bytes[2], bytes[3]]);
+        bytes[6..8].copy_from_slice(&sequence.to_be_bytes());
+        bytes[2..4].copy_from_slice(&checksum::update(sum, old, sequence).to_be_bytes());
+
+        assert_eq!(checksum::checksum(&bytes), 0);
+        assert_eq!(
+            t!(Icmpv4Message::decode(&bytes)),
+            Icmpv4Message::Echo {
+                identifier: 0x1234,
+                sequence,
+                payload: b"rewrite me".to_vec(),
+            }
+        );
+    }
+}
+
+#[test]
+fn v4_decode_verifies() {
+    let message = Icmpv4Message::Echo {
+        identifier: 1,
+        sequence: 1,
+        payload: vec![1, 2, 3],
+    };
+    let mut bytes = t!(message.to_bytes());
+    bytes[9] ^= 0x10;
+
+    assert!(Icmpv4Message::decode(&bytes).is_err());
+}
+
+#[test]
+fn v6_pseudo_header() {
+    let source = Ipv6Addr::new(0xfe80, 0, 0, 0, 0x0200, 0x5eff, 0xfe00, 0x5301);
+    let destination = Ipv6Addr::new(0xff02, 0, 0, 0, 0, 0, 0, 1);
+    let message = Icmpv6Message::EchoRequest {
+        identifier: 0x0bad,
+        sequence: 7,
+        payload: b"pseudo".to_vec(),
+    };
+
+    let mut bytes = vec![0; message.encoded_len()];
+    t!(message.encode_with_checksum(&mut bytes, &source, &destination));
+
+    let mut sum = checksum::pseudo_header_v6(&source, &destination, bytes.len() as u32);
+    sum.add_bytes(&bytes);
+    assert_eq!(sum.finish(), 0);
+    assert_eq!(checksum::icmpv6_checksum(&source, &destination, &bytes), 0);
+
+    assert_eq!(t!(Icmpv6Message::decode_with_checksum(&bytes, &source, &destination)), message);
+    assert!(Icmpv6Message::decode_with_checksum(&bytes, &destination, &source).is_ok());
+    assert!(Icmpv6Message::decode_with_checksum(&bytes, &source, &Ipv6Addr::LOCALHOST).is_err());
+
+    bytes[8] ^= 0x01;
+    assert!(Icmpv6Message::decode_with_checksum(&bytes, &source, &destination).is_err());
+}
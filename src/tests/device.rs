@@ -0,0 +1,133 @@
+use std::io::ErrorKind;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::time::Duration;
+
+use crate::{AddressFamily, IcmpSocket};
+use crate::v4::Icmpv4Message;
+use crate::v6::Icmpv6Message;
+
+use super::netns::{index, ip, with_veth};
+
+fn open(family: AddressFamily) -> IcmpSocket {
+    let socket = t!(IcmpSocket::new(family));
+    t!(socket.set_read_timeout(Some(Duration::from_secs(2))));
+    socket
+}
+
+#[test]
+fn bind_device() {
+    let socket = open(AddressFamily::V4);
+    assert_eq!(t!(socket.device()), None);
+    assert_eq!(t!(socket.device_index()), 0);
+
+    t!(socket.bind_device(Some("lo")));
+    assert_eq!(t!(socket.device()), Some("lo".to_string()));
+    assert_eq!(t!(socket.device_index()), index("lo"));
+
+    t!(socket.bind_device(None));
+    assert_eq!(t!(socket.device()), None);
+
+    t!(socket.bind_device_index(index("lo")));
+    assert_eq!(t!(socket.device()), Some("lo".to_string()));
+    t!(socket.bind_device_index(0));
+    assert_eq!(t!(socket.device_index()), 0);
+
+    assert!(socket.bind_device(Some("no-such-device")).is_err());
+    assert_eq!(socket.bind_device(Some("lo\0")).unwrap_err().kind(), ErrorKind::InvalidInput);
+}
+
+#[test]
+fn bind_device_v4() {
+    with_veth(|| {
+        let peer = IpAddr::V4(Ipv4Addr::new(10, 9, 0, 2));
+        let request = Icmpv4Message::Echo {
+            identifier: 0x7015,
+            sequence: 1,
+            payload: b"bind_device_v4".to_vec(),
+        };
+
+        // No route to the peer through the loopback interface
+        let socket = open(AddressFamily::V4);
+        t!(socket.bind_device(Some("lo")));
+        assert!(socket.send_to(&t!(request.to_bytes()), peer).is_err());
+
+        t!(socket.bind_device(Some("v0")));
+        assert_eq!(t!(socket.device()), Some("v0".to_string()));
+        t!(socket.send_to(&t!(request.to_bytes()), peer));
+
+        let mut buf = [0u8; 1024];
+        loop {
+            let (size, source) = t!(socket.recv_message(&mut buf));
+            if let Ok(Icmpv4Message::EchoReply { identifier: 0x7015, .. }) = Icmpv4Message::decode(&buf[..size]) {
+                assert_eq!(source, peer);
+                break;
+            }
+        }
+    });
+}
+
+#[test]
+fn link_local_v6() {
+    with_veth(|| {
+        let peer = IpAddr::V6(Ipv6Addr::new(0xfe80, 0, 0, 0, 0, 0, 0, 2));
+        let request = Icmpv6Message::EchoRequest {
+            identifier: 0x7015,
+            sequence: 1,
+            payload: b"link_local_v6".to_vec(),
+        };
+
+        // The peer is not on the loopback link
+        let socket = open(AddressFamily::V6);
+        t!(socket.bind_device_index(index("lo")));
+        assert!(socket.send_to(&t!(request.to_bytes()), peer).is_err());
+
+        t!(socket.bind_device_index(index("v0")));
+        assert_eq!(t!(socket.device_index()), index("v0"));
+        t!(socket.send_to(&t!(request.to_bytes()), peer));
+
+        let mut buf = [0u8; 1024];
+        loop {
+            let (size, source) = t!(socket.recv_from(&mut buf));
+            if let Ok(Icmpv6Message::EchoReply { identifier: 0x7015, .. }) = Icmpv6Message::decode(&buf[..size]) {
+                assert_eq!(source, peer);
+                break;
+            }
+        }
+    });
+}
+
+#[test]
+fn bind_device_vrf() {
+    with_veth(|| {
+        if !ip("link add vrf0 type vrf table 10") {
+            super::skip("bind_device_vrf", "VRF devices are not available");
+            return;
+        }
+        assert!(ip("link set v0 master vrf0"));
+        assert!(ip("link set vrf0 up"));
+        let peer = IpAddr::V4(Ipv4Addr::new(10, 9, 0, 2));
+        let request = Icmpv4Message::Echo {
+            identifier: 0x7015,
+            sequence: 2,
+            payload: b"bind_device_vrf".to_vec(),
+        };
+
+        // The route to the peer moved to the table of the VRF
+        let socket = open(AddressFamily::V4);
+        assert!(socket.send_to(&t!(request.to_bytes()), peer).is_err());
+
+        t!(socket.bind_device(Some("vrf0")));
+        assert_eq!(t!(socket.device()), Some("vrf0".to_string()));
+        assert_eq!(t!(socket.device_index()), index("vrf0"));
+        t!(socket.send_to(&t!(request.to_bytes()), peer));
+
+        let mut buf = [0u8; 1024];
+        loop {
+            let (size, source) = t!(socket.recv_message(&mut buf));
+            if let Ok(Icmpv4Message::EchoReply { identifier: 0x7015, sequence: 2, .. }) = Icmpv4Message::decode(&buf[..size]) {
+                assert_eq!(source, peer);
+                break;
+            }
+        }
+    });
+}
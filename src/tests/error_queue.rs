@@ -0,0 +1,146 @@
+use std::fs;
+use std::io::ErrorKind;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::thread;
+use std::time::Duration;
+
+use crate::{ErrorOrigin, ExtendedError, IcmpSocket, SocketKind};
+use crate::v4::Icmpv4Message;
+use crate::v6::Icmpv6Message;
+
+use super::netns::with_veth;
+use super::ping::kernel_identifier;
+
+const ICMP_DEST_UNREACH: u8 = 3;
+const ICMP_FRAG_NEEDED: u8 = 4;
+const ICMP_TIME_EXCEEDED: u8 = 11;
+const ICMPV6_TIME_EXCEEDED: u8 = 3;
+
+/// Waits for the next error, which is queued asynchronously.
+fn recv_error(socket: &IcmpSocket, buf: &mut [u8]) -> (usize, ExtendedError) {
+    for _ in 0..200 {
+        match socket.recv_error(buf) {
+            Ok(error) => return error,
+            Err(ref err) if err.kind() == ErrorKind::WouldBlock => thread::sleep(Duration::from_millis(10)),
+            Err(err) => panic!("recv_error failed: {}", err),
+        }
+    }
+    panic!("no error queued");
+}
+
+fn connect(addr: IpAddr, kind: SocketKind) -> IcmpSocket {
+    let socket = t!(IcmpSocket::connect_with_kind(addr, kind));
+    assert!(!t!(socket.error_queue()));
+    t!(socket.set_error_queue(true));
+    assert!(t!(socket.error_queue()));
+    socket
+}
+
+fn time_exceeded_v4(kind: SocketKind) {
+    with_veth(move || {
+        // The range is per namespace
+        if kind == SocketKind::Datagram && fs::write("/proc/sys/net/ipv4/ping_group_range", "0 2147483647").is_err() {
+            return;
+        }
+        let target = IpAddr::V4(Ipv4Addr::new(10, 9, 2, 1));
+        let socket = connect(target, kind);
+        t!(socket.set_ttl(1));
+
+        let request = Icmpv4Message::Echo {
+            identifier: 0x7022,
+            sequence: 1,
+            payload: b"time_exceeded".to_vec(),
+        };
+        t!(socket.send(&t!(request.to_bytes())));
+
+        let mut buf = [0u8; 1024];
+        let (size, error) = recv_error(&socket, &mut buf);
+        assert_eq!(error, ExtendedError {
+            errno: libc::EHOSTUNREACH,
+            origin: ErrorOrigin::Icmp,
+            message_type: ICMP_TIME_EXCEEDED,
+            code: 0,
+            info: 0,
+            data: 0,
+            offender: Some(IpAddr::V4(Ipv4Addr::new(10, 9, 0, 2))),
+            destination: Some(target),
+        });
+        assert_eq!(error.error().raw_os_error(), Some(libc::EHOSTUNREACH));
+        // The request quoted is the one on the wire, with the identifier of the kernel on
+        // datagram sockets
+        let identifier = match kind {
+            SocketKind::Datagram => kernel_identifier(&socket),
+            _ => 0x7022,
+        };
+        assert_eq!(t!(Icmpv4Message::decode(&buf[..size])), Icmpv4Message::Echo {
+            identifier,
+            sequence: 1,
+            payload: b"time_exceeded".to_vec(),
+        });
+    });
+}
+
+#[test]
+fn time_exceeded_raw_v4() {
+    time_exceeded_v4(SocketKind::Raw);
+}
+
+#[test]
+fn time_exceeded_datagram_v4() {
+    time_exceeded_v4(SocketKind::Datagram);
+}
+
+#[test]
+fn fragmentation_needed() {
+    with_veth(|| {
+        let target = IpAddr::V4(Ipv4Addr::new(10, 9, 2, 1));
+        let socket = connect(target, SocketKind::Raw);
+
+        let request = Icmpv4Message::Echo {
+            identifier: 0x7022,
+            sequence: 1,
+            payload: vec![0; 1400],
+        };
+        t!(socket.send(&t!(request.to_bytes())));
+
+        let mut buf = [0u8; 2048];
+        let (_, error) = recv_error(&socket, &mut buf);
+        assert_eq!(error.errno, libc::EMSGSIZE);
+        assert_eq!(error.origin, ErrorOrigin::Icmp);
+        assert_eq!((error.message_type, error.code), (ICMP_DEST_UNREACH, ICMP_FRAG_NEEDED));
+        assert_eq!(error.info, 1280);
+    });
+}
+
+#[test]
+fn time_exceeded_v6() {
+    with_veth(|| {
+        let target = IpAddr::V6(Ipv6Addr::new(0xfd09, 2, 0, 0, 0, 0, 0, 1));
+        let socket = connect(target, SocketKind::Raw);
+        t!(socket.set_ttl(1));
+
+        let request = Icmpv6Message::EchoRequest {
+            identifier: 0x7022,
+            sequence: 1,
+            payload: b"time_exceeded".to_vec(),
+        };
+        t!(socket.send(&t!(request.to_bytes())));
+
+        let mut buf = [0u8; 1024];
+        let (size, error) = recv_error(&socket, &mut buf);
+        assert_eq!(error.origin, ErrorOrigin::Icmp6);
+        assert_eq!((error.message_type, error.code), (ICMPV6_TIME_EXCEEDED, 0));
+        assert_eq!(error.offender, Some(IpAddr::V6(Ipv6Addr::new(0xfd09, 0, 0, 0, 0, 0, 0, 2))));
+        assert_eq!(error.destination, Some(target));
+        assert_eq!(&buf[..2], &[128, 0]);
+        assert_eq!(&buf[4..8], &[0x70, 0x22, 0, 1]);
+        assert_eq!(&buf[8..size], b"time_exceeded");
+    });
+}
+
+#[test]
+fn empty_error_queue() {
+    let socket = connect(IpAddr::V4(Ipv4Addr::LOCALHOST), SocketKind::Raw);
+    let mut buf = [0u8; 64];
+    assert_eq!(socket.recv_error(&mut buf).unwrap_err().kind(), ErrorKind::WouldBlock);
+}
@@ -0,0 +1,445 @@
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+use crate::checksum::checksum;
+use crate::extension::{ExtensionObject, Extensions, InterfaceInformation, InterfaceRole, MplsLabel};
+use crate::quote::Transport;
+use crate::v4::{DestinationUnreachableCode, Icmpv4Message, ParameterProblemCode, TimeExceededCode};
+use crate::v6::{self, Icmpv6Message};
+
+// Captured from Linux 6.18 routing between two network namespaces, in answer to
+// traceroute-style UDP datagrams with a TTL or hop limit of 1 sent from 10.9.0.1 to
+// 10.9.2.1 and from fd09::1 to fd09:2::1. Linux sends neither MPLS nor interface
+// information objects, and sets no length attribute.
+
+/// Time Exceeded for a short datagram, quoted in full.
+const LINUX_TIME_EXCEEDED: &[u8] = &[
+    0x0b, 0x00, 0xe4, 0x4f, 0x00, 0x00, 0x00, 0x00, 0x45, 0x00, 0x00, 0x3c, 0x76, 0x26, 0x40, 0x00, 0x01, 0x11,
+    0xed, 0x77, 0x0a, 0x09, 0x00, 0x01, 0x0a, 0x09, 0x02, 0x01, 0x82, 0x9a, 0x82, 0x9b, 0x00, 0x28, 0x16, 0x4d,
+    0x40, 0x41, 0x42, 0x43, 0x44, 0x45, 0x46, 0x47, 0x48, 0x49, 0x4a, 0x4b, 0x4c, 0x4d, 0x4e, 0x4f, 0x50, 0x51,
+    0x52, 0x53, 0x54, 0x55, 0x56, 0x57, 0x58, 0x59, 0x5a, 0x5b, 0x5c, 0x5d, 0x5e, 0x5f,
+];
+
+/// Time Exceeded quoting a datagram longer than 128 bytes, which carries no extensions.
+const LINUX_TIME_EXCEEDED_LONG: &[u8] = &[
+    0x0b, 0x00, 0x06, 0xd0, 0x00, 0x00, 0x00, 0x00, 0x45, 0x00, 0x00, 0xe4, 0x76, 0x39, 0x40, 0x00, 0x01, 0x11,
+    0xec, 0xbc, 0x0a, 0x09, 0x00, 0x01, 0x0a, 0x09, 0x02, 0x01, 0x82, 0x9a, 0x82, 0x9b, 0x00, 0xd0, 0x16, 0xf5,
+    0x40, 0x41, 0x42, 0x43, 0x44, 0x45, 0x46, 0x47, 0x48, 0x49, 0x4a, 0x4b, 0x4c, 0x4d, 0x4e, 0x4f, 0x50, 0x51,
+    0x52, 0x53, 0x54, 0x55, 0x56, 0x57, 0x58, 0x59, 0x5a, 0x5b, 0x5c, 0x5d, 0x5e, 0x5f, 0x60, 0x61, 0x62, 0x63,
+    0x64, 0x65, 0x66, 0x67, 0x68, 0x69, 0x6a, 0x6b, 0x6c, 0x6d, 0x6e, 0x6f, 0x70, 0x71, 0x72, 0x73, 0x74, 0x75,
+    0x76, 0x77, 0x78, 0x79, 0x7a, 0x7b, 0x7c, 0x7d, 0x7e, 0x7f, 0x40, 0x41, 0x42, 0x43, 0x44, 0x45, 0x46, 0x47,
+    0x48, 0x49, 0x4a, 0x4b, 0x4c, 0x4d, 0x4e, 0x4f, 0x50, 0x51, 0x52, 0x53, 0x54, 0x55, 0x56, 0x57, 0x58, 0x59,
+    0x5a, 0x5b, 0x5c, 0x5d, 0x5e, 0x5f, 0x60, 0x61, 0x62, 0x63, 0x64, 0x65, 0x66, 0x67, 0x68, 0x69, 0x6a, 0x6b,
+    0x6c, 0x6d, 0x6e, 0x6f, 0x70, 0x71, 0x72, 0x73, 0x74, 0x75, 0x76, 0x77, 0x78, 0x79, 0x7a, 0x7b, 0x7c, 0x7d,
+    0x7e, 0x7f, 0x40, 0x41, 0x42, 0x43, 0x44, 0x45, 0x46, 0x47, 0x48, 0x49, 0x4a, 0x4b, 0x4c, 0x4d, 0x4e, 0x4f,
+    0x50, 0x51, 0x52, 0x53, 0x54, 0x55, 0x56, 0x57, 0x58, 0x59, 0x5a, 0x5b, 0x5c, 0x5d, 0x5e, 0x5f, 0x60, 0x61,
+    0x62, 0x63, 0x64, 0x65, 0x66, 0x67, 0x68, 0x69, 0x6a, 0x6b, 0x6c, 0x6d, 0x6e, 0x6f, 0x70, 0x71, 0x72, 0x73,
+    0x74, 0x75, 0x76, 0x77, 0x78, 0x79, 0x7a, 0x7b, 0x7c, 0x7d, 0x7e, 0x7f, 0x40, 0x41, 0x42, 0x43, 0x44, 0x45,
+    0x46, 0x47,
+];
+
+/// ICMPv6 Time Exceeded, sent by fd09::2.
+const LINUX_TIME_EXCEEDED_V6: &[u8] = &[
+    0x03, 0x00, 0x62, 0xef, 0x00, 0x00, 0x00, 0x00, 0x60, 0x0a, 0x3f, 0x68, 0x00, 0x28, 0x11, 0x01, 0xfd, 0x09,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0xfd, 0x09, 0x00, 0x02,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x82, 0x9a, 0x82, 0x9b, 0x00, 0x28,
+    0xfa, 0x50, 0x40, 0x41, 0x42, 0x43, 0x44, 0x45, 0x46, 0x47, 0x48, 0x49, 0x4a, 0x4b, 0x4c, 0x4d, 0x4e, 0x4f,
+    0x50, 0x51, 0x52, 0x53, 0x54, 0x55, 0x56, 0x57, 0x58, 0x59, 0x5a, 0x5b, 0x5c, 0x5d, 0x5e, 0x5f,
+];
+
+// No captures of routers sending MPLS or interface information objects were available, so
+// these messages are built by hand after the layouts of RFC 4884, RFC 4950 and RFC 5837,
+// with documentation addresses and filler bytes standing in for the quoted datagram.
+
+/// Time Exceeded with a compliant length attribute and a single label.
+const TIME_EXCEEDED_MPLS: &[u8] = &[
+    0x0b, 0x00, 0xfa, 0x72, 0x00, 0x20, 0x00, 0x00, 0x45, 0x00, 0x00, 0x3c, 0x1c, 0x46, 0x00, 0x00, 0x01, 0x11,
+    0xb1, 0x34, 0xc0, 0x00, 0x02, 0x02, 0xc6, 0x33, 0x64, 0x01, 0x82, 0x9b, 0x82, 0xa4, 0x00, 0x28, 0x00, 0x00,
+    0x40, 0x41, 0x42, 0x43, 0x44, 0x45, 0x46, 0x47, 0x48, 0x49, 0x4a, 0x4b, 0x4c, 0x4d, 0x4e, 0x4f, 0x50, 0x51,
+    0x52, 0x53, 0x54, 0x55, 0x56, 0x57, 0x58, 0x59, 0x5a, 0x5b, 0x5c, 0x5d, 0x5e, 0x5f, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x20, 0x00, 0xc8, 0x19, 0x00, 0x08, 0x01, 0x01,
+    0x05, 0xdc, 0x11, 0x01,
+];
+
+/// Time Exceeded without a length attribute, extensions at offset 128 and two labels.
+const TIME_EXCEEDED_MPLS_NON_COMPLIANT: &[u8] = &[
+    0x0b, 0x00, 0xfa, 0x92, 0x00, 0x00, 0x00, 0x00, 0x45, 0x00, 0x00, 0x3c, 0x1c, 0x46, 0x00, 0x00, 0x01, 0x11,
+    0xb1, 0x34, 0xc0, 0x00, 0x02, 0x02, 0xc6, 0x33, 0x64, 0x01, 0x82, 0x9b, 0x82, 0xa4, 0x00, 0x28, 0x00, 0x00,
+    0x40, 0x41, 0x42, 0x43, 0x44, 0x45, 0x46, 0x47, 0x48, 0x49, 0x4a, 0x4b, 0x4c, 0x4d, 0x4e, 0x4f, 0x50, 0x51,
+    0x52, 0x53, 0x54, 0x55, 0x56, 0x57, 0x58, 0x59, 0x5a, 0x5b, 0x5c, 0x5d, 0x5e, 0x5f, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x20, 0x00, 0x46, 0xd7, 0x00, 0x0c, 0x01, 0x01,
+    0x03, 0xe8, 0x4a, 0x01, 0x49, 0x31, 0x01, 0x01,
+];
+
+/// ICMPv6 Time Exceeded with a length attribute in 64-bit words.
+const TIME_EXCEEDED_MPLS_V6: &[u8] = &[
+    0x03, 0x00, 0x00, 0x00, 0x10, 0x00, 0x00, 0x00, 0x60, 0x00, 0x00, 0x00, 0x00, 0x28, 0x11, 0x01, 0xfd, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x20, 0x01, 0x0d, 0xb8,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x82, 0x9b, 0x82, 0xa4, 0x00, 0x28,
+    0x00, 0x00, 0x40, 0x41, 0x42, 0x43, 0x44, 0x45, 0x46, 0x47, 0x48, 0x49, 0x4a, 0x4b, 0x4c, 0x4d, 0x4e, 0x4f,
+    0x50, 0x51, 0x52, 0x53, 0x54, 0x55, 0x56, 0x57, 0x58, 0x59, 0x5a, 0x5b, 0x5c, 0x5d, 0x5e, 0x5f, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x20, 0x00, 0xb7, 0x1c, 0x00, 0x08, 0x01, 0x01,
+    0x05, 0xdc, 0x21, 0xfe,
+];
+
+/// Time Exceeded with an MPLS label stack and the incoming interface.
+const TIME_EXCEEDED_INTERFACE: &[u8] = &[
+    0x0b, 0x00, 0xfa, 0x72, 0x00, 0x20, 0x00, 0x00, 0x45, 0x00, 0x00, 0x3c, 0x1c, 0x46, 0x00, 0x00, 0x01, 0x11,
+    0xb1, 0x34, 0xc0, 0x00, 0x02, 0x02, 0xc6, 0x33, 0x64, 0x01, 0x82, 0x9b, 0x82, 0xa4, 0x00, 0x28, 0x00, 0x00,
+    0x40, 0x41, 0x42, 0x43, 0x44, 0x45, 0x46, 0x47, 0x48, 0x49, 0x4a, 0x4b, 0x4c, 0x4d, 0x4e, 0x4f, 0x50, 0x51,
+    0x52, 0x53, 0x54, 0x55, 0x56, 0x57, 0x58, 0x59, 0x5a, 0x5b, 0x5c, 0x5d, 0x5e, 0x5f, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x20, 0x00, 0xca, 0x99, 0x00, 0x08, 0x01, 0x01,
+    0x05, 0xdc, 0x11, 0x01, 0x00, 0x20, 0x02, 0x0f, 0x00, 0x00, 0x00, 0x05, 0x00, 0x01, 0x00, 0x00, 0xc0, 0x00,
+    0x02, 0x4d, 0x0c, 0x67, 0x65, 0x2d, 0x30, 0x2f, 0x30, 0x2f, 0x31, 0x2e, 0x30, 0x00, 0x00, 0x00, 0x05, 0xdc,
+];
+
+/// ICMPv6 Destination Unreachable describing the incoming and outgoing interfaces.
+const UNREACHABLE_INTERFACES_V6: &[u8] = &[
+    0x01, 0x00, 0x00, 0x00, 0x10, 0x00, 0x00, 0x00, 0x60, 0x00, 0x00, 0x00, 0x00, 0x28, 0x11, 0x01, 0xfd, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x20, 0x01, 0x0d, 0xb8,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x82, 0x9b, 0x82, 0xa4, 0x00, 0x28,
+    0x00, 0x00, 0x40, 0x41, 0x42, 0x43, 0x44, 0x45, 0x46, 0x47, 0x48, 0x49, 0x4a, 0x4b, 0x4c, 0x4d, 0x4e, 0x4f,
+    0x50, 0x51, 0x52, 0x53, 0x54, 0x55, 0x56, 0x57, 0x58, 0x59, 0x5a, 0x5b, 0x5c, 0x5d, 0x5e, 0x5f, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x20, 0x00, 0x84, 0xb4, 0x00, 0x18, 0x02, 0x04,
+    0x00, 0x02, 0x00, 0x00, 0x20, 0x01, 0x0d, 0xb8, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x99, 0x00, 0x18, 0x02, 0x8b, 0x00, 0x00, 0x00, 0x0c, 0x0c, 0x78, 0x65, 0x2d, 0x31, 0x2f, 0x32, 0x2f,
+    0x30, 0x00, 0x00, 0x00, 0x00, 0x00, 0x23, 0x28,
+];
+
+fn labels(message: &Icmpv4Message) -> Vec<MplsLabel> {
+    match *message {
+        Icmpv4Message::TimeExceeded { extensions: Some(ref extensions), .. } => {
+            extensions.mpls_label_stack().expect("MPLS label stack object").to_vec()
+        },
+        _ => panic!("expected Time Exceeded with extensions, got {:?}", message),
+    }
+}
+
+#[test]
+fn mpls_compliant() {
+    let message = t!(Icmpv4Message::decode(TIME_EXCEEDED_MPLS));
+
+    assert_eq!(labels(&message), [MplsLabel {
+        label: 24001,
+        traffic_class: 0,
+        bottom_of_stack: true,
+        ttl: 1,
+    }]);
+    assert_eq!(message.datagram().map(<[u8]>::len), Some(128));
+    match t!(message.quoted()).transport {
+        Transport::Udp(header) => assert_eq!(header.destination_port, 33444),
+        transport => panic!("expected UDP, got {:?}", transport),
+    }
+
+    assert_eq!(t!(message.to_bytes()), TIME_EXCEEDED_MPLS);
+}
+
+#[test]
+fn mpls_non_compliant() {
+    let message = t!(Icmpv4Message::decode(TIME_EXCEEDED_MPLS_NON_COMPLIANT));
+
+    assert_eq!(labels(&message), [
+        MplsLabel {
+            label: 16004,
+            traffic_class: 5,
+            bottom_of_stack: false,
+            ttl: 1,
+        },
+        MplsLabel {
+            label: 299_792,
+            traffic_class: 0,
+            bottom_of_stack: true,
+            ttl: 1,
+        },
+    ]);
+
+    // Re-encoded as a compliant message, with the length attribute set.
+    let bytes = t!(message.to_bytes());
+    assert_eq!(bytes.len(), TIME_EXCEEDED_MPLS_NON_COMPLIANT.len());
+    assert_eq!(bytes[5], 32);
+    assert_eq!(t!(Icmpv4Message::decode(&bytes)), message);
+}
+
+#[test]
+fn mpls_v6() {
+    let message = t!(Icmpv6Message::decode(TIME_EXCEEDED_MPLS_V6));
+
+    match message {
+        Icmpv6Message::TimeExceeded { code, ref datagram, extensions: Some(ref extensions) } => {
+            assert_eq!(code, v6::TimeExceededCode::HopLimitExceeded);
+            assert_eq!(datagram.len(), 128);
+            assert_eq!(extensions.objects, [ExtensionObject::MplsLabelStack(vec![MplsLabel {
+                label: 24002,
+                traffic_class: 0,
+                bottom_of_stack: true,
+                ttl: 254,
+            }])]);
+        },
+        _ => panic!("expected Time Exceeded with extensions, got {:?}", message),
+    }
+
+    assert_eq!(t!(message.to_bytes()), TIME_EXCEEDED_MPLS_V6);
+}
+
+#[test]
+fn invalid_checksum() {
+    let mut bytes = TIME_EXCEEDED_MPLS_V6.to_vec();
+    let last = bytes.len() - 1;
+    bytes[last] ^= 0xff;
+
+    // Extensions are dropped, nothing of the body is lost.
+    match t!(Icmpv6Message::decode(&bytes)) {
+        Icmpv6Message::TimeExceeded { datagram, extensions: None, .. } => assert_eq!(datagram, &bytes[8..]),
+        message => panic!("expected Time Exceeded without extensions, got {:?}", message),
+    }
+    assert!(Extensions::decode(&bytes[136..]).is_err());
+}
+
+#[test]
+fn without_extensions() {
+    // Body longer than 128 bytes, but no extension structure at the offset
+    let message = Icmpv4Message::TimeExceeded {
+        code: TimeExceededCode::TtlExceeded,
+        datagram: vec![0x45; 200],
+        extensions: None,
+    };
+    let bytes = t!(message.to_bytes());
+
+    assert_eq!(bytes[5], 0);
+    assert_eq!(t!(Icmpv4Message::decode(&bytes)), message);
+}
+
+#[test]
+fn linux_time_exceeded() {
+    for &(bytes, quoted) in &[(LINUX_TIME_EXCEEDED, 60), (LINUX_TIME_EXCEEDED_LONG, 228)] {
+        let message = t!(Icmpv4Message::decode(bytes));
+        match message {
+            Icmpv4Message::TimeExceeded { code, ref datagram, ref extensions } => {
+                assert_eq!(code, TimeExceededCode::TtlExceeded);
+                assert_eq!(datagram[..], bytes[8..]);
+                assert_eq!(datagram.len(), quoted);
+                assert_eq!(*extensions, None);
+            },
+            _ => panic!("not a Time Exceeded message: {:?}", message),
+        }
+        assert_eq!(t!(message.to_bytes()), bytes);
+    }
+
+    let source = Ipv6Addr::new(0xfd09, 0, 0, 0, 0, 0, 0, 2);
+    let destination = Ipv6Addr::new(0xfd09, 0, 0, 0, 0, 0, 0, 1);
+    let message = t!(Icmpv6Message::decode_with_checksum(LINUX_TIME_EXCEEDED_V6, &source, &destination));
+    match message {
+        Icmpv6Message::TimeExceeded { code, ref datagram, ref extensions } => {
+            assert_eq!(code, v6::TimeExceededCode::HopLimitExceeded);
+            assert_eq!(datagram[..], LINUX_TIME_EXCEEDED_V6[8..]);
+            assert_eq!(*extensions, None);
+        },
+        _ => panic!("not a Time Exceeded message: {:?}", message),
+    }
+}
+
+#[test]
+fn round_trip() {
+    let extensions = Extensions {
+        objects: vec![
+            ExtensionObject::MplsLabelStack(vec![MplsLabel {
+                label: 0x000f_ffff,
+                traffic_class: 7,
+                bottom_of_stack: true,
+                ttl: 255,
+            }]),
+            ExtensionObject::Unknown {
+                class_num: 200,
+                c_type: 3,
+                payload: vec![1, 2, 3, 4, 5, 6, 7, 8],
+            },
+        ],
+    };
+    assert_eq!(t!(Extensions::decode(&t!(extensions.to_bytes()))), extensions);
+
+    let mut datagram = vec![0x45; 140];
+    datagram.extend_from_slice(&[0; 4]);
+    let message = Icmpv4Message::DestinationUnreachable {
+        code: DestinationUnreachableCode::FragmentationNeeded,
+        next_hop_mtu: 1400,
+        datagram,
+        extensions: Some(extensions.clone()),
+    };
+    let bytes = t!(message.to_bytes());
+    assert_eq!(bytes[5], 36);
+    assert_eq!(&bytes[6..8], &[0x05, 0x78]);
+    assert_eq!(t!(Icmpv4Message::decode(&bytes)), message);
+
+    let message = Icmpv4Message::ParameterProblem {
+        code: ParameterProblemCode::Pointer,
+        pointer: 9,
+        datagram: vec![0x45; 128],
+        extensions: Some(extensions.clone()),
+    };
+    assert_eq!(t!(Icmpv4Message::decode(&t!(message.to_bytes()))), message);
+
+    let message = Icmpv6Message::DestinationUnreachable {
+        code: v6::DestinationUnreachableCode::Address,
+        datagram: vec![0x60; 136],
+        extensions: Some(extensions),
+    };
+    let bytes = t!(message.to_bytes());
+    assert_eq!(bytes[4], 17);
+    assert_eq!(t!(Icmpv6Message::decode(&bytes)), message);
+}
+
+#[test]
+fn short_datagram_is_padded() {
+    let message = Icmpv4Message::TimeExceeded {
+        code: TimeExceededCode::TtlExceeded,
+        datagram: vec![0x45; 28],
+        extensions: Some(Extensions::default()),
+    };
+    let bytes = t!(message.to_bytes());
+    assert_eq!(bytes.len(), 8 + 128 + 4);
+    assert_eq!(bytes[5], 32);
+
+    match t!(Icmpv4Message::decode(&bytes)) {
+        Icmpv4Message::TimeExceeded { datagram, extensions: Some(extensions), .. } => {
+            assert_eq!(&datagram[..28], &[0x45; 28][..]);
+            assert!(datagram[28..].iter().all(|byte| *byte == 0));
+            assert!(extensions.objects.is_empty());
+        },
+        message => panic!("expected Time Exceeded with extensions, got {:?}", message),
+    }
+}
+
+#[test]
+fn datagram_too_long() {
+    let message = Icmpv4Message::TimeExceeded {
+        code: TimeExceededCode::TtlExceeded,
+        datagram: vec![0x45; 1024],
+        extensions: Some(Extensions::default()),
+    };
+    let mut buf = vec![0; message.encoded_len()];
+
+    assert!(message.encode(&mut buf).is_err());
+    assert!(message.to_bytes().is_err());
+}
+
+#[test]
+fn invalid_objects() {
+    // Wrong version
+    assert!(Extensions::decode(&[0x10, 0x00, 0xef, 0xff]).is_err());
+    // Object length exceeds the structure
+    let mut bytes = t!(Extensions::default().to_bytes());
+    bytes.extend_from_slice(&[0x00, 0x10, 0x01, 0x01]);
+    bytes[2..4].copy_from_slice(&[0, 0]);
+    let sum = checksum(&bytes);
+    bytes[2..4].copy_from_slice(&sum.to_be_bytes());
+    assert!(Extensions::decode(&bytes).is_err());
+
+    // MPLS label stack with a partial entry
+    let mut bytes = vec![0x20, 0x00, 0x00, 0x00, 0x00, 0x07, 0x01, 0x01, 0x05, 0xdc, 0x11];
+    let sum = checksum(&bytes);
+    bytes[2..4].copy_from_slice(&sum.to_be_bytes());
+    assert!(Extensions::decode(&bytes).is_err());
+}
+
+#[test]
+fn interface_information() {
+    let message = t!(Icmpv4Message::decode(TIME_EXCEEDED_INTERFACE));
+    let extensions = match message {
+        Icmpv4Message::TimeExceeded { extensions: Some(ref extensions), .. } => extensions,
+        _ => panic!("expected Time Exceeded with extensions, got {:?}", message),
+    };
+
+    assert_eq!(extensions.mpls_label_stack().map(<[MplsLabel]>::len), Some(1));
+    let interfaces: Vec<_> = extensions.interface_information().collect();
+    assert_eq!(interfaces, [&InterfaceInformation {
+        role: InterfaceRole::Incoming,
+        if_index: Some(5),
+        address: Some(IpAddr::V4(Ipv4Addr::new(192, 0, 2, 77))),
+        name: Some("ge-0/0/1.0".to_string()),
+        mtu: Some(1500),
+    }]);
+
+    assert_eq!(t!(message.to_bytes()), TIME_EXCEEDED_INTERFACE);
+}
+
+#[test]
+fn interface_information_v6() {
+    let message = t!(Icmpv6Message::decode(UNREACHABLE_INTERFACES_V6));
+    let extensions = match message {
+        Icmpv6Message::DestinationUnreachable { extensions: Some(ref extensions), .. } => extensions,
+        _ => panic!("expected Destination Unreachable with extensions, got {:?}", message),
+    };
+
+    let interfaces: Vec<_> = extensions.interface_information().collect();
+    assert_eq!(interfaces, [
+        &InterfaceInformation {
+            role: InterfaceRole::Incoming,
+            if_index: None,
+            address: Some(IpAddr::V6(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 0x99))),
+            name: None,
+            mtu: None,
+        },
+        &InterfaceInformation {
+            role: InterfaceRole::Outgoing,
+            if_index: Some(12),
+            address: None,
+            name: Some("xe-1/2/0".to_string()),
+            mtu: Some(9000),
+        },
+    ]);
+
+    assert_eq!(t!(message.to_bytes()), UNREACHABLE_INTERFACES_V6);
+}
+
+#[test]
+fn interface_information_round_trip() {
+    for role in &[InterfaceRole::Incoming, InterfaceRole::SubIpComponent, InterfaceRole::Outgoing, InterfaceRole::NextHop] {
+        for name in &["".to_string(), "eth0".to_string(), "abc".to_string(), "n".repeat(63)] {
+            let extensions = Extensions {
+                objects: vec![ExtensionObject::InterfaceInformation(InterfaceInformation {
+                    role: *role,
+                    if_index: Some(u32::MAX),
+                    address: None,
+                    name: Some(name.clone()),
+                    mtu: None,
+                })],
+            };
+            assert_eq!(t!(Extensions::decode(&t!(extensions.to_bytes()))), extensions);
+        }
+    }
+
+    let too_long = Extensions {
+        objects: vec![ExtensionObject::InterfaceInformation(InterfaceInformation {
+            role: InterfaceRole::Incoming,
+            if_index: None,
+            address: None,
+            name: Some("x".repeat(64)),
+            mtu: None,
+        })],
+    };
+    let mut buf = vec![0; too_long.encoded_len()];
+    assert!(too_long.encode(&mut buf).is_err());
+    assert!(too_long.to_bytes().is_err());
+}
+
+#[test]
+fn interface_information_truncated() {
+    // ifIndex and MTU announced, only ifIndex present
+    let mut bytes = vec![0x20, 0x00, 0x00, 0x00, 0x00, 0x08, 0x02, 0x09, 0x00, 0x00, 0x00, 0x05];
+    let sum = checksum(&bytes);
+    bytes[2..4].copy_from_slice(&sum.to_be_bytes());
+
+    assert!(Extensions::decode(&bytes).is_err());
+}
@@ -0,0 +1,352 @@
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::time::Duration;
+
+use crate::{AddressFamily, IcmpSocket};
+use crate::filter::{BpfBuilder, BpfProgram, IcmpTypeFilter};
+use crate::v4::Icmpv4Message;
+use crate::v6::Icmpv6Message;
+
+const ICMP_ECHO_REPLY: u8 = 0;
+const ICMP_ECHO: u8 = 8;
+const ICMPV6_ECHO_REPLY: u8 = 129;
+
+const SKF_NET_OFF: i64 = -0x10_0000;
+
+/// Runs `program` over `packet`, whose network header starts at 0 and which the socket
+/// receives from `data` on, the way the kernel does; returns the number of bytes passed.
+fn run(program: &BpfProgram, packet: &[u8], data: usize) -> u32 {
+    let load = |offset: i64, size: usize| -> Option<u32> {
+        let start = if offset < 0 {
+            (offset - SKF_NET_OFF) as usize
+        } else {
+            data + offset as usize
+        };
+        let bytes = packet.get(start..start + size)?;
+        Some(bytes.iter().fold(0, |value, &byte| value << 8 | u32::from(byte)))
+    };
+
+    let (mut a, mut x) = (0u32, 0u32);
+    let mut pc = 0;
+    loop {
+        let instruction = program.instructions()[pc];
+        let size = match instruction.code & 0x18 {
+            0x00 => 4,
+            0x08 => 2,
+            _ => 1,
+        };
+        pc += 1;
+        match instruction.code {
+            // ld abs, ld ind
+            0x20 | 0x28 | 0x30 => match load(i64::from(instruction.k as i32), size) {
+                Some(value) => a = value,
+                None => return 0,
+            },
+            0x40 | 0x48 | 0x50 => match load(i64::from(x) + i64::from(instruction.k as i32), size) {
+                Some(value) => a = value,
+                None => return 0,
+            },
+            // ldxb 4 * ([k] & 0xf)
+            0xb1 => match load(i64::from(instruction.k), 1) {
+                Some(value) => x = 4 * (value & 0x0f),
+                None => return 0,
+            },
+            // jeq #k
+            0x15 => {
+                pc += usize::from(if a == instruction.k { instruction.jt } else { instruction.jf });
+            },
+            // ret #k
+            0x06 => return instruction.k,
+            code => panic!("unexpected opcode {:#x}", code),
+        }
+    }
+}
+
+fn ipv4_packet(header_len: usize, source: Ipv4Addr, message: &[u8]) -> Vec<u8> {
+    let mut packet = vec![0; header_len];
+    packet[0] = 0x40 | (header_len / 4) as u8;
+    packet[9] = 1;
+    packet[12..16].copy_from_slice(&source.octets());
+    packet[16..20].copy_from_slice(&Ipv4Addr::LOCALHOST.octets());
+    packet.extend_from_slice(message);
+    packet
+}
+
+fn ipv6_packet(source: Ipv6Addr, message: &[u8]) -> Vec<u8> {
+    let mut packet = vec![0; 40];
+    packet[0] = 0x60;
+    packet[6] = 58;
+    packet[8..24].copy_from_slice(&source.octets());
+    packet[24..40].copy_from_slice(&Ipv6Addr::LOCALHOST.octets());
+    packet.extend_from_slice(message);
+    packet
+}
+
+fn echo_reply_v4(identifier: u16) -> Vec<u8> {
+    let reply = Icmpv4Message::EchoReply {
+        identifier,
+        sequence: 1,
+        payload: vec![],
+    };
+    t!(reply.to_bytes())
+}
+
+#[test]
+fn pass_and_block() {
+    let mut filter = IcmpTypeFilter::default();
+    assert_eq!(filter, IcmpTypeFilter::pass_all());
+    assert!((0..=255).all(|message_type| filter.passes(message_type)));
+
+    filter.block(ICMP_ECHO);
+    filter.block(255);
+    assert!(!filter.passes(ICMP_ECHO));
+    assert!(!filter.passes(255));
+    assert!(filter.passes(ICMP_ECHO_REPLY));
+    assert!(filter.passes(254));
+
+    let mut filter = IcmpTypeFilter::block_all();
+    assert!((0..=255).all(|message_type| !filter.passes(message_type)));
+    filter.pass(ICMPV6_ECHO_REPLY);
+    filter.pass(31);
+    filter.pass(32);
+    assert_eq!((0..=255).filter(|&message_type| filter.passes(message_type)).count(), 3);
+    filter.block(ICMPV6_ECHO_REPLY);
+    assert!(!filter.passes(ICMPV6_ECHO_REPLY));
+}
+
+#[test]
+fn type_filter_v4() {
+    let socket = t!(IcmpSocket::connect(IpAddr::V4(Ipv4Addr::LOCALHOST)));
+    assert_eq!(t!(socket.type_filter()), IcmpTypeFilter::pass_all());
+
+    let mut filter = IcmpTypeFilter::pass_all();
+    filter.block(ICMP_ECHO);
+    filter.block(31);
+    t!(socket.set_type_filter(&filter));
+    assert_eq!(t!(socket.type_filter()), filter);
+
+    // Types above 31 are out of reach of ICMP_FILTER and always pass
+    let mut blocked = filter;
+    blocked.block(42);
+    t!(socket.set_type_filter(&blocked));
+    assert_eq!(t!(socket.type_filter()), filter);
+
+    let mut filter = IcmpTypeFilter::block_all();
+    filter.pass(ICMP_ECHO_REPLY);
+    t!(socket.set_type_filter(&filter));
+    let filter = t!(socket.type_filter());
+    assert!(filter.passes(ICMP_ECHO_REPLY));
+    assert!((1..32).all(|message_type| !filter.passes(message_type)));
+    assert!((32..=255).all(|message_type| filter.passes(message_type)));
+}
+
+#[test]
+fn type_filter_v6() {
+    let socket = t!(IcmpSocket::connect(IpAddr::V6(Ipv6Addr::LOCALHOST)));
+    assert_eq!(t!(socket.type_filter()), IcmpTypeFilter::pass_all());
+
+    let mut filter = IcmpTypeFilter::block_all();
+    filter.pass(ICMPV6_ECHO_REPLY);
+    filter.pass(1);
+    t!(socket.set_type_filter(&filter));
+    assert_eq!(t!(socket.type_filter()), filter);
+}
+
+#[test]
+fn filtered_echo_v4() {
+    let socket = t!(IcmpSocket::connect(IpAddr::V4(Ipv4Addr::LOCALHOST)));
+    t!(socket.set_read_timeout(Some(Duration::from_secs(1))));
+    let mut filter = IcmpTypeFilter::pass_all();
+    filter.block(ICMP_ECHO);
+    t!(socket.set_type_filter(&filter));
+
+    let request = Icmpv4Message::Echo {
+        identifier: 0x7016,
+        sequence: 1,
+        payload: b"filtered_echo_v4".to_vec(),
+    };
+    t!(socket.send(&t!(request.to_bytes())));
+
+    // The request looped back to this socket is dropped by the kernel
+    let mut buf = [0u8; 1024];
+    loop {
+        let (size, _) = t!(socket.recv_message(&mut buf));
+        assert_ne!(buf[0], ICMP_ECHO);
+        if let Ok(Icmpv4Message::EchoReply { identifier: 0x7016, .. }) = Icmpv4Message::decode(&buf[..size]) {
+            break;
+        }
+    }
+}
+
+#[test]
+fn block_all_but_echo_reply_v4() {
+    let socket = t!(IcmpSocket::connect(IpAddr::V4(Ipv4Addr::LOCALHOST)));
+    t!(socket.set_read_timeout(Some(Duration::from_secs(1))));
+    let mut filter = IcmpTypeFilter::block_all();
+    filter.pass(ICMP_ECHO_REPLY);
+    t!(socket.set_type_filter(&filter));
+
+    let request = Icmpv4Message::Echo {
+        identifier: 0x7116,
+        sequence: 1,
+        payload: b"block_all_but_echo_reply_v4".to_vec(),
+    };
+    t!(socket.send(&t!(request.to_bytes())));
+
+    let mut buf = [0u8; 1024];
+    loop {
+        let (size, _) = t!(socket.recv_message(&mut buf));
+        assert_eq!(buf[0], ICMP_ECHO_REPLY);
+        if let Ok(Icmpv4Message::EchoReply { identifier: 0x7116, .. }) = Icmpv4Message::decode(&buf[..size]) {
+            break;
+        }
+    }
+}
+
+#[test]
+fn filtered_echo_v6() {
+    let socket = t!(IcmpSocket::connect(IpAddr::V6(Ipv6Addr::LOCALHOST)));
+    t!(socket.set_read_timeout(Some(Duration::from_secs(1))));
+    let mut filter = IcmpTypeFilter::block_all();
+    filter.pass(ICMPV6_ECHO_REPLY);
+    t!(socket.set_type_filter(&filter));
+
+    let request = Icmpv6Message::EchoRequest {
+        identifier: 0x7016,
+        sequence: 1,
+        payload: b"filtered_echo_v6".to_vec(),
+    };
+    t!(socket.send(&t!(request.to_bytes())));
+
+    let mut buf = [0u8; 1024];
+    loop {
+        let (size, _) = t!(socket.recv_message(&mut buf));
+        assert_eq!(buf[0], ICMPV6_ECHO_REPLY);
+        if let Ok(Icmpv6Message::EchoReply { identifier: 0x7016, .. }) = Icmpv6Message::decode(&buf[..size]) {
+            break;
+        }
+    }
+}
+
+#[test]
+fn bpf_program_v4() {
+    let source = Ipv4Addr::new(192, 0, 2, 1);
+    let program = t!(BpfBuilder::new(AddressFamily::V4)
+        .message_type(ICMP_ECHO_REPLY)
+        .code(0)
+        .identifier(0x7017)
+        .source(IpAddr::V4(source))
+        .build());
+
+    for &header_len in &[20, 24, 60] {
+        let packet = ipv4_packet(header_len, source, &echo_reply_v4(0x7017));
+        assert_eq!(run(&program, &packet, 0), u32::MAX);
+
+        // Each condition on its own
+        assert_eq!(run(&program, &ipv4_packet(header_len, source, &echo_reply_v4(0x7018)), 0), 0);
+        assert_eq!(run(&program, &ipv4_packet(header_len, Ipv4Addr::LOCALHOST, &echo_reply_v4(0x7017)), 0), 0);
+        let mut message = echo_reply_v4(0x7017);
+        message[0] = ICMP_ECHO;
+        assert_eq!(run(&program, &ipv4_packet(header_len, source, &message), 0), 0);
+        message[0] = ICMP_ECHO_REPLY;
+        message[1] = 1;
+        assert_eq!(run(&program, &ipv4_packet(header_len, source, &message), 0), 0);
+
+        // Truncated
+        assert_eq!(run(&program, &packet[..header_len + 5], 0), 0);
+    }
+
+    let everything = t!(BpfBuilder::new(AddressFamily::V4).build());
+    assert_eq!(run(&everything, &ipv4_packet(20, source, &[]), 0), u32::MAX);
+}
+
+#[test]
+fn bpf_program_v6() {
+    let source = Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1);
+    let program = t!(BpfBuilder::new(AddressFamily::V6)
+        .message_type(ICMPV6_ECHO_REPLY)
+        .identifier(0x7017)
+        .source(IpAddr::V6(source))
+        .build());
+
+    let reply = |identifier| t!(Icmpv6Message::EchoReply {
+        identifier,
+        sequence: 1,
+        payload: vec![],
+    }.to_bytes());
+
+    // Raw IPv6 sockets receive the message without the IPv6 header
+    assert_eq!(run(&program, &ipv6_packet(source, &reply(0x7017)), 40), u32::MAX);
+    assert_eq!(run(&program, &ipv6_packet(source, &reply(0x1770)), 40), 0);
+    for i in 0..16 {
+        let mut other = source.octets();
+        other[i] ^= 1;
+        assert_eq!(run(&program, &ipv6_packet(Ipv6Addr::from(other), &reply(0x7017)), 40), 0);
+    }
+
+    assert!(BpfBuilder::new(AddressFamily::V6).source(IpAddr::V4(Ipv4Addr::LOCALHOST)).build().is_err());
+    assert!(BpfBuilder::new(AddressFamily::V4).source(IpAddr::V6(source)).build().is_err());
+}
+
+#[test]
+fn attach_filter_v4() {
+    let socket = t!(IcmpSocket::connect(IpAddr::V4(Ipv4Addr::LOCALHOST)));
+    t!(socket.set_read_timeout(Some(Duration::from_secs(1))));
+    let program = t!(BpfBuilder::new(AddressFamily::V4)
+        .message_type(ICMP_ECHO_REPLY)
+        .identifier(0x7017)
+        .source(IpAddr::V4(Ipv4Addr::LOCALHOST))
+        .build());
+    t!(socket.attach_filter(&program));
+
+    // Neither the requests looped back nor the reply to another prober get through
+    for &identifier in &[0x7018, 0x7017] {
+        let request = Icmpv4Message::Echo {
+            identifier,
+            sequence: 1,
+            payload: b"attach_filter_v4".to_vec(),
+        };
+        t!(socket.send(&t!(request.to_bytes())));
+    }
+
+    let mut buf = [0u8; 1024];
+    let (size, _) = t!(socket.recv_message(&mut buf));
+    assert_eq!(t!(Icmpv4Message::decode(&buf[..size])), Icmpv4Message::EchoReply {
+        identifier: 0x7017,
+        sequence: 1,
+        payload: b"attach_filter_v4".to_vec(),
+    });
+
+    t!(socket.detach_filter());
+    t!(socket.send(&echo_reply_v4(0x7018)));
+    let (size, _) = t!(socket.recv_message(&mut buf));
+    assert_eq!(&buf[..size], &echo_reply_v4(0x7018)[..]);
+}
+
+#[test]
+fn attach_filter_v6() {
+    let socket = t!(IcmpSocket::connect(IpAddr::V6(Ipv6Addr::LOCALHOST)));
+    t!(socket.set_read_timeout(Some(Duration::from_secs(1))));
+    let program = t!(BpfBuilder::new(AddressFamily::V6)
+        .message_type(ICMPV6_ECHO_REPLY)
+        .identifier(0x7017)
+        .source(IpAddr::V6(Ipv6Addr::LOCALHOST))
+        .build());
+    t!(socket.attach_filter(&program));
+
+    for &identifier in &[0x7018, 0x7017] {
+        let request = Icmpv6Message::EchoRequest {
+            identifier,
+            sequence: 1,
+            payload: b"attach_filter_v6".to_vec(),
+        };
+        t!(socket.send(&t!(request.to_bytes())));
+    }
+
+    let mut buf = [0u8; 1024];
+    let (size, _) = t!(socket.recv_message(&mut buf));
+    assert_eq!(t!(Icmpv6Message::decode(&buf[..size])), Icmpv6Message::EchoReply {
+        identifier: 0x7017,
+        sequence: 1,
+        payload: b"attach_filter_v6".to_vec(),
+    });
+}
@@ -0,0 +1,224 @@
+use std::io::ErrorKind;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::time::Duration;
+
+use crate::IcmpSocket;
+use crate::checksum::checksum;
+use crate::ip::{Ipv4Header, Ipv4HeaderBuilder};
+use crate::v4::Icmpv4Message;
+
+/// IPv4 header with a Record Route option, carrying an ICMP echo.
+const HEADER_WITH_OPTIONS: &[u8] = &[
+    0x47, 0x00, 0x00, 0x2c, 0xab, 0xcd, 0x40, 0x00, 0x3f, 0x01, 0x12, 0x34, 0xc0, 0x00, 0x02, 0x01, 0xc6, 0x33,
+    0x64, 0x02, 0x07, 0x07, 0x04, 0x00, 0x00, 0x00, 0x00, 0x00,
+];
+
+#[test]
+fn decode_with_options() {
+    let header = t!(Ipv4Header::decode(HEADER_WITH_OPTIONS));
+
+    assert_eq!(header, Ipv4Header {
+        tos: 0,
+        total_len: 44,
+        identification: 0xabcd,
+        dont_fragment: true,
+        more_fragments: false,
+        fragment_offset: 0,
+        ttl: 63,
+        protocol: 1,
+        checksum: 0x1234,
+        source: Ipv4Addr::new(192, 0, 2, 1),
+        destination: Ipv4Addr::new(198, 51, 100, 2),
+        options: vec![0x07, 0x07, 0x04, 0x00, 0x00, 0x00, 0x00, 0x00],
+    });
+    assert_eq!(header.header_len(), 28);
+}
+
+#[test]
+fn decode_fragment() {
+    let mut bytes = HEADER_WITH_OPTIONS.to_vec();
+    bytes[0] = 0x45;
+    bytes[6] = 0x20;
+    bytes[7] = 0xb9;
+
+    let header = t!(Ipv4Header::decode(&bytes));
+    assert!(!header.dont_fragment);
+    assert!(header.more_fragments);
+    assert_eq!(header.fragment_offset, 185);
+    assert!(header.options.is_empty());
+    assert_eq!(header.header_len(), 20);
+}
+
+#[test]
+fn decode_invalid() {
+    assert!(Ipv4Header::decode(&HEADER_WITH_OPTIONS[..19]).is_err());
+    // Options exceed the buffer
+    assert!(Ipv4Header::decode(&HEADER_WITH_OPTIONS[..24]).is_err());
+
+    let mut bytes = HEADER_WITH_OPTIONS.to_vec();
+    bytes[0] = 0x67;
+    assert!(Ipv4Header::decode(&bytes).is_err());
+
+    bytes[0] = 0x44;
+    assert!(Ipv4Header::decode(&bytes).is_err());
+}
+
+#[test]
+fn encode() {
+    let header = t!(Ipv4Header::decode(HEADER_WITH_OPTIONS));
+    assert_eq!(header.encoded_len(), 28);
+    assert_eq!(t!(header.to_bytes()), HEADER_WITH_OPTIONS);
+
+    let mut header = header;
+    header.options.push(0);
+    let mut buf = [0u8; 64];
+    assert!(header.encode(&mut buf).is_err());
+    assert_eq!(header.to_bytes().unwrap_err().kind(), ErrorKind::InvalidInput);
+    header.options = vec![0; 44];
+    assert!(header.encode(&mut buf).is_err());
+    assert_eq!(header.to_bytes().unwrap_err().kind(), ErrorKind::InvalidInput);
+    header.options.clear();
+    assert!(header.encode(&mut buf[..19]).is_err());
+}
+
+#[test]
+fn header_checksum() {
+    let bytes = [
+        0x45, 0x00, 0x00, 0x73, 0x00, 0x00, 0x40, 0x00, 0x40, 0x11, 0xb8, 0x61, 0xc0, 0xa8, 0x00, 0x01, 0xc0, 0xa8,
+        0x00, 0xc7,
+    ];
+    let mut header = t!(Ipv4Header::decode(&bytes));
+    assert_eq!(header.compute_checksum(), 0xb861);
+    assert!(header.verify_checksum());
+
+    header.ttl -= 1;
+    assert!(!header.verify_checksum());
+
+    // The fixture carries a made-up checksum
+    assert!(!t!(Ipv4Header::decode(HEADER_WITH_OPTIONS)).verify_checksum());
+}
+
+#[test]
+fn builder() {
+    let source = Ipv4Addr::new(192, 0, 2, 1);
+    let destination = Ipv4Addr::new(198, 51, 100, 2);
+    let builder = Ipv4HeaderBuilder::new(source, destination)
+        .tos(0x10)
+        .identification(0xabcd)
+        .dont_fragment(true)
+        .ttl(17)
+        .options(vec![0x01, 0x01, 0x01, 0x00]);
+
+    let header = t!(builder.build(8));
+    assert_eq!(header, Ipv4Header {
+        tos: 0x10,
+        total_len: 32,
+        identification: 0xabcd,
+        dont_fragment: true,
+        more_fragments: false,
+        fragment_offset: 0,
+        ttl: 17,
+        protocol: 1,
+        checksum: header.checksum,
+        source,
+        destination,
+        options: vec![0x01, 0x01, 0x01, 0x00],
+    });
+    assert!(header.verify_checksum());
+    assert_eq!(checksum(&t!(header.to_bytes())), 0);
+
+    let datagram = t!(builder.datagram(&[0xaa; 8]));
+    assert_eq!(&datagram[..24], &t!(header.to_bytes())[..]);
+    assert_eq!(&datagram[24..], &[0xaa; 8]);
+
+    let defaults = t!(Ipv4HeaderBuilder::new(source, destination).build(0));
+    assert_eq!((defaults.ttl, defaults.protocol, defaults.total_len), (64, 1, 20));
+    assert!(!defaults.dont_fragment);
+
+    assert!(builder.build(65535 - 24).is_ok());
+    assert!(builder.build(65535 - 23).is_err());
+    assert!(builder.clone().options(vec![1, 1, 0]).build(0).is_err());
+    assert!(builder.options(vec![1; 44]).build(0).is_err());
+}
+
+#[test]
+fn header_included_loopback() {
+    let localhost = Ipv4Addr::new(127, 0, 0, 1);
+    let socket = t!(IcmpSocket::connect(IpAddr::V4(localhost)));
+    t!(socket.set_read_timeout(Some(Duration::from_secs(1))));
+    assert!(!t!(socket.header_included()));
+    t!(socket.set_header_included(true));
+    assert!(t!(socket.header_included()));
+
+    let request = Icmpv4Message::Echo {
+        identifier: 0x7018,
+        sequence: 1,
+        payload: b"header_included".to_vec(),
+    };
+    let datagram = t!(Ipv4HeaderBuilder::new(localhost, localhost)
+        .identification(0x4242)
+        .dont_fragment(true)
+        .ttl(17)
+        .options(vec![0x01, 0x01, 0x01, 0x00])
+        .datagram(&t!(request.to_bytes())));
+    assert_eq!(t!(socket.send(&datagram)), datagram.len());
+
+    // Loopback delivers the request as sent
+    let mut buf = [0u8; 1024];
+    loop {
+        let (header, message) = t!(socket.recv_packet(&mut buf));
+        if message == request {
+            assert_eq!(header.identification, 0x4242);
+            assert!(header.dont_fragment);
+            assert_eq!(header.ttl, 17);
+            assert_eq!(header.options, vec![0x01, 0x01, 0x01, 0x00]);
+            assert!(header.verify_checksum());
+            break;
+        }
+    }
+
+    let socket = t!(IcmpSocket::connect(IpAddr::V6(Ipv6Addr::LOCALHOST)));
+    assert!(socket.set_header_included(true).is_err());
+    assert!(!t!(socket.header_included()));
+}
+
+#[test]
+fn recv_packet_loopback() {
+    let localhost = Ipv4Addr::new(127, 0, 0, 1);
+    let socket = t!(IcmpSocket::connect(IpAddr::V4(localhost)));
+    t!(socket.set_read_timeout(Some(Duration::from_secs(1))));
+    t!(socket.set_ttl(33));
+
+    let request = Icmpv4Message::Echo {
+        identifier: 0x5005,
+        sequence: 9,
+        payload: b"recv_packet".to_vec(),
+    };
+    t!(socket.send(&t!(request.to_bytes())));
+
+    let mut buf = [0u8; 1024];
+    loop {
+        let (header, message) = t!(socket.recv_packet(&mut buf));
+        assert_eq!(header.protocol, 1);
+        assert_eq!(header.source, localhost);
+        assert_eq!(header.destination, localhost);
+
+        // Loopback delivers our own request to raw sockets as well.
+        if message == request {
+            assert_eq!(header.ttl, 33);
+        }
+        if let Icmpv4Message::EchoReply { identifier: 0x5005, sequence, .. } = message {
+            assert_eq!(sequence, 9);
+            assert_eq!(usize::from(header.total_len), header.header_len() + request.encoded_len());
+            break;
+        }
+    }
+}
+
+#[test]
+fn recv_packet_v6() {
+    let socket = t!(IcmpSocket::connect(IpAddr::V6(Ipv6Addr::LOCALHOST)));
+    let mut buf = [0u8; 64];
+
+    assert!(socket.recv_packet(&mut buf).is_err());
+}
@@ -0,0 +1,211 @@
+use std::ffi::CString;
+use std::net::{IpAddr, Ipv6Addr};
+#[cfg(target_os = "linux")]
+use std::os::unix::io::AsRawFd;
+use std::time::{Duration, Instant};
+
+use crate::IcmpSocket;
+use crate::mld::{self, Mldv2Query, MulticastAddressRecord, RecordType};
+use crate::v6::Icmpv6Message;
+
+/// MLDv2 report sent by Linux in response to a general query, listing three groups.
+const REPORT_V2: &[u8] = &[
+    0x8f, 0x00, 0x71, 0xc1, 0x00, 0x00, 0x00, 0x03, 0x02, 0x00, 0x00, 0x00, 0xff, 0x02, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x16, 0x02, 0x00, 0x00, 0x00, 0xff, 0x02, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0xff, 0x00, 0x00, 0x02, 0x02, 0x00, 0x00, 0x00, 0xff, 0x02,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0xff, 0x00, 0x00, 0x01,
+];
+
+/// MLDv2 general query with a maximum response delay of 100 ms.
+const GENERAL_QUERY: &[u8] = &[
+    0x82, 0x00, 0x7d, 0x46, 0x00, 0x64, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x7d, 0x00, 0x00,
+];
+
+fn solicited_node(last: u16) -> Ipv6Addr {
+    Ipv6Addr::new(0xff02, 0, 0, 0, 0, 1, 0xff00, last)
+}
+
+fn round_trip(message: Icmpv6Message) {
+    let bytes = t!(message.to_bytes());
+    assert_eq!(bytes.len(), message.encoded_len());
+    assert_eq!(bytes[0], message.message_type());
+    assert_eq!(t!(Icmpv6Message::decode(&bytes)), message);
+}
+
+#[test]
+fn decode_report_v2() {
+    let record = |multicast_address| MulticastAddressRecord {
+        record_type: RecordType::ModeIsExclude,
+        multicast_address,
+        sources: vec![],
+        auxiliary_data: vec![],
+    };
+    let expected = Icmpv6Message::MulticastListenerReportV2 {
+        records: vec![record(mld::ALL_MLDV2_ROUTERS), record(solicited_node(2)), record(solicited_node(1))],
+    };
+    assert_eq!(t!(Icmpv6Message::decode(REPORT_V2)), expected);
+}
+
+#[test]
+fn decode_general_query() {
+    let expected = Icmpv6Message::MulticastListenerQuery {
+        max_response_code: 100,
+        multicast_address: Ipv6Addr::UNSPECIFIED,
+        v2: Some(Mldv2Query {
+            suppress_router_processing: false,
+            robustness: 2,
+            query_interval_code: 125,
+            sources: vec![],
+        }),
+    };
+    assert_eq!(t!(Icmpv6Message::decode(GENERAL_QUERY)), expected);
+
+    // Only the checksum differs; the kernel fills it in.
+    let mut bytes = t!(expected.to_bytes());
+    bytes[2..4].copy_from_slice(&GENERAL_QUERY[2..4]);
+    assert_eq!(bytes, GENERAL_QUERY);
+}
+
+#[test]
+fn messages_round_trip() {
+    let group = Ipv6Addr::new(0xff05, 0, 0, 0, 0, 0, 0, 0x1234);
+    let source = Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1);
+
+    round_trip(Icmpv6Message::MulticastListenerQuery {
+        max_response_code: 10000,
+        multicast_address: group,
+        v2: None,
+    });
+    round_trip(Icmpv6Message::MulticastListenerQuery {
+        max_response_code: 0x8123,
+        multicast_address: group,
+        v2: Some(Mldv2Query {
+            suppress_router_processing: true,
+            robustness: 7,
+            query_interval_code: 0x85,
+            sources: vec![source, Ipv6Addr::LOCALHOST],
+        }),
+    });
+    round_trip(Icmpv6Message::MulticastListenerReport {
+        multicast_address: group,
+    });
+    round_trip(Icmpv6Message::MulticastListenerDone {
+        multicast_address: group,
+    });
+    round_trip(Icmpv6Message::MulticastListenerReportV2 {
+        records: vec![],
+    });
+    round_trip(Icmpv6Message::MulticastListenerReportV2 {
+        records: vec![
+            MulticastAddressRecord {
+                record_type: RecordType::AllowNewSources,
+                multicast_address: group,
+                sources: vec![source],
+                auxiliary_data: vec![1, 2, 3, 4],
+            },
+            MulticastAddressRecord {
+                record_type: RecordType::Unassigned(42),
+                multicast_address: group,
+                sources: vec![],
+                auxiliary_data: vec![],
+            },
+        ],
+    });
+}
+
+#[test]
+fn invalid_messages() {
+    // Neither MLDv1 nor MLDv2 query length
+    assert!(Icmpv6Message::decode(&GENERAL_QUERY[..26]).is_err());
+
+    // Query with more sources than it carries
+    let mut bytes = GENERAL_QUERY.to_vec();
+    bytes[27] = 1;
+    assert!(Icmpv6Message::decode(&bytes).is_err());
+
+    // Report with more records than it carries
+    let mut bytes = REPORT_V2.to_vec();
+    bytes[7] = 4;
+    assert!(Icmpv6Message::decode(&bytes).is_err());
+    assert!(Icmpv6Message::decode(&REPORT_V2[..60]).is_err());
+
+    let report = Icmpv6Message::MulticastListenerReportV2 {
+        records: vec![MulticastAddressRecord {
+            record_type: RecordType::ModeIsInclude,
+            multicast_address: mld::ALL_NODES,
+            sources: vec![],
+            auxiliary_data: vec![0; 3],
+        }],
+    };
+    let mut buf = vec![0; report.encoded_len()];
+    assert!(report.encode(&mut buf).is_err());
+}
+
+#[test]
+fn response_codes() {
+    assert_eq!(mld::max_response_delay(1000), Duration::from_millis(1000));
+    assert_eq!(mld::max_response_delay(0x8000), Duration::from_millis(0x1000 << 3));
+    assert_eq!(mld::max_response_delay(0xffff), Duration::from_millis(0x1fff << 10));
+
+    for &millis in &[0, 1, 1000, 0x7fff, 0x8000, 0x9000, 100_000, 1_000_000, 0x1fff << 10] {
+        let delay = Duration::from_millis(millis);
+        let code = mld::max_response_code(delay);
+        assert!(mld::max_response_delay(code) <= delay);
+    }
+    assert_eq!(mld::max_response_code(Duration::from_millis(0x8000)), 0x8000);
+    assert_eq!(mld::max_response_code(Duration::from_secs(86400)), 0xffff);
+
+    let query = Mldv2Query {
+        query_interval_code: 125,
+        ..Mldv2Query::default()
+    };
+    assert_eq!(query.query_interval(), Duration::from_secs(125));
+    let query = Mldv2Query {
+        query_interval_code: 0x81,
+        ..Mldv2Query::default()
+    };
+    assert_eq!(query.query_interval(), Duration::from_secs(0x11 << 3));
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn general_query_on_link() {
+    // The host answers its own queries, which are looped back on the link.
+    let name = CString::new("eth0").unwrap();
+    let interface = unsafe { libc::if_nametoindex(name.as_ptr()) };
+    if interface == 0 {
+        return;
+    }
+
+    let socket = t!(IcmpSocket::connect(IpAddr::V6(mld::ALL_NODES)));
+    t!(socket.set_multicast_if_v6(interface));
+    assert_eq!(t!(socket.multicast_if_v6()), interface);
+    t!(socket.join_multicast_v6(&mld::ALL_MLDV2_ROUTERS, interface));
+    t!(socket.set_read_timeout(Some(Duration::from_secs(5))));
+
+    t!(mld::send_general_query(&socket, Duration::from_millis(100)));
+    // The Router Alert option went out with the query only, not as a socket option.
+    let mut options = [0u8; 8];
+    let mut len = options.len() as libc::socklen_t;
+    let ret = unsafe {
+        libc::getsockopt(socket.as_raw_fd(), libc::IPPROTO_IPV6, libc::IPV6_HOPOPTS,
+                         options.as_mut_ptr() as *mut libc::c_void, &mut len)
+    };
+    assert_eq!(ret, 0);
+    assert_eq!(len, 0);
+    let start = Instant::now();
+    let reports = t!(mld::collect_reports(&socket, Duration::from_secs(1)));
+    assert!(start.elapsed() >= Duration::from_secs(1));
+    assert_eq!(t!(socket.read_timeout()), Some(Duration::from_secs(5)));
+
+    let listening = reports.iter().any(|(_, report)| match *report {
+        Icmpv6Message::MulticastListenerReportV2 { ref records } => {
+            records.iter().any(|record| record.multicast_address == mld::ALL_MLDV2_ROUTERS)
+        },
+        _ => false,
+    });
+    assert!(listening, "no report for {} in {:?}", mld::ALL_MLDV2_ROUTERS, reports);
+
+    t!(socket.leave_multicast_v6(&mld::ALL_MLDV2_ROUTERS, interface));
+}
@@ -0,0 +1,219 @@
+use std::io::ErrorKind;
+use std::net::Ipv6Addr;
+
+use crate::ndp::{NdpOption, PrefixInformation, RouteInformation, RoutePreference};
+use crate::v6::Icmpv6Message;
+
+/// Neighbor Solicitation for fd00::1 with the source link-layer address 02:00:00:00:00:02.
+const NEIGHBOR_SOLICITATION: &[u8] = &[
+    0x87, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xfd, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x01, 0x01, 0x02, 0x00, 0x00, 0x00, 0x00, 0x02,
+];
+
+/// Router Advertisement with M, O and high preference, a prefix, the MTU and a DNS server.
+const ROUTER_ADVERTISEMENT: &[u8] = &[
+    0x86, 0x00, 0x00, 0x00, 0x40, 0xc8, 0x07, 0x08, 0x00, 0x00, 0x75, 0x30, 0x00, 0x00, 0x03, 0xe8, 0x03, 0x04,
+    0x40, 0xc0, 0x00, 0x27, 0x8d, 0x00, 0x00, 0x09, 0x3a, 0x80, 0x00, 0x00, 0x00, 0x00, 0xfd, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x05, 0x01, 0x00, 0x00, 0x00, 0x00,
+    0x05, 0xdc, 0x19, 0x03, 0x00, 0x00, 0x00, 0x00, 0x0e, 0x10, 0xfd, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x35,
+];
+
+fn ethernet() -> Vec<u8> {
+    vec![0x02, 0x00, 0x00, 0x00, 0x00, 0x02]
+}
+
+fn round_trip(message: Icmpv6Message) {
+    let bytes = t!(message.to_bytes());
+    assert_eq!(bytes.len(), message.encoded_len());
+    assert_eq!(bytes[0], message.message_type());
+    assert_eq!(t!(Icmpv6Message::decode(&bytes)), message);
+}
+
+#[test]
+fn decode_neighbor_solicitation() {
+    let expected = Icmpv6Message::NeighborSolicitation {
+        target: Ipv6Addr::new(0xfd00, 0, 0, 0, 0, 0, 0, 1),
+        options: vec![NdpOption::SourceLinkLayerAddress(ethernet())],
+    };
+    assert_eq!(t!(Icmpv6Message::decode(NEIGHBOR_SOLICITATION)), expected);
+    assert_eq!(t!(expected.to_bytes()), NEIGHBOR_SOLICITATION);
+}
+
+#[test]
+fn decode_router_advertisement() {
+    let expected = Icmpv6Message::RouterAdvertisement {
+        hop_limit: 64,
+        managed: true,
+        other_config: true,
+        preference: RoutePreference::High,
+        router_lifetime: 1800,
+        reachable_time: 30000,
+        retrans_timer: 1000,
+        options: vec![
+            NdpOption::PrefixInformation(PrefixInformation {
+                prefix_len: 64,
+                on_link: true,
+                autonomous: true,
+                valid_lifetime: 2592000,
+                preferred_lifetime: 604800,
+                prefix: Ipv6Addr::new(0xfd00, 0, 0, 0, 0, 0, 0, 0),
+            }),
+            NdpOption::Mtu(1500),
+            NdpOption::RecursiveDnsServers {
+                lifetime: 3600,
+                servers: vec![Ipv6Addr::new(0xfd00, 0, 0, 0, 0, 0, 0, 0x35)],
+            },
+        ],
+    };
+    assert_eq!(t!(Icmpv6Message::decode(ROUTER_ADVERTISEMENT)), expected);
+    assert_eq!(t!(expected.to_bytes()), ROUTER_ADVERTISEMENT);
+}
+
+#[test]
+fn messages_round_trip() {
+    let target = Ipv6Addr::new(0xfe80, 0, 0, 0, 0, 0, 0, 1);
+
+    round_trip(Icmpv6Message::RouterSolicitation {
+        options: vec![],
+    });
+    round_trip(Icmpv6Message::RouterSolicitation {
+        options: vec![NdpOption::SourceLinkLayerAddress(ethernet())],
+    });
+    for &preference in &[RoutePreference::High, RoutePreference::Medium, RoutePreference::Low, RoutePreference::Reserved] {
+        round_trip(Icmpv6Message::RouterAdvertisement {
+            hop_limit: 0,
+            managed: false,
+            other_config: true,
+            preference,
+            router_lifetime: 0,
+            reachable_time: 0,
+            retrans_timer: 0,
+            options: vec![],
+        });
+    }
+    for flags in 0..8 {
+        round_trip(Icmpv6Message::NeighborAdvertisement {
+            router: flags & 1 != 0,
+            solicited: flags & 2 != 0,
+            override_flag: flags & 4 != 0,
+            target,
+            options: vec![NdpOption::TargetLinkLayerAddress(ethernet())],
+        });
+    }
+    round_trip(Icmpv6Message::Redirect {
+        target,
+        destination: Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1),
+        options: vec![
+            NdpOption::TargetLinkLayerAddress(ethernet()),
+            NdpOption::RedirectedHeader(vec![0x60; 48]),
+        ],
+    });
+}
+
+#[test]
+fn options_round_trip() {
+    let options = vec![
+        NdpOption::SourceLinkLayerAddress(vec![1; 14]),
+        NdpOption::RouteInformation(RouteInformation {
+            prefix_len: 0,
+            preference: RoutePreference::Low,
+            lifetime: u32::MAX,
+            prefix: Ipv6Addr::UNSPECIFIED,
+        }),
+        NdpOption::RouteInformation(RouteInformation {
+            prefix_len: 48,
+            preference: RoutePreference::High,
+            lifetime: 600,
+            prefix: Ipv6Addr::new(0x2001, 0xdb8, 0x1, 0, 0, 0, 0, 0),
+        }),
+        NdpOption::RouteInformation(RouteInformation {
+            prefix_len: 96,
+            preference: RoutePreference::Medium,
+            lifetime: 600,
+            prefix: Ipv6Addr::new(0x2001, 0xdb8, 0x1, 0x2, 0x3, 0x4, 0, 0),
+        }),
+        NdpOption::RecursiveDnsServers {
+            lifetime: 0,
+            servers: vec![Ipv6Addr::LOCALHOST, Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 0x53)],
+        },
+        NdpOption::DnsSearchList {
+            lifetime: 3600,
+            domains: vec!["example.com".to_string(), "lab.example.org".to_string()],
+        },
+        NdpOption::Unknown {
+            option_type: 14,
+            data: vec![0xaa; 6],
+        },
+    ];
+    round_trip(Icmpv6Message::RouterSolicitation {
+        options,
+    });
+}
+
+#[test]
+fn dns_search_list_encoding() {
+    let option = NdpOption::DnsSearchList {
+        lifetime: 60,
+        domains: vec!["example.com.".to_string()],
+    };
+    let mut buf = [0xff; 24];
+    assert_eq!(t!(option.encode(&mut buf)), 24);
+    assert_eq!(&buf[..8], &[31, 3, 0, 0, 0, 0, 0, 60]);
+    assert_eq!(&buf[8..21], b"\x07example\x03com\x00");
+    assert_eq!(&buf[21..], &[0, 0, 0]);
+
+    let (decoded, len) = t!(NdpOption::decode(&buf));
+    assert_eq!(len, 24);
+    assert_eq!(decoded, NdpOption::DnsSearchList {
+        lifetime: 60,
+        domains: vec!["example.com".to_string()],
+    });
+
+    let long = NdpOption::DnsSearchList {
+        lifetime: 60,
+        domains: vec![format!("{}.com", "a".repeat(64))],
+    };
+    let mut buf = vec![0; long.encoded_len()];
+    assert!(long.encode(&mut buf).is_err());
+}
+
+#[test]
+fn empty_dns_options() {
+    let mut buf = [0; 8];
+    let servers = NdpOption::RecursiveDnsServers {
+        lifetime: 60,
+        servers: vec![],
+    };
+    assert_eq!(servers.encode(&mut buf).unwrap_err().kind(), ErrorKind::InvalidInput);
+
+    let domains = NdpOption::DnsSearchList {
+        lifetime: 60,
+        domains: vec![],
+    };
+    assert_eq!(domains.encode(&mut buf).unwrap_err().kind(), ErrorKind::InvalidInput);
+}
+
+#[test]
+fn invalid_options() {
+    // Zero length
+    let mut bytes = NEIGHBOR_SOLICITATION.to_vec();
+    bytes[25] = 0;
+    assert!(Icmpv6Message::decode(&bytes).is_err());
+
+    // Length beyond the message
+    bytes[25] = 2;
+    assert!(Icmpv6Message::decode(&bytes).is_err());
+
+    // Truncated option header
+    let mut bytes = NEIGHBOR_SOLICITATION.to_vec();
+    bytes.push(1);
+    assert!(Icmpv6Message::decode(&bytes).is_err());
+
+    // MTU option two units long
+    assert!(NdpOption::decode(&[5, 2, 0, 0, 0, 0, 5, 0xdc, 0, 0, 0, 0, 0, 0, 0, 0]).is_err());
+
+    // Truncated fixed part
+    assert!(Icmpv6Message::decode(&NEIGHBOR_SOLICITATION[..20]).is_err());
+    assert!(Icmpv6Message::decode(&ROUTER_ADVERTISEMENT[..12]).is_err());
+}
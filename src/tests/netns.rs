@@ -0,0 +1,108 @@
+//! Network namespaces for tests which need more than the loopback interface
+
+use std::ffi::CString;
+use std::fs;
+use std::panic;
+use std::process::Command;
+use std::sync::mpsc;
+use std::thread;
+
+/// Runs `ip` with `args` in the network namespace of the calling thread.
+pub fn ip(args: &str) -> bool {
+    Command::new("ip")
+        .args(args.split_whitespace())
+        .status()
+        .map(|status| status.success())
+        .unwrap_or(false)
+}
+
+fn new_netns() -> bool {
+    unsafe { libc::unshare(libc::CLONE_NEWNET) == 0 }
+}
+
+pub fn index(interface: &str) -> u32 {
+    let name = CString::new(interface).unwrap();
+    unsafe { libc::if_nametoindex(name.as_ptr()) }
+}
+
+/// Runs `test` on a thread moved to a new network namespace with only the loopback
+/// interface, so that the sysctls it sets do not leak to the host. Skips `test` if
+/// namespaces are not available.
+pub fn isolated<F: FnOnce() + Send + 'static>(name: &str, test: F) {
+    let name = name.to_string();
+    let result = thread::spawn(move || {
+        if !new_netns() || !ip("link set lo up") {
+            super::skip(&name, "network namespaces are not available");
+            return;
+        }
+        test();
+    }).join();
+
+    if let Err(err) = result {
+        panic::resume_unwind(err);
+    }
+}
+
+/// Runs `test` on a thread moved to a new network namespace, linked through the veth
+/// pair v0 (10.9.0.1, fe80::1, fd09::1) and v1 (10.9.0.2, fe80::2, fd09::2) to a second
+/// namespace that answers pings.
+///
+/// The second namespace routes 10.9.2.0/24 and fd09:2::/64 through the veth pair v2/v3,
+/// whose MTU is 1280 and which leads nowhere, and the first one routes them through the
+/// second. Skips `test` if namespaces or veth devices are not available.
+pub fn with_veth<F: FnOnce() + Send + 'static>(test: F) {
+    let (tid_tx, tid_rx) = mpsc::channel();
+    let (linked_tx, linked_rx) = mpsc::channel();
+    let (ready_tx, ready_rx) = mpsc::channel();
+
+    let peer = thread::spawn(move || {
+        if !new_netns() {
+            return;
+        }
+        tid_tx.send(unsafe { libc::gettid() }).unwrap();
+        if linked_rx.recv().is_err() {
+            return;
+        }
+        let configured = ip("link set lo up")
+            && ip("addr add 10.9.0.2/24 dev v1")
+            && ip("addr add fe80::2/64 dev v1 nodad")
+            && ip("addr add fd09::2/64 dev v1 nodad")
+            && ip("link set v1 up")
+            && ip("link add v2 mtu 1280 type veth peer name v3 mtu 1280")
+            && ip("link set v2 up")
+            && ip("link set v3 up")
+            && ip("route add 10.9.2.0/24 dev v2")
+            && ip("route add fd09:2::/64 dev v2")
+            && fs::write("/proc/sys/net/ipv4/ip_forward", "1").is_ok()
+            && fs::write("/proc/sys/net/ipv6/conf/all/forwarding", "1").is_ok();
+        ready_tx.send(configured).unwrap();
+        // Keep the namespace until the test is done
+        let _ = linked_rx.recv();
+    });
+
+    let local = thread::spawn(move || {
+        let tid = match tid_rx.recv() {
+            Ok(tid) => tid,
+            Err(..) => return,
+        };
+        if !new_netns() || !ip(&format!("link add v0 type veth peer name v1 netns {}", tid)) {
+            return;
+        }
+        assert!(ip("addr add 10.9.0.1/24 dev v0"));
+        assert!(ip("addr add fe80::1/64 dev v0 nodad"));
+        assert!(ip("addr add fd09::1/64 dev v0 nodad"));
+        assert!(ip("link set v0 up"));
+        assert!(ip("route add 10.9.2.0/24 via 10.9.0.2"));
+        assert!(ip("route add fd09:2::/64 via fd09::2"));
+        linked_tx.send(()).unwrap();
+        assert!(ready_rx.recv().unwrap());
+
+        test();
+    });
+
+    let result = local.join();
+    peer.join().unwrap();
+    if let Err(err) = result {
+        panic::resume_unwind(err);
+    }
+}
@@ -0,0 +1,139 @@
+use std::io::ErrorKind;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::{IcmpSocket, SocketKind};
+use crate::v4::Icmpv4Message;
+use crate::v6::Icmpv6Message;
+
+use super::ping::{kernel_identifier, with_ping_sockets};
+
+/// Opens a nonblocking datagram socket, which only receives replies to its own requests.
+fn connect(addr: IpAddr) -> IcmpSocket {
+    let socket = t!(IcmpSocket::connect_with_kind(addr, SocketKind::Datagram));
+    t!(socket.set_nonblocking(true));
+    socket
+}
+
+/// Retries `recv` until a message arrives, for up to a second.
+fn recv_retrying(socket: &IcmpSocket, buf: &mut [u8]) -> usize {
+    let deadline = Instant::now() + Duration::from_secs(1);
+    loop {
+        match socket.recv(buf) {
+            Ok(size) => return size,
+            Err(ref err) if err.kind() == ErrorKind::WouldBlock && Instant::now() < deadline => {
+                thread::sleep(Duration::from_millis(10));
+            },
+            Err(err) => panic!("recv failed: {}", err),
+        }
+    }
+}
+
+fn assert_would_block(socket: &IcmpSocket) {
+    let mut buf = [0u8; 64];
+    match socket.recv(&mut buf) {
+        Err(ref err) if err.kind() == ErrorKind::WouldBlock => {},
+        other => panic!("expected WouldBlock, got {:?}", other),
+    }
+}
+
+#[test]
+fn nonblocking_v4() {
+    with_ping_sockets("nonblocking_v4", || {
+        let socket = connect(IpAddr::V4(Ipv4Addr::LOCALHOST));
+        assert_would_block(&socket);
+
+        let request = Icmpv4Message::Echo {
+            identifier: 0x2323,
+            sequence: 1,
+            payload: b"nonblocking_v4".to_vec(),
+        };
+        t!(socket.send(&t!(request.to_bytes())));
+
+        let mut buf = [0u8; 1024];
+        let size = recv_retrying(&socket, &mut buf);
+        assert_eq!(t!(Icmpv4Message::decode(&buf[..size])), Icmpv4Message::EchoReply {
+            identifier: kernel_identifier(&socket),
+            sequence: 1,
+            payload: b"nonblocking_v4".to_vec(),
+        });
+        assert_would_block(&socket);
+
+        // Back in blocking mode, the read timeout applies again.
+        t!(socket.set_nonblocking(false));
+        t!(socket.set_read_timeout(Some(Duration::from_millis(50))));
+        let start = Instant::now();
+        assert_would_block(&socket);
+        assert!(start.elapsed() >= Duration::from_millis(50));
+    });
+}
+
+#[test]
+fn nonblocking_v6() {
+    with_ping_sockets("nonblocking_v6", || {
+        let socket = connect(IpAddr::V6(Ipv6Addr::LOCALHOST));
+        assert_would_block(&socket);
+
+        let request = Icmpv6Message::EchoRequest {
+            identifier: 0x2424,
+            sequence: 1,
+            payload: b"nonblocking_v6".to_vec(),
+        };
+        t!(socket.send(&t!(request.to_bytes())));
+
+        let mut buf = [0u8; 1024];
+        let size = recv_retrying(&socket, &mut buf);
+        assert_eq!(t!(Icmpv6Message::decode(&buf[..size])), Icmpv6Message::EchoReply {
+            identifier: kernel_identifier(&socket),
+            sequence: 1,
+            payload: b"nonblocking_v6".to_vec(),
+        });
+        assert_would_block(&socket);
+    });
+}
+
+#[cfg(feature = "mio")]
+#[test]
+fn mio_poll() {
+    use mio::{Events, Interest, Poll, Token};
+
+    with_ping_sockets("mio_poll", || {
+        let mut socket = connect(IpAddr::V4(Ipv4Addr::LOCALHOST));
+
+        let mut poll = t!(Poll::new());
+        let mut events = Events::with_capacity(4);
+        t!(poll.registry().register(&mut socket, Token(7), Interest::READABLE | Interest::WRITABLE));
+
+        // A fresh socket can be written to, but there is nothing to read yet.
+        t!(poll.poll(&mut events, Some(Duration::from_secs(1))));
+        let event = events.iter().next().expect("no event for a writable socket");
+        assert_eq!(event.token(), Token(7));
+        assert!(event.is_writable());
+        assert!(!event.is_readable());
+
+        t!(poll.registry().reregister(&mut socket, Token(8), Interest::READABLE));
+        let request = Icmpv4Message::Echo {
+            identifier: 0x2525,
+            sequence: 1,
+            payload: b"mio_poll".to_vec(),
+        };
+        t!(socket.send(&t!(request.to_bytes())));
+
+        t!(poll.poll(&mut events, Some(Duration::from_secs(1))));
+        let event = events.iter().next().expect("no event for a reply");
+        assert_eq!(event.token(), Token(8));
+        assert!(event.is_readable());
+
+        let mut buf = [0u8; 1024];
+        let size = t!(socket.recv(&mut buf));
+        assert_eq!(t!(Icmpv4Message::decode(&buf[..size])), Icmpv4Message::EchoReply {
+            identifier: kernel_identifier(&socket),
+            sequence: 1,
+            payload: b"mio_poll".to_vec(),
+        });
+        assert_would_block(&socket);
+
+        t!(poll.registry().deregister(&mut socket));
+    });
+}
@@ -0,0 +1,92 @@
+use std::net::Ipv6Addr;
+
+use crate::packet::{IcmpPacket, IcmpPacketMut};
+use crate::v4::Icmpv4Message;
+use crate::v6::Icmpv6Message;
+
+fn echo() -> Icmpv4Message {
+    Icmpv4Message::Echo {
+        identifier: 0x1234,
+        sequence: 0xabcd,
+        payload: b"abcdefghijklmnopqrstuvwabcdefghi".to_vec(),
+    }
+}
+
+#[test]
+fn accessors() {
+    let bytes = t!(echo().to_bytes());
+    let packet = t!(IcmpPacket::new(&bytes));
+
+    assert_eq!(packet.message_type(), 8);
+    assert_eq!(packet.code(), 0);
+    assert_eq!(packet.checksum(), u16::from_be_bytes([bytes[2], bytes[3]]));
+    assert_eq!(packet.rest_of_header(), [0x12, 0x34, 0xab, 0xcd]);
+    assert_eq!(packet.payload(), b"abcdefghijklmnopqrstuvwabcdefghi");
+    assert_eq!(packet.as_bytes(), &bytes[..]);
+    assert!(packet.verify_checksum());
+    assert_eq!(t!(Icmpv4Message::from_packet(&packet)), echo());
+}
+
+#[test]
+fn too_short() {
+    assert!(IcmpPacket::new(&[8, 0, 0, 0, 0, 0, 0]).is_err());
+    assert!(IcmpPacketMut::new(&mut [0; 7]).is_err());
+
+    let packet = t!(IcmpPacket::new(&[8, 0, 0xf7, 0xff, 0, 0, 0, 0]));
+    assert!(packet.payload().is_empty());
+    assert!(packet.verify_checksum());
+}
+
+#[test]
+fn edit_in_place() {
+    let mut bytes = t!(echo().to_bytes());
+    {
+        let mut packet = t!(IcmpPacketMut::new(&mut bytes));
+        packet.set_message_type(0);
+        packet.rest_of_header_mut()[2..].copy_from_slice(&[0, 1]);
+        packet.payload_mut()[0] = b'A';
+        assert!(!packet.as_packet().verify_checksum());
+        packet.fill_checksum();
+        assert!(packet.as_packet().verify_checksum());
+    }
+
+    let expected = Icmpv4Message::EchoReply {
+        identifier: 0x1234,
+        sequence: 1,
+        payload: b"Abcdefghijklmnopqrstuvwabcdefghi".to_vec(),
+    };
+    assert_eq!(bytes, t!(expected.to_bytes()));
+    assert_eq!(t!(Icmpv4Message::decode(&bytes)), expected);
+
+    let mut packet = t!(IcmpPacketMut::new(&mut bytes));
+    packet.set_code(1);
+    packet.set_checksum(0);
+    assert_eq!(packet.as_packet().code(), 1);
+    assert_eq!(packet.as_packet().checksum(), 0);
+    assert!(Icmpv4Message::decode(&bytes).is_err());
+}
+
+#[test]
+fn checksum_v6() {
+    let source = Ipv6Addr::new(0xfd00, 0, 0, 0, 0, 0, 0, 2);
+    let destination = Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1);
+    let message = Icmpv6Message::EchoRequest {
+        identifier: 0x1234,
+        sequence: 1,
+        payload: b"ping".to_vec(),
+    };
+
+    let mut expected = vec![0; message.encoded_len()];
+    t!(message.encode_with_checksum(&mut expected, &source, &destination));
+
+    let mut bytes = t!(message.to_bytes());
+    let mut packet = t!(IcmpPacketMut::new(&mut bytes));
+    assert!(!packet.as_packet().verify_checksum_v6(&source, &destination));
+    packet.fill_checksum_v6(&source, &destination);
+    assert!(packet.as_packet().verify_checksum_v6(&source, &destination));
+    assert!(!packet.as_packet().verify_checksum_v6(&source, &Ipv6Addr::LOCALHOST));
+    assert_eq!(bytes, expected);
+
+    let packet = t!(IcmpPacket::new(&bytes));
+    assert_eq!(t!(Icmpv6Message::from_packet(&packet)), message);
+}
@@ -0,0 +1,182 @@
+#[cfg(target_os = "linux")]
+use std::fs;
+use std::mem;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::os::unix::io::AsRawFd;
+use std::time::Duration;
+
+use crate::{IcmpSocket, SocketKind};
+use crate::compat::sockaddr_to_addr;
+use crate::extension::InterfaceIdentifier;
+use crate::probe::{self, ExtendedEchoReplyCode};
+use crate::v4::Icmpv4Message;
+use crate::v6::Icmpv6Message;
+
+#[cfg(target_os = "linux")]
+use super::netns::isolated;
+
+/// Runs `test` in a network namespace of its own where every group may open datagram
+/// sockets, so that the host keeps its `ping_group_range`. Skips `test` if that is not
+/// permitted.
+#[cfg(target_os = "linux")]
+pub fn with_ping_sockets<F: FnOnce() + Send + 'static>(name: &'static str, test: F) {
+    isolated(name, move || {
+        if fs::write("/proc/sys/net/ipv4/ping_group_range", "0 2147483647").is_err() {
+            super::skip(name, "datagram sockets are not permitted");
+            return;
+        }
+        test();
+    });
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn with_ping_sockets<F: FnOnce() + Send + 'static>(name: &'static str, _test: F) {
+    super::skip(name, "datagram sockets are only set up on Linux");
+}
+
+/// Returns the echo identifier the kernel picked for the datagram `socket`, which is its
+/// local port.
+pub fn kernel_identifier(socket: &IcmpSocket) -> u16 {
+    let mut storage: libc::sockaddr_storage = unsafe { mem::zeroed() };
+    let mut len = mem::size_of_val(&storage) as libc::socklen_t;
+    let ret = unsafe {
+        libc::getsockname(socket.as_raw_fd(), &mut storage as *mut _ as *mut libc::sockaddr, &mut len)
+    };
+    assert_eq!(ret, 0);
+    t!(sockaddr_to_addr(&storage, len as usize)).port()
+}
+
+fn connect(addr: IpAddr, kind: SocketKind) -> IcmpSocket {
+    let socket = t!(IcmpSocket::connect_with_kind(addr, kind));
+    t!(socket.set_read_timeout(Some(Duration::from_secs(1))));
+    socket
+}
+
+#[test]
+fn kinds() {
+    let localhost = IpAddr::V4(Ipv4Addr::LOCALHOST);
+    assert_eq!(connect(localhost, SocketKind::Raw).kind(), SocketKind::Raw);
+    assert_eq!(t!(IcmpSocket::connect(localhost)).kind(), SocketKind::Raw);
+    // Tests run with CAP_NET_RAW, so no fallback happens.
+    assert_eq!(connect(localhost, SocketKind::Auto).kind(), SocketKind::Raw);
+
+    with_ping_sockets("kinds", move || {
+        assert_eq!(connect(localhost, SocketKind::Datagram).kind(), SocketKind::Datagram);
+    });
+}
+
+#[test]
+fn datagram_echo_v4() {
+    with_ping_sockets("datagram_echo_v4", || {
+        let socket = connect(IpAddr::V4(Ipv4Addr::LOCALHOST), SocketKind::Datagram);
+
+        let request = Icmpv4Message::Echo {
+            identifier: 0x5151,
+            sequence: 3,
+            payload: b"datagram_echo_v4".to_vec(),
+        };
+        t!(socket.send(&t!(request.to_bytes())));
+
+        // Only replies to this socket are delivered, without an IP header and with the
+        // identifier the kernel used on the wire instead of the one sent.
+        let mut buf = [0u8; 1024];
+        let (size, source) = t!(socket.recv_message(&mut buf));
+        assert_eq!(source, IpAddr::V4(Ipv4Addr::LOCALHOST));
+        assert_eq!(t!(Icmpv4Message::decode(&buf[..size])), Icmpv4Message::EchoReply {
+            identifier: kernel_identifier(&socket),
+            sequence: 3,
+            payload: b"datagram_echo_v4".to_vec(),
+        });
+
+        assert!(socket.recv_packet(&mut buf).is_err());
+    });
+}
+
+#[test]
+fn datagram_echo_in_flight() {
+    with_ping_sockets("datagram_echo_in_flight", || {
+        let socket = connect(IpAddr::V4(Ipv4Addr::LOCALHOST), SocketKind::Datagram);
+
+        // Whatever identifiers are sent, all replies carry the one of the kernel.
+        let requests = [(0x5353, 5), (0x5454, 6)];
+        for &(identifier, sequence) in &requests {
+            let request = Icmpv4Message::Echo {
+                identifier,
+                sequence,
+                payload: b"datagram_echo_in_flight".to_vec(),
+            };
+            t!(socket.send(&t!(request.to_bytes())));
+        }
+
+        let mut buf = [0u8; 1024];
+        for &(_, sequence) in &requests {
+            let (size, _) = t!(socket.recv_message(&mut buf));
+            assert_eq!(t!(Icmpv4Message::decode(&buf[..size])), Icmpv4Message::EchoReply {
+                identifier: kernel_identifier(&socket),
+                sequence,
+                payload: b"datagram_echo_in_flight".to_vec(),
+            });
+        }
+    });
+}
+
+#[test]
+fn datagram_echo_v6() {
+    with_ping_sockets("datagram_echo_v6", || {
+        let localhost = Ipv6Addr::LOCALHOST;
+        let socket = connect(IpAddr::V6(localhost), SocketKind::Datagram);
+
+        let request = Icmpv6Message::EchoRequest {
+            identifier: 0x5151,
+            sequence: 3,
+            payload: b"datagram_echo_v6".to_vec(),
+        };
+        t!(socket.send(&t!(request.to_bytes())));
+
+        let mut buf = [0u8; 1024];
+        let size = t!(socket.recv(&mut buf));
+        assert_eq!(t!(Icmpv6Message::decode_with_checksum(&buf[..size], &localhost, &localhost)), Icmpv6Message::EchoReply {
+            identifier: kernel_identifier(&socket),
+            sequence: 3,
+            payload: b"datagram_echo_v6".to_vec(),
+        });
+    });
+}
+
+#[test]
+fn raw_recv_message_v4() {
+    let socket = connect(IpAddr::V4(Ipv4Addr::LOCALHOST), SocketKind::Raw);
+
+    let request = Icmpv4Message::Echo {
+        identifier: 0x5252,
+        sequence: 4,
+        payload: b"raw_recv_message_v4".to_vec(),
+    };
+    t!(socket.send(&t!(request.to_bytes())));
+
+    let expected = Icmpv4Message::EchoReply {
+        identifier: 0x5252,
+        sequence: 4,
+        payload: b"raw_recv_message_v4".to_vec(),
+    };
+    let mut buf = [0u8; 1024];
+    loop {
+        let (size, _) = t!(socket.recv_message(&mut buf));
+        // Raw sockets also see the request and unrelated traffic.
+        if Icmpv4Message::decode(&buf[..size]).ok() == Some(expected.clone()) {
+            break;
+        }
+    }
+}
+
+#[test]
+fn datagram_probe() {
+    with_ping_sockets("datagram_probe", || {
+        t!(fs::write("/proc/sys/net/ipv4/icmp_echo_enable_probe", "1"));
+        let socket = connect(IpAddr::V4(Ipv4Addr::LOCALHOST), SocketKind::Datagram);
+
+        let status = t!(probe::probe(&socket, &InterfaceIdentifier::Name("lo".to_string()), true));
+        assert_eq!(status.code, ExtendedEchoReplyCode::NoError);
+        assert!(status.active);
+    });
+}
@@ -0,0 +1,133 @@
+#[cfg(target_os = "linux")]
+use std::fs;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::time::Duration;
+
+use crate::IcmpSocket;
+use crate::extension::InterfaceIdentifier;
+use crate::probe::{self, ExtendedEchoReplyCode, InterfaceStatus};
+use crate::v4::Icmpv4Message;
+use crate::v6::Icmpv6Message;
+
+#[cfg(target_os = "linux")]
+use super::netns::isolated;
+
+fn interfaces() -> Vec<InterfaceIdentifier> {
+    vec![
+        InterfaceIdentifier::Name("eth0".to_string()),
+        InterfaceIdentifier::Name("a-longer-name".to_string()),
+        InterfaceIdentifier::Index(2),
+        InterfaceIdentifier::Address(IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1))),
+        InterfaceIdentifier::Address(IpAddr::V6(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1))),
+    ]
+}
+
+#[test]
+fn request_round_trip() {
+    for interface in interfaces() {
+        let message = Icmpv4Message::ExtendedEchoRequest {
+            identifier: 0x1234,
+            sequence: 7,
+            local: true,
+            interface: interface.clone(),
+        };
+        let bytes = t!(message.to_bytes());
+        assert_eq!(bytes.len(), message.encoded_len());
+        assert_eq!(&bytes[..2], &[42, 0]);
+        assert_eq!(&bytes[4..8], &[0x12, 0x34, 7, 0x01]);
+        assert_eq!(t!(Icmpv4Message::decode(&bytes)), message);
+
+        let message = Icmpv6Message::ExtendedEchoRequest {
+            identifier: 0x1234,
+            sequence: 7,
+            local: false,
+            interface,
+        };
+        let bytes = t!(message.to_bytes());
+        assert_eq!(&bytes[..2], &[160, 0]);
+        assert_eq!(&bytes[4..8], &[0x12, 0x34, 7, 0x00]);
+        assert_eq!(t!(Icmpv6Message::decode(&bytes)), message);
+    }
+}
+
+#[test]
+fn reply_round_trip() {
+    let status = InterfaceStatus {
+        code: ExtendedEchoReplyCode::NoError,
+        state: 0,
+        active: true,
+        ipv4: true,
+        ipv6: false,
+    };
+    let message = Icmpv4Message::ExtendedEchoReply {
+        identifier: 0x1234,
+        sequence: 7,
+        status,
+    };
+    let bytes = t!(message.to_bytes());
+    assert_eq!(bytes.len(), 8);
+    assert_eq!(&bytes[..2], &[43, 0]);
+    assert_eq!(&bytes[4..8], &[0x12, 0x34, 7, 0x06]);
+    assert_eq!(t!(Icmpv4Message::decode(&bytes)), message);
+
+    let status = InterfaceStatus {
+        code: ExtendedEchoReplyCode::NoSuchTableEntry,
+        state: 5,
+        active: false,
+        ipv4: false,
+        ipv6: true,
+    };
+    let message = Icmpv6Message::ExtendedEchoReply {
+        identifier: 0x1234,
+        sequence: 7,
+        status,
+    };
+    let bytes = t!(message.to_bytes());
+    assert_eq!(&bytes[..2], &[161, 3]);
+    assert_eq!(&bytes[4..8], &[0x12, 0x34, 7, 0xa1]);
+    assert_eq!(t!(Icmpv6Message::decode(&bytes)), message);
+}
+
+#[test]
+fn codes() {
+    assert_eq!(ExtendedEchoReplyCode::from(2), ExtendedEchoReplyCode::NoSuchInterface);
+    assert_eq!(u8::from(ExtendedEchoReplyCode::MultipleInterfaces), 4);
+    assert_eq!(ExtendedEchoReplyCode::from(9), ExtendedEchoReplyCode::Unassigned(9));
+}
+
+#[test]
+fn request_without_interface() {
+    // Extension header with a valid checksum, but no objects.
+    let bytes = [42, 0, 0xbc, 0xca, 0x12, 0x34, 7, 0x01, 0x20, 0x00, 0xdf, 0xff];
+    assert!(Icmpv4Message::decode(&bytes).is_err());
+    assert!(Icmpv4Message::decode(&bytes[..8]).is_err());
+}
+
+#[cfg(target_os = "linux")]
+fn probe_loopback(address: IpAddr) {
+    // Linux only answers extended echo requests when asked to.
+    t!(fs::write("/proc/sys/net/ipv4/icmp_echo_enable_probe", "1"));
+    let socket = t!(IcmpSocket::connect(address));
+    t!(socket.set_read_timeout(Some(Duration::from_secs(1))));
+
+    let status = t!(probe::probe(&socket, &InterfaceIdentifier::Name("lo".to_string()), true));
+    assert_eq!(status.code, ExtendedEchoReplyCode::NoError);
+    assert!(status.active);
+    assert!(status.ipv4);
+    assert!(status.ipv6);
+
+    let status = t!(probe::probe(&socket, &InterfaceIdentifier::Name("nonexistent0".to_string()), true));
+    assert_eq!(status.code, ExtendedEchoReplyCode::NoSuchInterface);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn probe_loopback_v4() {
+    isolated("probe_loopback_v4", || probe_loopback(IpAddr::V4(Ipv4Addr::LOCALHOST)));
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn probe_loopback_v6() {
+    isolated("probe_loopback_v6", || probe_loopback(IpAddr::V6(Ipv6Addr::LOCALHOST)));
+}
@@ -0,0 +1,179 @@
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, UdpSocket};
+use std::time::Duration;
+
+use crate::IcmpSocket;
+use crate::ip::IpHeader;
+use crate::quote::{EchoHeader, QuotedDatagram, TcpHeader, Transport, UdpHeader};
+use crate::v4::{DestinationUnreachableCode, Icmpv4Message, TimeExceededCode};
+use crate::v6::{self, Icmpv6Message};
+
+/// IPv4 header and leading bytes of an UDP datagram sent by traceroute.
+const UDP_V4: &[u8] = &[
+    0x45, 0x00, 0x00, 0x3c, 0x1c, 0x46, 0x40, 0x00, 0x01, 0x11, 0x00, 0x00, 0xc0, 0x00, 0x02, 0x02, 0xc6, 0x33,
+    0x64, 0x01, 0x82, 0x9b, 0x82, 0x9c, 0x00, 0x28, 0xab, 0xcd,
+];
+
+/// IPv4 header and leading bytes of a TCP SYN.
+const TCP_V4: &[u8] = &[
+    0x45, 0x00, 0x00, 0x3c, 0x00, 0x01, 0x40, 0x00, 0x01, 0x06, 0x00, 0x00, 0xc0, 0x00, 0x02, 0x02, 0xc6, 0x33,
+    0x64, 0x01, 0xc3, 0x50, 0x00, 0x50, 0xde, 0xad, 0xbe, 0xef,
+];
+
+/// IPv4 header and leading bytes of an ICMP echo request.
+const ECHO_V4: &[u8] = &[
+    0x45, 0x00, 0x00, 0x54, 0x00, 0x02, 0x00, 0x00, 0x01, 0x01, 0x00, 0x00, 0xc0, 0x00, 0x02, 0x02, 0xc6, 0x33,
+    0x64, 0x01, 0x08, 0x00, 0x00, 0x00, 0x12, 0x34, 0x00, 0x05,
+];
+
+/// IPv6 header, fragment header and leading bytes of an ICMPv6 echo request.
+const ECHO_V6_FRAGMENT: &[u8] = &[
+    0x60, 0x00, 0x00, 0x00, 0x05, 0xb0, 0x2c, 0x01, 0xfd, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x20, 0x01, 0x0d, 0xb8, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x01, 0x3a, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x2a, 0x80, 0x00, 0x00, 0x00, 0x43, 0x21,
+    0x00, 0x01,
+];
+
+#[test]
+fn v4_udp() {
+    let message = Icmpv4Message::TimeExceeded {
+        code: TimeExceededCode::TtlExceeded,
+        datagram: UDP_V4.to_vec(),
+        extensions: None,
+    };
+    let quoted = t!(message.quoted());
+
+    assert_eq!(quoted.header.source(), IpAddr::V4(Ipv4Addr::new(192, 0, 2, 2)));
+    assert_eq!(quoted.header.destination(), IpAddr::V4(Ipv4Addr::new(198, 51, 100, 1)));
+    assert_eq!(quoted.transport, Transport::Udp(UdpHeader {
+        source_port: 33435,
+        destination_port: 33436,
+        length: 40,
+        checksum: 0xabcd,
+    }));
+}
+
+#[test]
+fn v4_tcp() {
+    let quoted = t!(QuotedDatagram::decode(TCP_V4));
+
+    assert_eq!(quoted.transport, Transport::Tcp(TcpHeader {
+        source_port: 50000,
+        destination_port: 80,
+        sequence: 0xdead_beef,
+    }));
+}
+
+#[test]
+fn v4_echo() {
+    let message = Icmpv4Message::DestinationUnreachable {
+        code: DestinationUnreachableCode::Host,
+        next_hop_mtu: 0,
+        datagram: ECHO_V4.to_vec(),
+        extensions: None,
+    };
+    let quoted = t!(message.quoted());
+
+    match quoted.header {
+        IpHeader::V4(ref header) => assert_eq!(header.ttl, 1),
+        IpHeader::V6(..) => panic!("expected IPv4 header"),
+    }
+    assert_eq!(quoted.transport, Transport::Echo(EchoHeader {
+        identifier: 0x1234,
+        sequence: 5,
+    }));
+}
+
+#[test]
+fn v4_other() {
+    // Non-first fragment, transport header is not there
+    let mut bytes = UDP_V4.to_vec();
+    bytes[7] = 0x10;
+    assert_eq!(t!(QuotedDatagram::decode(&bytes)).transport, Transport::Other {
+        protocol: 17,
+        data: UDP_V4[20..].to_vec(),
+    });
+
+    // Truncated transport header
+    assert_eq!(t!(QuotedDatagram::decode(&UDP_V4[..24])).transport, Transport::Other {
+        protocol: 17,
+        data: UDP_V4[20..24].to_vec(),
+    });
+
+    // Echo reply is not a probe
+    let mut bytes = ECHO_V4.to_vec();
+    bytes[20] = 0;
+    assert_eq!(t!(QuotedDatagram::decode(&bytes)).transport, Transport::Other {
+        protocol: 1,
+        data: bytes[20..].to_vec(),
+    });
+}
+
+#[test]
+fn v6_fragment_echo() {
+    let message = Icmpv6Message::TimeExceeded {
+        code: v6::TimeExceededCode::HopLimitExceeded,
+        datagram: ECHO_V6_FRAGMENT.to_vec(),
+        extensions: None,
+    };
+    let quoted = t!(message.quoted());
+
+    match quoted.header {
+        IpHeader::V6(ref header) => {
+            assert_eq!(header.next_header, 44);
+            assert_eq!(header.payload_len, 1456);
+            assert_eq!(header.hop_limit, 1);
+        },
+        IpHeader::V4(..) => panic!("expected IPv6 header"),
+    }
+    assert_eq!(quoted.header.source(), IpAddr::V6(Ipv6Addr::new(0xfd00, 0, 0, 0, 0, 0, 0, 2)));
+    assert_eq!(quoted.header.destination(), IpAddr::V6(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1)));
+    assert_eq!(quoted.transport, Transport::Echo(EchoHeader {
+        identifier: 0x4321,
+        sequence: 1,
+    }));
+}
+
+#[test]
+fn not_an_error() {
+    let message = Icmpv4Message::Echo {
+        identifier: 1,
+        sequence: 1,
+        payload: UDP_V4.to_vec(),
+    };
+
+    assert!(message.datagram().is_none());
+    assert!(message.quoted().is_err());
+}
+
+#[test]
+fn invalid_header() {
+    assert!(QuotedDatagram::decode(&[]).is_err());
+    assert!(QuotedDatagram::decode(&UDP_V4[..19]).is_err());
+    assert!(QuotedDatagram::decode(&ECHO_V6_FRAGMENT[..39]).is_err());
+}
+
+#[test]
+fn port_unreachable_loopback() {
+    let localhost = Ipv4Addr::new(127, 0, 0, 1);
+    let socket = t!(IcmpSocket::connect(IpAddr::V4(localhost)));
+    t!(socket.set_read_timeout(Some(Duration::from_secs(1))));
+
+    // Grab a free port and close it again, so nobody listens there.
+    let port = t!(t!(UdpSocket::bind((localhost, 0))).local_addr()).port();
+    let udp = t!(UdpSocket::bind((localhost, 0)));
+    let source_port = t!(udp.local_addr()).port();
+    t!(udp.send_to(b"probe", (localhost, port)));
+
+    let mut buf = [0u8; 1024];
+    loop {
+        let (_, message) = t!(socket.recv_packet(&mut buf));
+        if let Icmpv4Message::DestinationUnreachable { code: DestinationUnreachableCode::Port, .. } = message {
+            if let Transport::Udp(header) = t!(message.quoted()).transport {
+                if header.source_port == source_port && header.destination_port == port {
+                    assert_eq!(header.length, 13);
+                    break;
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,162 @@
+use std::ffi::CString;
+use std::fs;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::time::Duration;
+
+use crate::{IcmpSocket, RecvMeta, SocketKind};
+use crate::ip::Ipv4Header;
+use crate::v4::Icmpv4Message;
+use crate::v6::Icmpv6Message;
+
+use super::ping::{kernel_identifier, with_ping_sockets};
+
+fn loopback_index() -> u32 {
+    let name = CString::new("lo").unwrap();
+    unsafe { libc::if_nametoindex(name.as_ptr()) }
+}
+
+fn default_ttl(path: &str) -> u8 {
+    t!(t!(fs::read_to_string(path)).trim().parse())
+}
+
+fn connect(addr: IpAddr, kind: SocketKind) -> IcmpSocket {
+    let socket = t!(IcmpSocket::connect_with_kind(addr, kind));
+    t!(socket.set_read_timeout(Some(Duration::from_secs(1))));
+    assert!(!t!(socket.recv_meta()));
+    t!(socket.set_recv_meta(true));
+    assert!(t!(socket.recv_meta()));
+    socket
+}
+
+/// Receives on a raw IPv4 socket until the echo reply with `identifier` arrives, returns
+/// the metadata of the looped back request and of the reply.
+fn echo_v4(socket: &IcmpSocket, identifier: u16) -> (Option<RecvMeta>, RecvMeta) {
+    let request = Icmpv4Message::Echo {
+        identifier,
+        sequence: 1,
+        payload: b"recv_msg_v4".to_vec(),
+    };
+    t!(socket.send(&t!(request.to_bytes())));
+
+    let identifier = match socket.kind() {
+        SocketKind::Datagram => kernel_identifier(socket),
+        _ => identifier,
+    };
+    let mut request_meta = None;
+    let mut buf = [0u8; 1024];
+    loop {
+        let (size, source, meta) = t!(socket.recv_msg(&mut buf));
+        assert_eq!(source, IpAddr::V4(Ipv4Addr::LOCALHOST));
+        let message = if socket.kind() == SocketKind::Raw {
+            let header = t!(Ipv4Header::decode(&buf[..size]));
+            assert_eq!(meta.ttl, Some(header.ttl));
+            t!(Icmpv4Message::decode(&buf[header.header_len()..size]))
+        } else {
+            t!(Icmpv4Message::decode(&buf[..size]))
+        };
+
+        match message {
+            Icmpv4Message::Echo { identifier: id, .. } if id == identifier => request_meta = Some(meta),
+            Icmpv4Message::EchoReply { identifier: id, .. } if id == identifier => return (request_meta, meta),
+            _ => {},
+        }
+    }
+}
+
+#[test]
+fn recv_msg_v4() {
+    let socket = connect(IpAddr::V4(Ipv4Addr::LOCALHOST), SocketKind::Raw);
+    t!(socket.set_ttl(33));
+
+    let (request, reply) = echo_v4(&socket, 0x7019);
+    let expected = RecvMeta {
+        ttl: Some(33),
+        destination: Some(IpAddr::V4(Ipv4Addr::LOCALHOST)),
+        interface: Some(loopback_index()),
+        timestamp: None,
+    };
+    assert_eq!(request, Some(expected));
+    assert_eq!(reply, RecvMeta {
+        ttl: Some(default_ttl("/proc/sys/net/ipv4/ip_default_ttl")),
+        ..expected
+    });
+}
+
+#[test]
+fn recv_msg_datagram_v4() {
+    with_ping_sockets("recv_msg_datagram_v4", || {
+        let socket = connect(IpAddr::V4(Ipv4Addr::LOCALHOST), SocketKind::Datagram);
+
+        // Datagram sockets only see the reply, without IP header: the TTL is only known
+        // through the ancillary data.
+        let (request, reply) = echo_v4(&socket, 0x7019);
+        assert_eq!(request, None);
+        assert_eq!(reply, RecvMeta {
+            ttl: Some(default_ttl("/proc/sys/net/ipv4/ip_default_ttl")),
+            destination: Some(IpAddr::V4(Ipv4Addr::LOCALHOST)),
+            interface: Some(loopback_index()),
+            timestamp: None,
+        });
+    });
+}
+
+#[test]
+fn recv_msg_v6() {
+    let socket = connect(IpAddr::V6(Ipv6Addr::LOCALHOST), SocketKind::Raw);
+    t!(socket.set_ttl(33));
+
+    let request = Icmpv6Message::EchoRequest {
+        identifier: 0x7019,
+        sequence: 1,
+        payload: b"recv_msg_v6".to_vec(),
+    };
+    t!(socket.send(&t!(request.to_bytes())));
+
+    let expected = RecvMeta {
+        ttl: Some(33),
+        destination: Some(IpAddr::V6(Ipv6Addr::LOCALHOST)),
+        interface: Some(loopback_index()),
+        timestamp: None,
+    };
+    let mut buf = [0u8; 1024];
+    loop {
+        let (size, source, meta) = t!(socket.recv_msg(&mut buf));
+        assert_eq!(source, IpAddr::V6(Ipv6Addr::LOCALHOST));
+        match Icmpv6Message::decode(&buf[..size]) {
+            Ok(Icmpv6Message::EchoRequest { identifier: 0x7019, .. }) => assert_eq!(meta, expected),
+            Ok(Icmpv6Message::EchoReply { identifier: 0x7019, .. }) => {
+                assert_eq!(meta, RecvMeta {
+                    ttl: Some(default_ttl("/proc/sys/net/ipv6/conf/lo/hop_limit")),
+                    ..expected
+                });
+                break;
+            },
+            _ => {},
+        }
+    }
+}
+
+#[test]
+fn recv_msg_without_meta() {
+    let localhost = IpAddr::V6(Ipv6Addr::LOCALHOST);
+    let socket = t!(IcmpSocket::connect_with_kind(localhost, SocketKind::Raw));
+    t!(socket.set_read_timeout(Some(Duration::from_secs(1))));
+
+    let request = Icmpv6Message::EchoRequest {
+        identifier: 0x7119,
+        sequence: 1,
+        payload: b"recv_msg_without_meta".to_vec(),
+    };
+    t!(socket.send(&t!(request.to_bytes())));
+
+    // Nothing is known beyond the source until asked for
+    let mut buf = [0u8; 1024];
+    loop {
+        let (size, source, meta) = t!(socket.recv_msg(&mut buf));
+        assert_eq!(source, localhost);
+        assert_eq!(meta, RecvMeta::default());
+        if let Ok(Icmpv6Message::EchoReply { identifier: 0x7119, .. }) = Icmpv6Message::decode(&buf[..size]) {
+            break;
+        }
+    }
+}
@@ -0,0 +1,103 @@
+use std::io::ErrorKind;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::time::Duration;
+
+use crate::{IcmpSocket, SendMeta};
+use crate::v4::Icmpv4Message;
+use crate::v6::Icmpv6Message;
+
+fn connect(addr: IpAddr) -> IcmpSocket {
+    let socket = t!(IcmpSocket::connect(addr));
+    t!(socket.set_read_timeout(Some(Duration::from_secs(1))));
+    socket
+}
+
+#[test]
+fn send_msg_v4() {
+    let localhost = IpAddr::V4(Ipv4Addr::LOCALHOST);
+    let socket = connect(localhost);
+    t!(socket.set_ttl(33));
+
+    // Probes with interleaved parameters, received back through loopback
+    let probes = [
+        (1, SendMeta { ttl: Some(5), ..SendMeta::default() }),
+        (2, SendMeta { ttl: Some(6), tos: Some(0x10), ..SendMeta::default() }),
+        (3, SendMeta { source: Some(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 2))), ..SendMeta::default() }),
+        (4, SendMeta::default()),
+    ];
+    for &(sequence, ref meta) in &probes {
+        let request = Icmpv4Message::Echo {
+            identifier: 0x7020,
+            sequence,
+            payload: b"send_msg_v4".to_vec(),
+        };
+        t!(socket.send_msg(&t!(request.to_bytes()), localhost, meta));
+    }
+
+    let mut seen = 0;
+    let mut buf = [0u8; 1024];
+    while seen < probes.len() {
+        let (header, message) = t!(socket.recv_packet(&mut buf));
+        let sequence = match message {
+            Icmpv4Message::Echo { identifier: 0x7020, sequence, .. } => sequence,
+            _ => continue,
+        };
+        let meta = probes[usize::from(sequence) - 1].1;
+        assert_eq!(header.ttl, meta.ttl.unwrap_or(33));
+        assert_eq!(header.tos, meta.tos.unwrap_or(0));
+        assert_eq!(IpAddr::V4(header.source), meta.source.unwrap_or(localhost));
+        seen += 1;
+    }
+
+    // Socket options are left alone
+    assert_eq!(t!(socket.ttl()), 33);
+    assert_eq!(t!(socket.qos()), 0);
+}
+
+#[test]
+fn send_msg_v6() {
+    let localhost = IpAddr::V6(Ipv6Addr::LOCALHOST);
+    let socket = connect(localhost);
+    t!(socket.set_recv_meta(true));
+
+    let meta = SendMeta {
+        ttl: Some(7),
+        tos: Some(0x20),
+        source: Some(localhost),
+        interface: None,
+    };
+    let request = Icmpv6Message::EchoRequest {
+        identifier: 0x7020,
+        sequence: 1,
+        payload: b"send_msg_v6".to_vec(),
+    };
+    t!(socket.send_msg(&t!(request.to_bytes()), localhost, &meta));
+
+    // The hop limit is only visible through the ancillary data
+    let mut buf = [0u8; 1024];
+    loop {
+        let (size, _, received) = t!(socket.recv_msg(&mut buf));
+        if let Ok(Icmpv6Message::EchoRequest { identifier: 0x7020, .. }) = Icmpv6Message::decode(&buf[..size]) {
+            assert_eq!(received.ttl, Some(7));
+            break;
+        }
+    }
+}
+
+#[test]
+fn send_msg_invalid() {
+    let localhost = IpAddr::V4(Ipv4Addr::LOCALHOST);
+    let socket = connect(localhost);
+    let meta = SendMeta {
+        source: Some(IpAddr::V6(Ipv6Addr::LOCALHOST)),
+        ..SendMeta::default()
+    };
+    assert_eq!(socket.send_msg(&[8, 0, 0, 0, 0, 0, 0, 0], localhost, &meta).unwrap_err().kind(), ErrorKind::InvalidInput);
+
+    // Not a local address
+    let meta = SendMeta {
+        source: Some(IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1))),
+        ..SendMeta::default()
+    };
+    assert!(socket.send_msg(&[8, 0, 0, 0, 0, 0, 0, 0], localhost, &meta).is_err());
+}
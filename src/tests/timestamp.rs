@@ -0,0 +1,129 @@
+use std::io::ErrorKind;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use crate::{IcmpSocket, SocketKind};
+use crate::v4::Icmpv4Message;
+use crate::v6::Icmpv6Message;
+
+use super::ping::{kernel_identifier, with_ping_sockets};
+
+fn connect(addr: IpAddr, kind: SocketKind) -> IcmpSocket {
+    let socket = t!(IcmpSocket::connect_with_kind(addr, kind));
+    t!(socket.set_read_timeout(Some(Duration::from_secs(1))));
+    socket
+}
+
+/// Waits for the next transmit timestamp, which is queued asynchronously.
+fn tx_timestamp(socket: &IcmpSocket) -> (u32, SystemTime) {
+    for _ in 0..100 {
+        match socket.recv_tx_timestamp() {
+            Ok(timestamp) => return timestamp,
+            Err(ref err) if err.kind() == ErrorKind::WouldBlock => thread::sleep(Duration::from_millis(10)),
+            Err(err) => panic!("recv_tx_timestamp failed: {}", err),
+        }
+    }
+    panic!("no transmit timestamp");
+}
+
+fn echo_request(addr: IpAddr, identifier: u16, sequence: u16) -> Vec<u8> {
+    let payload = b"timestamp".to_vec();
+    match addr {
+        IpAddr::V4(..) => t!(Icmpv4Message::Echo { identifier, sequence, payload }.to_bytes()),
+        IpAddr::V6(..) => t!(Icmpv6Message::EchoRequest { identifier, sequence, payload }.to_bytes()),
+    }
+}
+
+/// Returns the sequence number of the echo reply with `identifier` in `buf`, if any.
+fn echo_reply(socket: &IcmpSocket, identifier: u16, buf: &mut [u8]) -> Option<(u16, SystemTime)> {
+    let (size, _, meta) = t!(socket.recv_msg(buf));
+    let sequence = match socket.peer_addr() {
+        Ok(IpAddr::V4(..)) => {
+            let start = if socket.kind() == SocketKind::Raw { usize::from(buf[0] & 0x0f) * 4 } else { 0 };
+            match Icmpv4Message::decode(&buf[start..size]) {
+                Ok(Icmpv4Message::EchoReply { identifier: id, sequence, .. }) if id == identifier => sequence,
+                _ => return None,
+            }
+        },
+        _ => match Icmpv6Message::decode(&buf[..size]) {
+            Ok(Icmpv6Message::EchoReply { identifier: id, sequence, .. }) if id == identifier => sequence,
+            _ => return None,
+        },
+    };
+    Some((sequence, meta.timestamp.expect("no receive timestamp")))
+}
+
+fn round_trip(addr: IpAddr, kind: SocketKind) {
+    let socket = connect(addr, kind);
+    assert!(!t!(socket.timestamping()));
+    t!(socket.set_timestamping(true));
+    assert!(t!(socket.timestamping()));
+
+    for sequence in 0..2 {
+        t!(socket.send(&echo_request(addr, 0x7021, sequence)));
+    }
+    let sent = [tx_timestamp(&socket), tx_timestamp(&socket)];
+    assert_eq!((sent[0].0, sent[1].0), (0, 1));
+    assert!(sent[0].1 <= sent[1].1);
+
+    let identifier = match kind {
+        SocketKind::Datagram => kernel_identifier(&socket),
+        _ => 0x7021,
+    };
+    let mut replies = 0;
+    let mut buf = [0u8; 1024];
+    while replies < 2 {
+        if let Some((sequence, received)) = echo_reply(&socket, identifier, &mut buf) {
+            let rtt = t!(received.duration_since(sent[usize::from(sequence)].1));
+            assert!(rtt < Duration::from_secs(1));
+            replies += 1;
+        }
+    }
+
+    assert_eq!(socket.recv_tx_timestamp().unwrap_err().kind(), ErrorKind::WouldBlock);
+    t!(socket.set_timestamping(false));
+    assert!(!t!(socket.timestamping()));
+}
+
+#[test]
+fn timestamping_v4() {
+    round_trip(IpAddr::V4(Ipv4Addr::LOCALHOST), SocketKind::Raw);
+}
+
+#[test]
+fn timestamping_v6() {
+    round_trip(IpAddr::V6(Ipv6Addr::LOCALHOST), SocketKind::Raw);
+}
+
+#[test]
+fn timestamping_datagram() {
+    with_ping_sockets("timestamping_datagram", || {
+        round_trip(IpAddr::V4(Ipv4Addr::LOCALHOST), SocketKind::Datagram);
+    });
+}
+
+#[test]
+fn timestamp_ns() {
+    let addr = IpAddr::V6(Ipv6Addr::LOCALHOST);
+    let socket = connect(addr, SocketKind::Raw);
+    assert!(!t!(socket.timestamp_ns()));
+    t!(socket.set_timestamp_ns(true));
+    assert!(t!(socket.timestamp_ns()));
+
+    // An identifier of its own: the socket also sees the replies of timestamping_v6,
+    // which may be queued before timestamps are enabled here
+    let before = SystemTime::now();
+    t!(socket.send(&echo_request(addr, 0x7121, 0)));
+    let mut buf = [0u8; 1024];
+    let received = loop {
+        if let Some((_, received)) = echo_reply(&socket, 0x7121, &mut buf) {
+            break received;
+        }
+    };
+    assert!(received >= before);
+    assert!(received <= SystemTime::now());
+
+    // Receive timestamps only, nothing is queued for sent messages
+    assert_eq!(socket.recv_tx_timestamp().unwrap_err().kind(), ErrorKind::WouldBlock);
+}
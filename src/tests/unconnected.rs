@@ -0,0 +1,119 @@
+use std::collections::HashSet;
+use std::io::ErrorKind;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::time::Duration;
+
+use crate::{AddressFamily, IcmpSocket, SocketKind};
+use crate::v4::Icmpv4Message;
+use crate::v6::Icmpv6Message;
+
+use super::ping::{kernel_identifier, with_ping_sockets};
+
+fn open(family: AddressFamily, kind: SocketKind) -> IcmpSocket {
+    let socket = t!(IcmpSocket::new_with_kind(family, kind));
+    t!(socket.set_read_timeout(Some(Duration::from_secs(1))));
+    socket
+}
+
+/// Sends an echo request with `identifier` to each of `targets` and returns the sources
+/// of the replies.
+fn ping_v4(socket: &IcmpSocket, identifier: u16, targets: &[IpAddr]) -> HashSet<IpAddr> {
+    for (sequence, &target) in targets.iter().enumerate() {
+        let request = Icmpv4Message::Echo {
+            identifier,
+            sequence: sequence as u16,
+            payload: b"unconnected".to_vec(),
+        };
+        t!(socket.send_to(&t!(request.to_bytes()), target));
+    }
+
+    // Replies to datagram sockets carry the identifier of the kernel
+    let identifier = match socket.kind() {
+        SocketKind::Datagram => kernel_identifier(socket),
+        _ => identifier,
+    };
+    let mut sources = HashSet::new();
+    let mut buf = [0u8; 1024];
+    while sources.len() < targets.len() {
+        let (size, source) = t!(socket.recv_message(&mut buf));
+        match Icmpv4Message::decode(&buf[..size]) {
+            Ok(Icmpv4Message::EchoReply { identifier: id, .. }) if id == identifier => {
+                sources.insert(source);
+            },
+            _ => continue,
+        }
+    }
+    sources
+}
+
+#[test]
+fn not_connected() {
+    let socket = t!(IcmpSocket::new(AddressFamily::V4));
+    assert_eq!(socket.peer_addr().unwrap_err().kind(), ErrorKind::NotConnected);
+    assert_eq!(socket.send(&[8, 0, 0, 0, 0, 0, 0, 0]).unwrap_err().kind(), ErrorKind::NotConnected);
+
+    let socket = t!(IcmpSocket::connect(IpAddr::V4(Ipv4Addr::LOCALHOST)));
+    assert_eq!(t!(socket.peer_addr()), IpAddr::V4(Ipv4Addr::LOCALHOST));
+}
+
+#[test]
+fn bind() {
+    let socket = t!(IcmpSocket::new(AddressFamily::V4));
+    assert_eq!(t!(socket.local_addr()), IpAddr::V4(Ipv4Addr::UNSPECIFIED));
+    t!(socket.bind(IpAddr::V4(Ipv4Addr::LOCALHOST)));
+    assert_eq!(t!(socket.local_addr()), IpAddr::V4(Ipv4Addr::LOCALHOST));
+
+    let socket = t!(IcmpSocket::new(AddressFamily::V6));
+    t!(socket.bind(IpAddr::V6(Ipv6Addr::LOCALHOST)));
+    assert_eq!(t!(socket.local_addr()), IpAddr::V6(Ipv6Addr::LOCALHOST));
+
+    // Not a local address
+    let socket = t!(IcmpSocket::new(AddressFamily::V4));
+    assert!(socket.bind(IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1))).is_err());
+}
+
+#[test]
+fn send_to_many_v4() {
+    let targets = [IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), IpAddr::V4(Ipv4Addr::new(127, 0, 0, 2))];
+    let socket = open(AddressFamily::V4, SocketKind::Raw);
+
+    let sources = ping_v4(&socket, 0x7014, &targets);
+    assert_eq!(sources, targets.iter().cloned().collect());
+}
+
+#[test]
+fn send_to_many_datagram_v4() {
+    with_ping_sockets("send_to_many_datagram_v4", || {
+        let targets = [IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), IpAddr::V4(Ipv4Addr::new(127, 0, 0, 3))];
+        let socket = open(AddressFamily::V4, SocketKind::Datagram);
+
+        let sources = ping_v4(&socket, 0x7015, &targets);
+        assert_eq!(sources, targets.iter().cloned().collect());
+    });
+}
+
+#[test]
+fn send_to_v6() {
+    let socket = open(AddressFamily::V6, SocketKind::Raw);
+    t!(socket.bind(IpAddr::V6(Ipv6Addr::LOCALHOST)));
+
+    let request = Icmpv6Message::EchoRequest {
+        identifier: 0x7016,
+        sequence: 1,
+        payload: b"send_to_v6".to_vec(),
+    };
+    t!(socket.send_to(&t!(request.to_bytes()), IpAddr::V6(Ipv6Addr::LOCALHOST)));
+
+    let mut buf = [0u8; 1024];
+    loop {
+        let (size, source) = t!(socket.recv_from(&mut buf));
+        if let Ok(Icmpv6Message::EchoReply { identifier: 0x7016, sequence, .. }) = Icmpv6Message::decode(&buf[..size]) {
+            assert_eq!(source, IpAddr::V6(Ipv6Addr::LOCALHOST));
+            assert_eq!(sequence, 1);
+            break;
+        }
+    }
+
+    // A socket only sends to addresses of its own family
+    assert!(socket.send_to(&t!(request.to_bytes()), IpAddr::V4(Ipv4Addr::LOCALHOST)).is_err());
+}
@@ -0,0 +1,189 @@
+use std::net::{IpAddr, Ipv4Addr};
+use std::time::Duration;
+
+use crate::IcmpSocket;
+use crate::v4::{
+    DestinationUnreachableCode, Icmpv4Message, ParameterProblemCode, RedirectCode, TimeExceededCode,
+};
+
+/// IPv4 header followed by the first 8 bytes of an UDP datagram, as quoted by error messages.
+const DATAGRAM: &[u8] = &[
+    0x45, 0x00, 0x00, 0x3c, 0x1c, 0x46, 0x40, 0x00, 0x01, 0x11, 0x00, 0x00, 0xc0, 0x00, 0x02, 0x02, 0xc6, 0x33,
+    0x64, 0x01, 0x82, 0x9b, 0x82, 0x9b, 0x00, 0x28, 0x00, 0x00,
+];
+
+fn round_trip(message: Icmpv4Message) {
+    let bytes = t!(message.to_bytes());
+    assert_eq!(bytes.len(), message.encoded_len());
+    assert_eq!(bytes[0], message.message_type());
+    assert_eq!(bytes[1], message.code());
+    assert_eq!(t!(Icmpv4Message::decode(&bytes)), message);
+}
+
+#[test]
+fn echo_round_trip() {
+    round_trip(Icmpv4Message::Echo {
+        identifier: 0x1234,
+        sequence: 0xabcd,
+        payload: b"abcdefghijklmnopqrstuvwabcdefghi".to_vec(),
+    });
+    round_trip(Icmpv4Message::Echo {
+        identifier: 0,
+        sequence: 0,
+        payload: vec![],
+    });
+    round_trip(Icmpv4Message::EchoReply {
+        identifier: 0xffff,
+        sequence: 1,
+        payload: vec![0xde, 0xad, 0xbe],
+    });
+}
+
+#[test]
+fn destination_unreachable_round_trip() {
+    for code in 0..=255u8 {
+        round_trip(Icmpv4Message::DestinationUnreachable {
+            code: code.into(),
+            next_hop_mtu: if code == 4 { 1400 } else { 0 },
+            datagram: DATAGRAM.to_vec(),
+            extensions: None,
+        });
+    }
+}
+
+#[test]
+fn error_messages_round_trip() {
+    round_trip(Icmpv4Message::SourceQuench {
+        datagram: DATAGRAM.to_vec(),
+    });
+    for code in 0..=255u8 {
+        round_trip(Icmpv4Message::Redirect {
+            code: code.into(),
+            gateway: Ipv4Addr::new(192, 0, 2, 1),
+            datagram: DATAGRAM.to_vec(),
+        });
+        round_trip(Icmpv4Message::TimeExceeded {
+            code: code.into(),
+            datagram: DATAGRAM.to_vec(),
+            extensions: None,
+        });
+        round_trip(Icmpv4Message::ParameterProblem {
+            code: code.into(),
+            pointer: code,
+            datagram: DATAGRAM.to_vec(),
+            extensions: None,
+        });
+    }
+}
+
+#[test]
+fn informational_round_trip() {
+    round_trip(Icmpv4Message::Timestamp {
+        identifier: 7,
+        sequence: 8,
+        originate: 0x0102_0304,
+        receive: 0,
+        transmit: 0,
+    });
+    round_trip(Icmpv4Message::TimestampReply {
+        identifier: 7,
+        sequence: 8,
+        originate: 0x0102_0304,
+        receive: 0x0506_0708,
+        transmit: 0x090a_0b0c,
+    });
+    round_trip(Icmpv4Message::AddressMaskRequest {
+        identifier: 1,
+        sequence: 2,
+        mask: Ipv4Addr::UNSPECIFIED,
+    });
+    round_trip(Icmpv4Message::AddressMaskReply {
+        identifier: 1,
+        sequence: 2,
+        mask: Ipv4Addr::new(255, 255, 255, 0),
+    });
+    round_trip(Icmpv4Message::Unknown {
+        message_type: 9,
+        code: 0,
+        header: [1, 0, 0x07, 0x08],
+        payload: vec![192, 0, 2, 1, 0, 0, 0, 0],
+    });
+}
+
+#[test]
+fn codes() {
+    for code in 0..=255u8 {
+        assert_eq!(u8::from(DestinationUnreachableCode::from(code)), code);
+        assert_eq!(u8::from(RedirectCode::from(code)), code);
+        assert_eq!(u8::from(TimeExceededCode::from(code)), code);
+        assert_eq!(u8::from(ParameterProblemCode::from(code)), code);
+    }
+
+    assert_eq!(DestinationUnreachableCode::from(4), DestinationUnreachableCode::FragmentationNeeded);
+    assert_eq!(DestinationUnreachableCode::from(15), DestinationUnreachableCode::PrecedenceCutoff);
+    assert_eq!(DestinationUnreachableCode::from(16), DestinationUnreachableCode::Unassigned(16));
+    assert_eq!(TimeExceededCode::from(1), TimeExceededCode::FragmentReassembly);
+}
+
+#[test]
+fn encode_echo() {
+    let message = Icmpv4Message::Echo {
+        identifier: 0x0001,
+        sequence: 0x0007,
+        payload: b"abcd".to_vec(),
+    };
+
+    assert_eq!(t!(message.to_bytes()), [0x08, 0x00, 0x33, 0x31, 0x00, 0x01, 0x00, 0x07, b'a', b'b', b'c', b'd']);
+}
+
+#[test]
+fn encode_into_short_buffer() {
+    let message = Icmpv4Message::Echo {
+        identifier: 1,
+        sequence: 1,
+        payload: vec![0; 16],
+    };
+    let mut buf = [0u8; 23];
+
+    assert!(message.encode(&mut buf).is_err());
+
+    let mut buf = [0u8; 64];
+    assert_eq!(t!(message.encode(&mut buf)), 24);
+}
+
+#[test]
+fn decode_truncated() {
+    assert!(Icmpv4Message::decode(&[]).is_err());
+    assert!(Icmpv4Message::decode(&[8, 0, 0, 0, 0, 1, 0]).is_err());
+    assert!(Icmpv4Message::decode(&[13, 0, 0, 0, 0, 1, 0, 1, 0, 0, 0, 0]).is_err());
+    assert!(Icmpv4Message::decode(&[17, 0, 0, 0, 0, 1, 0, 1, 255]).is_err());
+}
+
+#[test]
+fn echo_loopback() {
+    let localhost = Ipv4Addr::new(127, 0, 0, 1);
+    let socket = t!(IcmpSocket::connect(IpAddr::V4(localhost)));
+    t!(socket.set_read_timeout(Some(Duration::from_secs(1))));
+
+    let request = Icmpv4Message::Echo {
+        identifier: 0x4242,
+        sequence: 1,
+        payload: b"echo_loopback".to_vec(),
+    };
+    t!(socket.send(&t!(request.to_bytes())));
+
+    let expected = Icmpv4Message::EchoReply {
+        identifier: 0x4242,
+        sequence: 1,
+        payload: b"echo_loopback".to_vec(),
+    };
+    let mut buf = [0u8; 1024];
+    loop {
+        let (size, source) = t!(socket.recv_from(&mut buf));
+        assert_eq!(source, IpAddr::V4(localhost));
+        let ihl = usize::from(buf[0] & 0x0f) * 4;
+        if t!(Icmpv4Message::decode(&buf[ihl..size])) == expected {
+            break;
+        }
+    }
+}
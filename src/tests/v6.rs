@@ -0,0 +1,165 @@
+use std::net::{IpAddr, Ipv6Addr};
+#[cfg(target_os = "linux")]
+use std::os::unix::io::AsRawFd;
+use std::time::Duration;
+
+use crate::IcmpSocket;
+use crate::v6::{DestinationUnreachableCode, Icmpv6Message, ParameterProblemCode, TimeExceededCode};
+
+/// IPv6 header followed by the first 8 bytes of an UDP datagram, as quoted by error messages.
+const DATAGRAM: &[u8] = &[
+    0x60, 0x00, 0x00, 0x00, 0x00, 0x08, 0x11, 0x01, 0xfd, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x20, 0x01, 0x0d, 0xb8, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x01, 0x82, 0x9b, 0x82, 0x9b, 0x00, 0x08, 0x00, 0x00,
+];
+
+fn round_trip(message: Icmpv6Message) {
+    let bytes = t!(message.to_bytes());
+    assert_eq!(bytes.len(), message.encoded_len());
+    assert_eq!(bytes[0], message.message_type());
+    assert_eq!(bytes[1], message.code());
+    assert_eq!(t!(Icmpv6Message::decode(&bytes)), message);
+}
+
+#[test]
+fn echo_round_trip() {
+    round_trip(Icmpv6Message::EchoRequest {
+        identifier: 0x1234,
+        sequence: 0xabcd,
+        payload: b"abcdefghijklmnopqrstuvwabcdefghi".to_vec(),
+    });
+    round_trip(Icmpv6Message::EchoReply {
+        identifier: 0xffff,
+        sequence: 0,
+        payload: vec![],
+    });
+}
+
+#[test]
+fn error_messages_round_trip() {
+    for code in 0..=255u8 {
+        round_trip(Icmpv6Message::DestinationUnreachable {
+            code: code.into(),
+            datagram: DATAGRAM.to_vec(),
+            extensions: None,
+        });
+        round_trip(Icmpv6Message::TimeExceeded {
+            code: code.into(),
+            datagram: DATAGRAM.to_vec(),
+            extensions: None,
+        });
+        round_trip(Icmpv6Message::ParameterProblem {
+            code: code.into(),
+            pointer: 0x0001_0006,
+            datagram: DATAGRAM.to_vec(),
+        });
+    }
+    round_trip(Icmpv6Message::PacketTooBig {
+        mtu: 1280,
+        datagram: DATAGRAM.to_vec(),
+    });
+}
+
+#[test]
+fn unknown_round_trip() {
+    round_trip(Icmpv6Message::Unknown {
+        message_type: 200,
+        code: 3,
+        header: [1, 2, 3, 4],
+        payload: vec![5, 6, 7],
+    });
+    round_trip(Icmpv6Message::Unknown {
+        message_type: 100,
+        code: 0,
+        header: [0; 4],
+        payload: vec![],
+    });
+}
+
+#[test]
+fn codes() {
+    for code in 0..=255u8 {
+        assert_eq!(u8::from(DestinationUnreachableCode::from(code)), code);
+        assert_eq!(u8::from(TimeExceededCode::from(code)), code);
+        assert_eq!(u8::from(ParameterProblemCode::from(code)), code);
+    }
+
+    assert_eq!(DestinationUnreachableCode::from(4), DestinationUnreachableCode::Port);
+    assert_eq!(TimeExceededCode::from(0), TimeExceededCode::HopLimitExceeded);
+    assert_eq!(ParameterProblemCode::from(11), ParameterProblemCode::Unassigned(11));
+}
+
+#[test]
+fn decode_packet_too_big() {
+    let mut bytes = vec![0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x05, 0xdc];
+    bytes.extend_from_slice(DATAGRAM);
+
+    let message = t!(Icmpv6Message::decode(&bytes));
+    assert!(message.is_error());
+    assert_eq!(message, Icmpv6Message::PacketTooBig {
+        mtu: 1500,
+        datagram: DATAGRAM.to_vec(),
+    });
+}
+
+#[test]
+fn encode_leaves_checksum() {
+    let message = Icmpv6Message::EchoRequest {
+        identifier: 1,
+        sequence: 2,
+        payload: vec![0xff; 4],
+    };
+
+    assert!(!message.is_error());
+    assert_eq!(t!(message.to_bytes()), [0x80, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x02, 0xff, 0xff, 0xff, 0xff]);
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn kernel_checksum() {
+    // The kernel fills in the checksum at offset 2 of what raw ICMPv6 sockets send.
+    let socket = t!(IcmpSocket::connect(IpAddr::V6(Ipv6Addr::LOCALHOST)));
+    let mut offset: libc::c_int = -1;
+    let mut len = std::mem::size_of::<libc::c_int>() as libc::socklen_t;
+    let ret = unsafe {
+        libc::getsockopt(socket.as_raw_fd(), 255, libc::IPV6_CHECKSUM,
+                         &mut offset as *mut _ as *mut libc::c_void, &mut len)
+    };
+    assert_eq!(ret, 0);
+    assert_eq!(offset, 2);
+}
+
+#[test]
+fn decode_truncated() {
+    assert!(Icmpv6Message::decode(&[]).is_err());
+    assert!(Icmpv6Message::decode(&[128, 0, 0, 0, 0, 1, 0]).is_err());
+}
+
+#[test]
+fn echo_loopback() {
+    let localhost = Ipv6Addr::LOCALHOST;
+    let socket = t!(IcmpSocket::connect(IpAddr::V6(localhost)));
+    t!(socket.set_read_timeout(Some(Duration::from_secs(1))));
+
+    let request = Icmpv6Message::EchoRequest {
+        identifier: 0x4242,
+        sequence: 1,
+        payload: b"echo_loopback".to_vec(),
+    };
+    t!(socket.send(&t!(request.to_bytes())));
+
+    let expected = Icmpv6Message::EchoReply {
+        identifier: 0x4242,
+        sequence: 1,
+        payload: b"echo_loopback".to_vec(),
+    };
+    let mut buf = [0u8; 1024];
+    loop {
+        let (size, source) = t!(socket.recv_from(&mut buf));
+        assert_eq!(source, IpAddr::V6(localhost));
+        // Kernel computes the checksum, so it must match the pseudo-header.
+        if t!(Icmpv6Message::decode_with_checksum(&buf[..size], &localhost, &localhost)) == expected {
+            break;
+        }
+    }
+}
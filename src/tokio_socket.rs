@@ -0,0 +1,195 @@
+//! ICMP socket for the tokio runtime
+
+use std::io::Result;
+use std::net::IpAddr;
+use std::task::{Context, Poll};
+
+use tokio::io::Interest;
+use tokio::io::unix::AsyncFd;
+
+use crate::socket::IcmpSocket;
+
+/// An [`IcmpSocket`][socket] driven by the tokio reactor.
+///
+/// Every call waits for the socket to become ready instead of blocking the thread, so
+/// many sockets can be served by a single task. The `poll_*` methods do the same for
+/// hand-written futures: they return `Poll::Pending` and arrange for the task to be
+/// woken once the socket is ready.
+///
+/// Options are set on the wrapped socket, see [`get_ref`][get_ref]; read and write
+/// timeouts do not apply here.
+///
+/// ```rust,no_run
+/// use std::net::{IpAddr, Ipv4Addr};
+/// use icmp::{AsyncIcmpSocket, IcmpSocket};
+/// use icmp::v4::Icmpv4Message;
+///
+/// # async fn ping() -> std::io::Result<()> {
+/// let localhost = IpAddr::V4(Ipv4Addr::LOCALHOST);
+/// let socket = AsyncIcmpSocket::new(IcmpSocket::connect(localhost)?)?;
+///
+/// let request = Icmpv4Message::Echo {
+///     identifier: 1,
+///     sequence: 1,
+///     payload: b"ping".to_vec(),
+/// };
+/// socket.send(&request.to_bytes()?).await?;
+///
+/// let mut buf = [0; 1024];
+/// let (size, source) = socket.recv_from(&mut buf).await?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [socket]: struct.IcmpSocket.html
+/// [get_ref]: #method.get_ref
+pub struct AsyncIcmpSocket {
+    inner: AsyncFd<IcmpSocket>,
+}
+
+impl AsyncIcmpSocket {
+    /// Moves `socket` into nonblocking mode and registers it with the reactor of the
+    /// current tokio runtime.
+    ///
+    /// # Panics
+    ///
+    /// This function panics when called outside of a tokio runtime, or in one without
+    /// the IO driver enabled.
+    pub fn new(socket: IcmpSocket) -> Result<AsyncIcmpSocket> {
+        socket.set_nonblocking(true)?;
+
+        Ok(AsyncIcmpSocket {
+            inner: AsyncFd::with_interest(socket, Interest::READABLE | Interest::WRITABLE)?,
+        })
+    }
+
+    /// Returns a reference to the wrapped socket.
+    pub fn get_ref(&self) -> &IcmpSocket {
+        self.inner.get_ref()
+    }
+
+    /// Returns a mutable reference to the wrapped socket.
+    pub fn get_mut(&mut self) -> &mut IcmpSocket {
+        self.inner.get_mut()
+    }
+
+    /// Deregisters the socket from the reactor and returns it, still in nonblocking
+    /// mode.
+    pub fn into_inner(self) -> IcmpSocket {
+        self.inner.into_inner()
+    }
+
+    /// Receives data from the socket. On success, returns the number of bytes read.
+    ///
+    /// See [`IcmpSocket::recv`][recv].
+    ///
+    /// [recv]: struct.IcmpSocket.html#method.recv
+    pub async fn recv(&self, buf: &mut [u8]) -> Result<usize> {
+        self.inner.async_io(Interest::READABLE, |socket| socket.recv(buf)).await
+    }
+
+    /// Receives data from the socket. On success, returns the number of bytes read and
+    /// the address from whence the data came.
+    ///
+    /// See [`IcmpSocket::recv_from`][recv_from].
+    ///
+    /// [recv_from]: struct.IcmpSocket.html#method.recv_from
+    pub async fn recv_from(&self, buf: &mut [u8]) -> Result<(usize, IpAddr)> {
+        self.inner.async_io(Interest::READABLE, |socket| socket.recv_from(buf)).await
+    }
+
+    /// Sends data on the socket to the remote address to which it is connected.
+    ///
+    /// See [`IcmpSocket::send`][send].
+    ///
+    /// [send]: struct.IcmpSocket.html#method.send
+    pub async fn send(&self, buf: &[u8]) -> Result<usize> {
+        self.inner.async_io(Interest::WRITABLE, |socket| socket.send(buf)).await
+    }
+
+    /// Sends data on the socket to the given address. On success, returns the number of
+    /// bytes written.
+    ///
+    /// See [`IcmpSocket::send_to`][send_to].
+    ///
+    /// [send_to]: struct.IcmpSocket.html#method.send_to
+    pub async fn send_to(&self, buf: &[u8], addr: IpAddr) -> Result<usize> {
+        self.inner.async_io(Interest::WRITABLE, |socket| socket.send_to(buf, addr)).await
+    }
+
+    /// Polls for a message to be ready to receive.
+    ///
+    /// Returns `Poll::Ready(Ok(()))` once a receive is likely to succeed; it may still
+    /// fail with `WouldBlock`, e.g. when another task took the message first.
+    pub fn poll_recv_ready(&self, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        self.inner.poll_read_ready(cx).map_ok(|_| ())
+    }
+
+    /// Polls for the socket to be ready to send.
+    ///
+    /// Returns `Poll::Ready(Ok(()))` once a send is likely to succeed.
+    pub fn poll_send_ready(&self, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        self.inner.poll_write_ready(cx).map_ok(|_| ())
+    }
+
+    /// Attempts to receive data from the socket, like [`recv`][recv].
+    ///
+    /// [recv]: #method.recv
+    pub fn poll_recv(&self, cx: &mut Context<'_>, buf: &mut [u8]) -> Poll<Result<usize>> {
+        loop {
+            let mut guard = match self.inner.poll_read_ready(cx) {
+                Poll::Ready(guard) => guard?,
+                Poll::Pending => return Poll::Pending,
+            };
+            if let Ok(result) = guard.try_io(|inner| inner.get_ref().recv(buf)) {
+                return Poll::Ready(result);
+            }
+        }
+    }
+
+    /// Attempts to receive data from the socket, like [`recv_from`][recv_from].
+    ///
+    /// [recv_from]: #method.recv_from
+    pub fn poll_recv_from(&self, cx: &mut Context<'_>, buf: &mut [u8]) -> Poll<Result<(usize, IpAddr)>> {
+        loop {
+            let mut guard = match self.inner.poll_read_ready(cx) {
+                Poll::Ready(guard) => guard?,
+                Poll::Pending => return Poll::Pending,
+            };
+            if let Ok(result) = guard.try_io(|inner| inner.get_ref().recv_from(buf)) {
+                return Poll::Ready(result);
+            }
+        }
+    }
+
+    /// Attempts to send data on the socket, like [`send`][send].
+    ///
+    /// [send]: #method.send
+    pub fn poll_send(&self, cx: &mut Context<'_>, buf: &[u8]) -> Poll<Result<usize>> {
+        loop {
+            let mut guard = match self.inner.poll_write_ready(cx) {
+                Poll::Ready(guard) => guard?,
+                Poll::Pending => return Poll::Pending,
+            };
+            if let Ok(result) = guard.try_io(|inner| inner.get_ref().send(buf)) {
+                return Poll::Ready(result);
+            }
+        }
+    }
+
+    /// Attempts to send data on the socket to the given address, like
+    /// [`send_to`][send_to].
+    ///
+    /// [send_to]: #method.send_to
+    pub fn poll_send_to(&self, cx: &mut Context<'_>, buf: &[u8], addr: IpAddr) -> Poll<Result<usize>> {
+        loop {
+            let mut guard = match self.inner.poll_write_ready(cx) {
+                Poll::Ready(guard) => guard?,
+                Poll::Pending => return Poll::Pending,
+            };
+            if let Ok(result) = guard.try_io(|inner| inner.get_ref().send_to(buf, addr)) {
+                return Poll::Ready(result);
+            }
+        }
+    }
+}
@@ -0,0 +1,554 @@
+//! ICMPv4 messages (RFC 792, RFC 950, RFC 8335)
+
+use std::io::{Error, ErrorKind, Result};
+use std::net::Ipv4Addr;
+
+use crate::extension::{self, Extensions, InterfaceIdentifier};
+use crate::packet::{self, IcmpPacket, IcmpPacketMut};
+use crate::probe::{self, InterfaceStatus};
+use crate::quote::QuotedDatagram;
+
+const ECHO_REPLY: u8 = 0;
+const DESTINATION_UNREACHABLE: u8 = 3;
+const SOURCE_QUENCH: u8 = 4;
+const REDIRECT: u8 = 5;
+const ECHO: u8 = 8;
+const TIME_EXCEEDED: u8 = 11;
+const PARAMETER_PROBLEM: u8 = 12;
+const TIMESTAMP: u8 = 13;
+const TIMESTAMP_REPLY: u8 = 14;
+const ADDRESS_MASK_REQUEST: u8 = 17;
+const ADDRESS_MASK_REPLY: u8 = 18;
+const EXTENDED_ECHO_REQUEST: u8 = 42;
+const EXTENDED_ECHO_REPLY: u8 = 43;
+
+/// Length of the fixed ICMPv4 header: type, code, checksum and four bytes of rest-of-header.
+pub const HEADER_LEN: usize = packet::HEADER_LEN;
+
+icmp_code! {
+    /// Codes of the `Destination Unreachable` message.
+    pub enum DestinationUnreachableCode {
+        /// Net Unreachable
+        Net = 0,
+        /// Host Unreachable
+        Host = 1,
+        /// Protocol Unreachable
+        Protocol = 2,
+        /// Port Unreachable
+        Port = 3,
+        /// Fragmentation Needed and Don't Fragment was Set
+        FragmentationNeeded = 4,
+        /// Source Route Failed
+        SourceRouteFailed = 5,
+        /// Destination Network Unknown
+        NetUnknown = 6,
+        /// Destination Host Unknown
+        HostUnknown = 7,
+        /// Source Host Isolated
+        SourceHostIsolated = 8,
+        /// Communication with Destination Network is Administratively Prohibited
+        NetProhibited = 9,
+        /// Communication with Destination Host is Administratively Prohibited
+        HostProhibited = 10,
+        /// Destination Network Unreachable for Type of Service
+        NetTos = 11,
+        /// Destination Host Unreachable for Type of Service
+        HostTos = 12,
+        /// Communication Administratively Prohibited
+        AdministrativelyProhibited = 13,
+        /// Host Precedence Violation
+        HostPrecedenceViolation = 14,
+        /// Precedence cutoff in effect
+        PrecedenceCutoff = 15,
+    }
+}
+
+icmp_code! {
+    /// Codes of the `Redirect` message.
+    pub enum RedirectCode {
+        /// Redirect Datagram for the Network
+        Net = 0,
+        /// Redirect Datagram for the Host
+        Host = 1,
+        /// Redirect Datagram for the Type of Service and Network
+        TosNet = 2,
+        /// Redirect Datagram for the Type of Service and Host
+        TosHost = 3,
+    }
+}
+
+icmp_code! {
+    /// Codes of the `Time Exceeded` message.
+    pub enum TimeExceededCode {
+        /// Time to Live exceeded in Transit
+        TtlExceeded = 0,
+        /// Fragment Reassembly Time Exceeded
+        FragmentReassembly = 1,
+    }
+}
+
+icmp_code! {
+    /// Codes of the `Parameter Problem` message.
+    pub enum ParameterProblemCode {
+        /// Pointer indicates the error
+        Pointer = 0,
+        /// Missing a Required Option
+        MissingOption = 1,
+        /// Bad Length
+        BadLength = 2,
+    }
+}
+
+/// An ICMPv4 message.
+///
+/// Messages are encoded with [`encode`][encode] (or [`to_bytes`][to_bytes]) into a buffer
+/// suitable for `IcmpSocket::send` and decoded from the ICMP part of a received datagram
+/// with [`decode`][decode]. The checksum is computed on encoding and verified on decoding.
+///
+/// Error messages keep the quoted `datagram` (the offending IP header and leading payload
+/// bytes) as-is. RFC 4884 extensions following it are parsed separately; when present, the
+/// datagram is zero-padded to at least 128 bytes on encoding.
+///
+/// ```rust
+/// use icmp::v4::Icmpv4Message;
+///
+/// let echo = Icmpv4Message::Echo {
+///     identifier: 0x1234,
+///     sequence: 1,
+///     payload: b"ping".to_vec(),
+/// };
+/// let bytes = echo.to_bytes().unwrap();
+///
+/// assert_eq!(Icmpv4Message::decode(&bytes).unwrap(), echo);
+/// ```
+///
+/// [encode]: #method.encode
+/// [to_bytes]: #method.to_bytes
+/// [decode]: #method.decode
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Icmpv4Message {
+    /// Echo Reply (type 0)
+    EchoReply {
+        /// Identifier, aids in matching echos and replies
+        identifier: u16,
+        /// Sequence number, aids in matching echos and replies
+        sequence: u16,
+        /// Data echoed back from the request
+        payload: Vec<u8>,
+    },
+    /// Destination Unreachable (type 3)
+    DestinationUnreachable {
+        /// Reason the destination is unreachable
+        code: DestinationUnreachableCode,
+        /// MTU of the next-hop network, set for `FragmentationNeeded` (RFC 1191)
+        next_hop_mtu: u16,
+        /// Quoted IP header and leading bytes of the original datagram
+        datagram: Vec<u8>,
+        /// RFC 4884 extensions following the datagram
+        extensions: Option<Extensions>,
+    },
+    /// Source Quench (type 4), deprecated by RFC 6633
+    SourceQuench {
+        /// Quoted IP header and leading bytes of the original datagram
+        datagram: Vec<u8>,
+    },
+    /// Redirect (type 5)
+    Redirect {
+        /// Kind of the redirect
+        code: RedirectCode,
+        /// Address of the gateway to which traffic should be sent
+        gateway: Ipv4Addr,
+        /// Quoted IP header and leading bytes of the original datagram
+        datagram: Vec<u8>,
+    },
+    /// Echo (type 8)
+    Echo {
+        /// Identifier, aids in matching echos and replies
+        identifier: u16,
+        /// Sequence number, aids in matching echos and replies
+        sequence: u16,
+        /// Data to be echoed back
+        payload: Vec<u8>,
+    },
+    /// Time Exceeded (type 11)
+    TimeExceeded {
+        /// What exactly has exceeded its time
+        code: TimeExceededCode,
+        /// Quoted IP header and leading bytes of the original datagram
+        datagram: Vec<u8>,
+        /// RFC 4884 extensions following the datagram
+        extensions: Option<Extensions>,
+    },
+    /// Parameter Problem (type 12)
+    ParameterProblem {
+        /// Kind of the problem
+        code: ParameterProblemCode,
+        /// Octet of the original datagram where the error was detected
+        pointer: u8,
+        /// Quoted IP header and leading bytes of the original datagram
+        datagram: Vec<u8>,
+        /// RFC 4884 extensions following the datagram
+        extensions: Option<Extensions>,
+    },
+    /// Timestamp (type 13)
+    Timestamp {
+        /// Identifier, aids in matching timestamps and replies
+        identifier: u16,
+        /// Sequence number, aids in matching timestamps and replies
+        sequence: u16,
+        /// Time the sender last touched the message, in milliseconds since midnight UT
+        originate: u32,
+        /// Time the echoer first touched the message
+        receive: u32,
+        /// Time the echoer last touched the message
+        transmit: u32,
+    },
+    /// Timestamp Reply (type 14)
+    TimestampReply {
+        /// Identifier, aids in matching timestamps and replies
+        identifier: u16,
+        /// Sequence number, aids in matching timestamps and replies
+        sequence: u16,
+        /// Time the sender last touched the message, in milliseconds since midnight UT
+        originate: u32,
+        /// Time the echoer first touched the message
+        receive: u32,
+        /// Time the echoer last touched the message
+        transmit: u32,
+    },
+    /// Address Mask Request (type 17)
+    AddressMaskRequest {
+        /// Identifier, aids in matching requests and replies
+        identifier: u16,
+        /// Sequence number, aids in matching requests and replies
+        sequence: u16,
+        /// Subnet address mask, zero in requests
+        mask: Ipv4Addr,
+    },
+    /// Address Mask Reply (type 18)
+    AddressMaskReply {
+        /// Identifier, aids in matching requests and replies
+        identifier: u16,
+        /// Sequence number, aids in matching requests and replies
+        sequence: u16,
+        /// Subnet address mask
+        mask: Ipv4Addr,
+    },
+    /// Extended Echo Request (type 42, RFC 8335)
+    ExtendedEchoRequest {
+        /// Identifier, aids in matching requests and replies
+        identifier: u16,
+        /// Sequence number, aids in matching requests and replies
+        sequence: u8,
+        /// Probed interface resides on the node receiving the request
+        local: bool,
+        /// Probed interface
+        interface: InterfaceIdentifier,
+    },
+    /// Extended Echo Reply (type 43, RFC 8335)
+    ExtendedEchoReply {
+        /// Identifier, aids in matching requests and replies
+        identifier: u16,
+        /// Sequence number, aids in matching requests and replies
+        sequence: u8,
+        /// Status of the probed interface
+        status: InterfaceStatus,
+    },
+    /// Any other message type, kept as-is
+    Unknown {
+        /// Message type
+        message_type: u8,
+        /// Message code
+        code: u8,
+        /// Four bytes of type-specific rest-of-header
+        header: [u8; 4],
+        /// Message body following the header
+        payload: Vec<u8>,
+    },
+}
+
+impl Icmpv4Message {
+    /// Returns the ICMP type of this message.
+    pub fn message_type(&self) -> u8 {
+        match *self {
+            Icmpv4Message::EchoReply { .. } => ECHO_REPLY,
+            Icmpv4Message::DestinationUnreachable { .. } => DESTINATION_UNREACHABLE,
+            Icmpv4Message::SourceQuench { .. } => SOURCE_QUENCH,
+            Icmpv4Message::Redirect { .. } => REDIRECT,
+            Icmpv4Message::Echo { .. } => ECHO,
+            Icmpv4Message::TimeExceeded { .. } => TIME_EXCEEDED,
+            Icmpv4Message::ParameterProblem { .. } => PARAMETER_PROBLEM,
+            Icmpv4Message::Timestamp { .. } => TIMESTAMP,
+            Icmpv4Message::TimestampReply { .. } => TIMESTAMP_REPLY,
+            Icmpv4Message::AddressMaskRequest { .. } => ADDRESS_MASK_REQUEST,
+            Icmpv4Message::AddressMaskReply { .. } => ADDRESS_MASK_REPLY,
+            Icmpv4Message::ExtendedEchoRequest { .. } => EXTENDED_ECHO_REQUEST,
+            Icmpv4Message::ExtendedEchoReply { .. } => EXTENDED_ECHO_REPLY,
+            Icmpv4Message::Unknown { message_type, .. } => message_type,
+        }
+    }
+
+    /// Returns the ICMP code of this message.
+    pub fn code(&self) -> u8 {
+        match *self {
+            Icmpv4Message::DestinationUnreachable { code, .. } => code.into(),
+            Icmpv4Message::Redirect { code, .. } => code.into(),
+            Icmpv4Message::TimeExceeded { code, .. } => code.into(),
+            Icmpv4Message::ParameterProblem { code, .. } => code.into(),
+            Icmpv4Message::ExtendedEchoReply { status, .. } => status.code.into(),
+            Icmpv4Message::Unknown { code, .. } => code,
+            _ => 0,
+        }
+    }
+
+    /// Returns the quoted datagram bytes of an error message, `None` for other messages.
+    pub fn datagram(&self) -> Option<&[u8]> {
+        match *self {
+            Icmpv4Message::DestinationUnreachable { ref datagram, .. }
+            | Icmpv4Message::SourceQuench { ref datagram, .. }
+            | Icmpv4Message::Redirect { ref datagram, .. }
+            | Icmpv4Message::TimeExceeded { ref datagram, .. }
+            | Icmpv4Message::ParameterProblem { ref datagram, .. } => Some(datagram),
+            _ => None,
+        }
+    }
+
+    /// Parses the IP header and leading transport bytes quoted by an error message.
+    ///
+    /// Fails with `InvalidInput` for messages which are not errors.
+    pub fn quoted(&self) -> Result<QuotedDatagram> {
+        match self.datagram() {
+            Some(datagram) => QuotedDatagram::decode(datagram),
+            None => Err(Error::new(ErrorKind::InvalidInput, "not an ICMP error message")),
+        }
+    }
+
+    /// Returns the number of bytes [`encode`][encode] writes for this message.
+    ///
+    /// [encode]: #method.encode
+    pub fn encoded_len(&self) -> usize {
+        HEADER_LEN + match *self {
+            Icmpv4Message::EchoReply { ref payload, .. }
+            | Icmpv4Message::Echo { ref payload, .. }
+            | Icmpv4Message::Unknown { ref payload, .. } => payload.len(),
+            Icmpv4Message::DestinationUnreachable { ref datagram, ref extensions, .. }
+            | Icmpv4Message::TimeExceeded { ref datagram, ref extensions, .. }
+            | Icmpv4Message::ParameterProblem { ref datagram, ref extensions, .. } => {
+                extension::body_len(datagram, extensions.as_ref(), 4)
+            },
+            Icmpv4Message::SourceQuench { ref datagram } | Icmpv4Message::Redirect { ref datagram, .. } => {
+                datagram.len()
+            },
+            Icmpv4Message::Timestamp { .. } | Icmpv4Message::TimestampReply { .. } => 12,
+            Icmpv4Message::AddressMaskRequest { .. } | Icmpv4Message::AddressMaskReply { .. } => 4,
+            Icmpv4Message::ExtendedEchoRequest { ref interface, .. } => {
+                probe::request_extensions(interface).encoded_len()
+            },
+            Icmpv4Message::ExtendedEchoReply { .. } => 0,
+        }
+    }
+
+    /// Encodes this message into `buf`, computing the checksum.
+    ///
+    /// On success, returns the number of bytes written. Fails with `InvalidInput` for a
+    /// buffer shorter than [`encoded_len`][encoded_len], or for contents which cannot be
+    /// encoded, e.g. a quoted datagram too long to be followed by extensions.
+    ///
+    /// [encoded_len]: #method.encoded_len
+    pub fn encode(&self, buf: &mut [u8]) -> Result<usize> {
+        let len = self.encoded_len();
+        if buf.len() < len {
+            return Err(Error::new(ErrorKind::InvalidInput, "buffer is too small for ICMPv4 message"));
+        }
+        let buf = &mut buf[..len];
+
+        buf[0] = self.message_type();
+        buf[1] = self.code();
+        buf[2..4].copy_from_slice(&[0, 0]);
+        let (header, body) = buf[4..].split_at_mut(4);
+
+        match *self {
+            Icmpv4Message::EchoReply { identifier, sequence, ref payload }
+            | Icmpv4Message::Echo { identifier, sequence, ref payload } => {
+                header[..2].copy_from_slice(&identifier.to_be_bytes());
+                header[2..].copy_from_slice(&sequence.to_be_bytes());
+                body.copy_from_slice(payload);
+            },
+            Icmpv4Message::DestinationUnreachable { next_hop_mtu, ref datagram, ref extensions, .. } => {
+                let length = extension::encode_body(body, datagram, extensions.as_ref(), 4)?;
+                header[..2].copy_from_slice(&[0, length]);
+                header[2..].copy_from_slice(&next_hop_mtu.to_be_bytes());
+            },
+            Icmpv4Message::SourceQuench { ref datagram } => {
+                header.copy_from_slice(&[0; 4]);
+                body.copy_from_slice(datagram);
+            },
+            Icmpv4Message::TimeExceeded { ref datagram, ref extensions, .. } => {
+                let length = extension::encode_body(body, datagram, extensions.as_ref(), 4)?;
+                header.copy_from_slice(&[0, length, 0, 0]);
+            },
+            Icmpv4Message::Redirect { gateway, ref datagram, .. } => {
+                header.copy_from_slice(&gateway.octets());
+                body.copy_from_slice(datagram);
+            },
+            Icmpv4Message::ParameterProblem { pointer, ref datagram, ref extensions, .. } => {
+                let length = extension::encode_body(body, datagram, extensions.as_ref(), 4)?;
+                header.copy_from_slice(&[pointer, length, 0, 0]);
+            },
+            Icmpv4Message::Timestamp { identifier, sequence, originate, receive, transmit }
+            | Icmpv4Message::TimestampReply { identifier, sequence, originate, receive, transmit } => {
+                header[..2].copy_from_slice(&identifier.to_be_bytes());
+                header[2..].copy_from_slice(&sequence.to_be_bytes());
+                body[..4].copy_from_slice(&originate.to_be_bytes());
+                body[4..8].copy_from_slice(&receive.to_be_bytes());
+                body[8..].copy_from_slice(&transmit.to_be_bytes());
+            },
+            Icmpv4Message::AddressMaskRequest { identifier, sequence, mask }
+            | Icmpv4Message::AddressMaskReply { identifier, sequence, mask } => {
+                header[..2].copy_from_slice(&identifier.to_be_bytes());
+                header[2..].copy_from_slice(&sequence.to_be_bytes());
+                body.copy_from_slice(&mask.octets());
+            },
+            Icmpv4Message::ExtendedEchoRequest { identifier, sequence, local, ref interface } => {
+                header[..2].copy_from_slice(&identifier.to_be_bytes());
+                header[2..].copy_from_slice(&[sequence, u8::from(local)]);
+                probe::request_extensions(interface).encode(body)?;
+            },
+            Icmpv4Message::ExtendedEchoReply { identifier, sequence, ref status } => {
+                header[..2].copy_from_slice(&identifier.to_be_bytes());
+                header[2..].copy_from_slice(&[sequence, probe::encode_status(status)]);
+            },
+            Icmpv4Message::Unknown { header: rest, ref payload, .. } => {
+                header.copy_from_slice(&rest);
+                body.copy_from_slice(payload);
+            },
+        }
+
+        IcmpPacketMut::new(buf)?.fill_checksum();
+
+        Ok(len)
+    }
+
+    /// Encodes this message into a newly allocated buffer.
+    ///
+    /// Fails like [`encode`][encode] does for contents that cannot be encoded.
+    ///
+    /// [encode]: #method.encode
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        let mut buf = vec![0; self.encoded_len()];
+        self.encode(&mut buf)?;
+        Ok(buf)
+    }
+
+    /// Decodes a message from `buf`, verifying its checksum.
+    ///
+    /// `buf` must start with the ICMP header; raw IPv4 sockets deliver the IP header in
+    /// front of it, which has to be skipped first.
+    pub fn decode(buf: &[u8]) -> Result<Icmpv4Message> {
+        let packet = IcmpPacket::new(buf)?;
+        if !packet.verify_checksum() {
+            return Err(Error::new(ErrorKind::InvalidData, "ICMPv4 checksum mismatch"));
+        }
+
+        Icmpv4Message::from_packet(&packet)
+    }
+
+    /// Decodes a message from a borrowed view, without verifying its checksum.
+    pub fn from_packet(packet: &IcmpPacket<'_>) -> Result<Icmpv4Message> {
+        let code = packet.code();
+        let header = packet.rest_of_header();
+        let identifier = u16::from_be_bytes([header[0], header[1]]);
+        let sequence = u16::from_be_bytes([header[2], header[3]]);
+        let body = packet.payload();
+
+        let message = match packet.message_type() {
+            ECHO_REPLY => Icmpv4Message::EchoReply {
+                identifier,
+                sequence,
+                payload: body.to_vec(),
+            },
+            DESTINATION_UNREACHABLE => {
+                let (datagram, extensions) = extension::split(body, usize::from(header[1]) * 4);
+                Icmpv4Message::DestinationUnreachable {
+                    code: code.into(),
+                    next_hop_mtu: u16::from_be_bytes([header[2], header[3]]),
+                    datagram: datagram.to_vec(),
+                    extensions,
+                }
+            },
+            SOURCE_QUENCH => Icmpv4Message::SourceQuench {
+                datagram: body.to_vec(),
+            },
+            REDIRECT => Icmpv4Message::Redirect {
+                code: code.into(),
+                gateway: Ipv4Addr::from(header),
+                datagram: body.to_vec(),
+            },
+            ECHO => Icmpv4Message::Echo {
+                identifier,
+                sequence,
+                payload: body.to_vec(),
+            },
+            TIME_EXCEEDED => {
+                let (datagram, extensions) = extension::split(body, usize::from(header[1]) * 4);
+                Icmpv4Message::TimeExceeded {
+                    code: code.into(),
+                    datagram: datagram.to_vec(),
+                    extensions,
+                }
+            },
+            PARAMETER_PROBLEM => {
+                let (datagram, extensions) = extension::split(body, usize::from(header[1]) * 4);
+                Icmpv4Message::ParameterProblem {
+                    code: code.into(),
+                    pointer: header[0],
+                    datagram: datagram.to_vec(),
+                    extensions,
+                }
+            },
+            message_type @ TIMESTAMP | message_type @ TIMESTAMP_REPLY => {
+                if body.len() < 12 {
+                    return Err(Error::new(ErrorKind::InvalidData, "ICMPv4 timestamp message is too short"));
+                }
+                let originate = u32::from_be_bytes([body[0], body[1], body[2], body[3]]);
+                let receive = u32::from_be_bytes([body[4], body[5], body[6], body[7]]);
+                let transmit = u32::from_be_bytes([body[8], body[9], body[10], body[11]]);
+                if message_type == TIMESTAMP {
+                    Icmpv4Message::Timestamp { identifier, sequence, originate, receive, transmit }
+                } else {
+                    Icmpv4Message::TimestampReply { identifier, sequence, originate, receive, transmit }
+                }
+            },
+            message_type @ ADDRESS_MASK_REQUEST | message_type @ ADDRESS_MASK_REPLY => {
+                if body.len() < 4 {
+                    return Err(Error::new(ErrorKind::InvalidData, "ICMPv4 address mask message is too short"));
+                }
+                let mask = Ipv4Addr::new(body[0], body[1], body[2], body[3]);
+                if message_type == ADDRESS_MASK_REQUEST {
+                    Icmpv4Message::AddressMaskRequest { identifier, sequence, mask }
+                } else {
+                    Icmpv4Message::AddressMaskReply { identifier, sequence, mask }
+                }
+            },
+            EXTENDED_ECHO_REQUEST => Icmpv4Message::ExtendedEchoRequest {
+                identifier,
+                sequence: header[2],
+                local: header[3] & 0x01 != 0,
+                interface: probe::decode_request(body)?,
+            },
+            EXTENDED_ECHO_REPLY => Icmpv4Message::ExtendedEchoReply {
+                identifier,
+                sequence: header[2],
+                status: probe::decode_status(code, header[3]),
+            },
+            message_type => Icmpv4Message::Unknown {
+                message_type,
+                code,
+                header,
+                payload: body.to_vec(),
+            },
+        };
+
+        Ok(message)
+    }
+}
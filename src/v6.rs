@@ -0,0 +1,697 @@
+//! ICMPv6 messages (RFC 4443, RFC 3810, RFC 4861, RFC 8335)
+
+use std::io::{Error, ErrorKind, Result};
+use std::net::Ipv6Addr;
+
+use crate::extension::{self, Extensions, InterfaceIdentifier};
+use crate::mld::{self, Mldv2Query, MulticastAddressRecord};
+use crate::ndp::{self, NdpOption, RoutePreference};
+use crate::packet::{self, IcmpPacket, IcmpPacketMut};
+use crate::probe::{self, InterfaceStatus};
+use crate::quote::QuotedDatagram;
+
+const DESTINATION_UNREACHABLE: u8 = 1;
+const PACKET_TOO_BIG: u8 = 2;
+const TIME_EXCEEDED: u8 = 3;
+const PARAMETER_PROBLEM: u8 = 4;
+const ECHO_REQUEST: u8 = 128;
+const ECHO_REPLY: u8 = 129;
+const MULTICAST_LISTENER_QUERY: u8 = 130;
+const MULTICAST_LISTENER_REPORT: u8 = 131;
+const MULTICAST_LISTENER_DONE: u8 = 132;
+const ROUTER_SOLICITATION: u8 = 133;
+const ROUTER_ADVERTISEMENT: u8 = 134;
+const NEIGHBOR_SOLICITATION: u8 = 135;
+const NEIGHBOR_ADVERTISEMENT: u8 = 136;
+const REDIRECT: u8 = 137;
+const MULTICAST_LISTENER_REPORT_V2: u8 = 143;
+const EXTENDED_ECHO_REQUEST: u8 = 160;
+const EXTENDED_ECHO_REPLY: u8 = 161;
+
+// Router Advertisement flags
+const MANAGED: u8 = 0x80;
+const OTHER_CONFIG: u8 = 0x40;
+
+// Neighbor Advertisement flags
+const ROUTER: u8 = 0x80;
+const SOLICITED: u8 = 0x40;
+const OVERRIDE: u8 = 0x20;
+
+/// Length of the fixed ICMPv6 header: type, code, checksum and four bytes of message body.
+pub const HEADER_LEN: usize = packet::HEADER_LEN;
+
+icmp_code! {
+    /// Codes of the `Destination Unreachable` message.
+    pub enum DestinationUnreachableCode {
+        /// No route to destination
+        NoRoute = 0,
+        /// Communication with destination administratively prohibited
+        AdministrativelyProhibited = 1,
+        /// Beyond scope of source address
+        BeyondScope = 2,
+        /// Address unreachable
+        Address = 3,
+        /// Port unreachable
+        Port = 4,
+        /// Source address failed ingress/egress policy
+        SourcePolicy = 5,
+        /// Reject route to destination
+        RejectRoute = 6,
+        /// Error in Source Routing Header (RFC 6550)
+        SourceRoutingHeader = 7,
+        /// Headers too long (RFC 8883)
+        HeadersTooLong = 8,
+    }
+}
+
+icmp_code! {
+    /// Codes of the `Time Exceeded` message.
+    pub enum TimeExceededCode {
+        /// Hop limit exceeded in transit
+        HopLimitExceeded = 0,
+        /// Fragment reassembly time exceeded
+        FragmentReassembly = 1,
+    }
+}
+
+icmp_code! {
+    /// Codes of the `Parameter Problem` message.
+    pub enum ParameterProblemCode {
+        /// Erroneous header field encountered
+        ErroneousHeader = 0,
+        /// Unrecognized Next Header type encountered
+        UnrecognizedNextHeader = 1,
+        /// Unrecognized IPv6 option encountered
+        UnrecognizedOption = 2,
+        /// IPv6 First Fragment has incomplete IPv6 Header Chain (RFC 7112)
+        IncompleteHeaderChain = 3,
+        /// SR Upper-layer Header Error (RFC 8754)
+        SrUpperLayerHeader = 4,
+        /// Unrecognized Next Header type encountered by intermediate node (RFC 8883)
+        IntermediateUnrecognizedNextHeader = 5,
+        /// Extension header too big (RFC 8883)
+        ExtensionHeaderTooBig = 6,
+        /// Extension header chain too long (RFC 8883)
+        ExtensionHeaderChainTooLong = 7,
+        /// Too many extension headers (RFC 8883)
+        TooManyExtensionHeaders = 8,
+        /// Too many options in extension header (RFC 8883)
+        TooManyOptions = 9,
+        /// Option too big (RFC 8883)
+        OptionTooBig = 10,
+    }
+}
+
+/// An ICMPv6 message.
+///
+/// Encoding and decoding mirror [`Icmpv4Message`][v4], except for the checksum: it covers an
+/// IPv6 pseudo-header, and the kernel fills it in for raw ICMPv6 sockets on send and verifies
+/// it on receive. [`encode`][encode] thus leaves it zeroed and [`decode`][decode] ignores it;
+/// when the addresses are known, [`encode_with_checksum`][encode_with_checksum] and
+/// [`decode_with_checksum`][decode_with_checksum] handle it instead.
+///
+/// ```rust
+/// use icmp::v6::Icmpv6Message;
+///
+/// let echo = Icmpv6Message::EchoRequest {
+///     identifier: 0x1234,
+///     sequence: 1,
+///     payload: b"ping".to_vec(),
+/// };
+/// let bytes = echo.to_bytes().unwrap();
+///
+/// assert_eq!(Icmpv6Message::decode(&bytes).unwrap(), echo);
+/// ```
+///
+/// [v4]: ../v4/enum.Icmpv4Message.html
+/// [encode]: #method.encode
+/// [decode]: #method.decode
+/// [encode_with_checksum]: #method.encode_with_checksum
+/// [decode_with_checksum]: #method.decode_with_checksum
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Icmpv6Message {
+    /// Destination Unreachable (type 1)
+    DestinationUnreachable {
+        /// Reason the destination is unreachable
+        code: DestinationUnreachableCode,
+        /// As much of the invoking packet as possible
+        datagram: Vec<u8>,
+        /// RFC 4884 extensions following the packet
+        extensions: Option<Extensions>,
+    },
+    /// Packet Too Big (type 2)
+    PacketTooBig {
+        /// MTU of the next-hop link
+        mtu: u32,
+        /// As much of the invoking packet as possible
+        datagram: Vec<u8>,
+    },
+    /// Time Exceeded (type 3)
+    TimeExceeded {
+        /// What exactly has exceeded its time
+        code: TimeExceededCode,
+        /// As much of the invoking packet as possible
+        datagram: Vec<u8>,
+        /// RFC 4884 extensions following the packet
+        extensions: Option<Extensions>,
+    },
+    /// Parameter Problem (type 4)
+    ParameterProblem {
+        /// Kind of the problem
+        code: ParameterProblemCode,
+        /// Octet offset within the invoking packet where the error was detected
+        pointer: u32,
+        /// As much of the invoking packet as possible
+        datagram: Vec<u8>,
+    },
+    /// Echo Request (type 128)
+    EchoRequest {
+        /// Identifier, aids in matching requests and replies
+        identifier: u16,
+        /// Sequence number, aids in matching requests and replies
+        sequence: u16,
+        /// Data to be echoed back
+        payload: Vec<u8>,
+    },
+    /// Echo Reply (type 129)
+    EchoReply {
+        /// Identifier, aids in matching requests and replies
+        identifier: u16,
+        /// Sequence number, aids in matching requests and replies
+        sequence: u16,
+        /// Data echoed back from the request
+        payload: Vec<u8>,
+    },
+    /// Multicast Listener Query (type 130)
+    MulticastListenerQuery {
+        /// Maximum Response Code; see `mld::max_response_delay`
+        max_response_code: u16,
+        /// Multicast address being queried, unspecified for a general query
+        multicast_address: Ipv6Addr,
+        /// MLDv2 fields, `None` for MLDv1 queries
+        v2: Option<Mldv2Query>,
+    },
+    /// Version 1 Multicast Listener Report (type 131)
+    MulticastListenerReport {
+        /// Multicast address being listened to
+        multicast_address: Ipv6Addr,
+    },
+    /// Multicast Listener Done (type 132)
+    MulticastListenerDone {
+        /// Multicast address no longer listened to
+        multicast_address: Ipv6Addr,
+    },
+    /// Router Solicitation (type 133)
+    RouterSolicitation {
+        /// Options, usually the source link-layer address
+        options: Vec<NdpOption>,
+    },
+    /// Router Advertisement (type 134)
+    RouterAdvertisement {
+        /// Hop limit hosts should use for outgoing packets, `0` if unspecified
+        hop_limit: u8,
+        /// Addresses are available via DHCPv6
+        managed: bool,
+        /// Other configuration is available via DHCPv6
+        other_config: bool,
+        /// Preference of this router over other default routers (RFC 4191)
+        preference: RoutePreference,
+        /// Seconds this router may be used as default router, `0` if it is none
+        router_lifetime: u16,
+        /// Milliseconds a neighbor is assumed reachable after a confirmation, `0` if unspecified
+        reachable_time: u32,
+        /// Milliseconds between retransmitted Neighbor Solicitations, `0` if unspecified
+        retrans_timer: u32,
+        /// Options such as prefixes, the link MTU and DNS servers
+        options: Vec<NdpOption>,
+    },
+    /// Neighbor Solicitation (type 135)
+    NeighborSolicitation {
+        /// Address whose link-layer address is requested
+        target: Ipv6Addr,
+        /// Options, usually the source link-layer address
+        options: Vec<NdpOption>,
+    },
+    /// Neighbor Advertisement (type 136)
+    NeighborAdvertisement {
+        /// Sender is a router
+        router: bool,
+        /// Sent in response to a Neighbor Solicitation
+        solicited: bool,
+        /// Override an existing cache entry
+        override_flag: bool,
+        /// Address the advertisement is about
+        target: Ipv6Addr,
+        /// Options, usually the target link-layer address
+        options: Vec<NdpOption>,
+    },
+    /// Redirect (type 137)
+    Redirect {
+        /// Better first hop for `destination`
+        target: Ipv6Addr,
+        /// Destination being redirected
+        destination: Ipv6Addr,
+        /// Options, usually the target link-layer address and the redirected header
+        options: Vec<NdpOption>,
+    },
+    /// Version 2 Multicast Listener Report (type 143)
+    MulticastListenerReportV2 {
+        /// Listening state per multicast address
+        records: Vec<MulticastAddressRecord>,
+    },
+    /// Extended Echo Request (type 160, RFC 8335)
+    ExtendedEchoRequest {
+        /// Identifier, aids in matching requests and replies
+        identifier: u16,
+        /// Sequence number, aids in matching requests and replies
+        sequence: u8,
+        /// Probed interface resides on the node receiving the request
+        local: bool,
+        /// Probed interface
+        interface: InterfaceIdentifier,
+    },
+    /// Extended Echo Reply (type 161, RFC 8335)
+    ExtendedEchoReply {
+        /// Identifier, aids in matching requests and replies
+        identifier: u16,
+        /// Sequence number, aids in matching requests and replies
+        sequence: u8,
+        /// Status of the probed interface
+        status: InterfaceStatus,
+    },
+    /// Any other message type, kept as-is
+    Unknown {
+        /// Message type
+        message_type: u8,
+        /// Message code
+        code: u8,
+        /// Four bytes of type-specific message body following the checksum
+        header: [u8; 4],
+        /// Rest of the message body
+        payload: Vec<u8>,
+    },
+}
+
+impl Icmpv6Message {
+    /// Returns the ICMPv6 type of this message.
+    pub fn message_type(&self) -> u8 {
+        match *self {
+            Icmpv6Message::DestinationUnreachable { .. } => DESTINATION_UNREACHABLE,
+            Icmpv6Message::PacketTooBig { .. } => PACKET_TOO_BIG,
+            Icmpv6Message::TimeExceeded { .. } => TIME_EXCEEDED,
+            Icmpv6Message::ParameterProblem { .. } => PARAMETER_PROBLEM,
+            Icmpv6Message::EchoRequest { .. } => ECHO_REQUEST,
+            Icmpv6Message::EchoReply { .. } => ECHO_REPLY,
+            Icmpv6Message::MulticastListenerQuery { .. } => MULTICAST_LISTENER_QUERY,
+            Icmpv6Message::MulticastListenerReport { .. } => MULTICAST_LISTENER_REPORT,
+            Icmpv6Message::MulticastListenerDone { .. } => MULTICAST_LISTENER_DONE,
+            Icmpv6Message::RouterSolicitation { .. } => ROUTER_SOLICITATION,
+            Icmpv6Message::RouterAdvertisement { .. } => ROUTER_ADVERTISEMENT,
+            Icmpv6Message::NeighborSolicitation { .. } => NEIGHBOR_SOLICITATION,
+            Icmpv6Message::NeighborAdvertisement { .. } => NEIGHBOR_ADVERTISEMENT,
+            Icmpv6Message::Redirect { .. } => REDIRECT,
+            Icmpv6Message::MulticastListenerReportV2 { .. } => MULTICAST_LISTENER_REPORT_V2,
+            Icmpv6Message::ExtendedEchoRequest { .. } => EXTENDED_ECHO_REQUEST,
+            Icmpv6Message::ExtendedEchoReply { .. } => EXTENDED_ECHO_REPLY,
+            Icmpv6Message::Unknown { message_type, .. } => message_type,
+        }
+    }
+
+    /// Returns the ICMPv6 code of this message.
+    pub fn code(&self) -> u8 {
+        match *self {
+            Icmpv6Message::DestinationUnreachable { code, .. } => code.into(),
+            Icmpv6Message::TimeExceeded { code, .. } => code.into(),
+            Icmpv6Message::ParameterProblem { code, .. } => code.into(),
+            Icmpv6Message::ExtendedEchoReply { status, .. } => status.code.into(),
+            Icmpv6Message::Unknown { code, .. } => code,
+            _ => 0,
+        }
+    }
+
+    /// Returns `true` for error messages (types 0 to 127).
+    pub fn is_error(&self) -> bool {
+        self.message_type() < 128
+    }
+
+    /// Returns the quoted datagram bytes of an error message, `None` for other messages.
+    pub fn datagram(&self) -> Option<&[u8]> {
+        match *self {
+            Icmpv6Message::DestinationUnreachable { ref datagram, .. }
+            | Icmpv6Message::PacketTooBig { ref datagram, .. }
+            | Icmpv6Message::TimeExceeded { ref datagram, .. }
+            | Icmpv6Message::ParameterProblem { ref datagram, .. } => Some(datagram),
+            _ => None,
+        }
+    }
+
+    /// Parses the IP header and leading transport bytes quoted by an error message.
+    ///
+    /// Fails with `InvalidInput` for messages which are not errors.
+    pub fn quoted(&self) -> Result<QuotedDatagram> {
+        match self.datagram() {
+            Some(datagram) => QuotedDatagram::decode(datagram),
+            None => Err(Error::new(ErrorKind::InvalidInput, "not an ICMP error message")),
+        }
+    }
+
+    /// Returns the number of bytes [`encode`][encode] writes for this message.
+    ///
+    /// [encode]: #method.encode
+    pub fn encoded_len(&self) -> usize {
+        HEADER_LEN + match *self {
+            Icmpv6Message::DestinationUnreachable { ref datagram, ref extensions, .. }
+            | Icmpv6Message::TimeExceeded { ref datagram, ref extensions, .. } => {
+                extension::body_len(datagram, extensions.as_ref(), 8)
+            },
+            Icmpv6Message::PacketTooBig { ref datagram, .. } | Icmpv6Message::ParameterProblem { ref datagram, .. } => {
+                datagram.len()
+            },
+            Icmpv6Message::EchoRequest { ref payload, .. }
+            | Icmpv6Message::EchoReply { ref payload, .. }
+            | Icmpv6Message::Unknown { ref payload, .. } => payload.len(),
+            Icmpv6Message::MulticastListenerQuery { ref v2, .. } => mld::query_len(v2.as_ref()),
+            Icmpv6Message::MulticastListenerReport { .. } | Icmpv6Message::MulticastListenerDone { .. } => 16,
+            Icmpv6Message::MulticastListenerReportV2 { ref records } => mld::records_len(records),
+            Icmpv6Message::RouterSolicitation { ref options } => ndp::options_len(options),
+            Icmpv6Message::RouterAdvertisement { ref options, .. } => 8 + ndp::options_len(options),
+            Icmpv6Message::NeighborSolicitation { ref options, .. }
+            | Icmpv6Message::NeighborAdvertisement { ref options, .. } => 16 + ndp::options_len(options),
+            Icmpv6Message::Redirect { ref options, .. } => 32 + ndp::options_len(options),
+            Icmpv6Message::ExtendedEchoRequest { ref interface, .. } => {
+                probe::request_extensions(interface).encoded_len()
+            },
+            Icmpv6Message::ExtendedEchoReply { .. } => 0,
+        }
+    }
+
+    /// Encodes this message into `buf`, leaving the checksum zeroed.
+    ///
+    /// The checksum covers a pseudo-header with the source and destination addresses, which
+    /// the kernel only settles when sending: messages sent on an `IcmpSocket` get their
+    /// checksum filled in by the kernel. Use [`encode_with_checksum`][with_checksum] for
+    /// messages sent any other way.
+    ///
+    /// On success, returns the number of bytes written. Fails with `InvalidInput` for a
+    /// buffer shorter than [`encoded_len`][encoded_len], or for contents which cannot be
+    /// encoded, e.g. a quoted datagram too long to be followed by extensions.
+    ///
+    /// [with_checksum]: #method.encode_with_checksum
+    /// [encoded_len]: #method.encoded_len
+    pub fn encode(&self, buf: &mut [u8]) -> Result<usize> {
+        let len = self.encoded_len();
+        if buf.len() < len {
+            return Err(Error::new(ErrorKind::InvalidInput, "buffer is too small for ICMPv6 message"));
+        }
+        let buf = &mut buf[..len];
+
+        buf[0] = self.message_type();
+        buf[1] = self.code();
+        buf[2..4].copy_from_slice(&[0, 0]);
+        let (header, body) = buf[4..].split_at_mut(4);
+
+        match *self {
+            Icmpv6Message::DestinationUnreachable { ref datagram, ref extensions, .. }
+            | Icmpv6Message::TimeExceeded { ref datagram, ref extensions, .. } => {
+                let length = extension::encode_body(body, datagram, extensions.as_ref(), 8)?;
+                header.copy_from_slice(&[length, 0, 0, 0]);
+            },
+            Icmpv6Message::PacketTooBig { mtu: value, ref datagram }
+            | Icmpv6Message::ParameterProblem { pointer: value, ref datagram, .. } => {
+                header.copy_from_slice(&value.to_be_bytes());
+                body.copy_from_slice(datagram);
+            },
+            Icmpv6Message::EchoRequest { identifier, sequence, ref payload }
+            | Icmpv6Message::EchoReply { identifier, sequence, ref payload } => {
+                header[..2].copy_from_slice(&identifier.to_be_bytes());
+                header[2..].copy_from_slice(&sequence.to_be_bytes());
+                body.copy_from_slice(payload);
+            },
+            Icmpv6Message::MulticastListenerQuery { max_response_code, ref multicast_address, ref v2 } => {
+                header[..2].copy_from_slice(&max_response_code.to_be_bytes());
+                header[2..].copy_from_slice(&[0, 0]);
+                mld::encode_query(body, multicast_address, v2.as_ref())?;
+            },
+            Icmpv6Message::MulticastListenerReport { multicast_address }
+            | Icmpv6Message::MulticastListenerDone { multicast_address } => {
+                header.copy_from_slice(&[0; 4]);
+                body.copy_from_slice(&multicast_address.octets());
+            },
+            Icmpv6Message::MulticastListenerReportV2 { ref records } => {
+                if records.len() > usize::from(u16::MAX) {
+                    return Err(Error::new(ErrorKind::InvalidInput, "too many records for MLD report"));
+                }
+                header[..2].copy_from_slice(&[0, 0]);
+                header[2..].copy_from_slice(&(records.len() as u16).to_be_bytes());
+                mld::encode_records(body, records)?;
+            },
+            Icmpv6Message::RouterSolicitation { ref options } => {
+                header.copy_from_slice(&[0; 4]);
+                ndp::encode_options(body, options)?;
+            },
+            Icmpv6Message::RouterAdvertisement {
+                hop_limit,
+                managed,
+                other_config,
+                preference,
+                router_lifetime,
+                reachable_time,
+                retrans_timer,
+                ref options,
+            } => {
+                let mut flags = preference.bits() << 3;
+                if managed {
+                    flags |= MANAGED;
+                }
+                if other_config {
+                    flags |= OTHER_CONFIG;
+                }
+                header[..2].copy_from_slice(&[hop_limit, flags]);
+                header[2..].copy_from_slice(&router_lifetime.to_be_bytes());
+                body[..4].copy_from_slice(&reachable_time.to_be_bytes());
+                body[4..8].copy_from_slice(&retrans_timer.to_be_bytes());
+                ndp::encode_options(&mut body[8..], options)?;
+            },
+            Icmpv6Message::NeighborSolicitation { target, ref options } => {
+                header.copy_from_slice(&[0; 4]);
+                body[..16].copy_from_slice(&target.octets());
+                ndp::encode_options(&mut body[16..], options)?;
+            },
+            Icmpv6Message::NeighborAdvertisement { router, solicited, override_flag, target, ref options } => {
+                let mut flags = 0;
+                if router {
+                    flags |= ROUTER;
+                }
+                if solicited {
+                    flags |= SOLICITED;
+                }
+                if override_flag {
+                    flags |= OVERRIDE;
+                }
+                header.copy_from_slice(&[flags, 0, 0, 0]);
+                body[..16].copy_from_slice(&target.octets());
+                ndp::encode_options(&mut body[16..], options)?;
+            },
+            Icmpv6Message::Redirect { target, destination, ref options } => {
+                header.copy_from_slice(&[0; 4]);
+                body[..16].copy_from_slice(&target.octets());
+                body[16..32].copy_from_slice(&destination.octets());
+                ndp::encode_options(&mut body[32..], options)?;
+            },
+            Icmpv6Message::ExtendedEchoRequest { identifier, sequence, local, ref interface } => {
+                header[..2].copy_from_slice(&identifier.to_be_bytes());
+                header[2..].copy_from_slice(&[sequence, u8::from(local)]);
+                probe::request_extensions(interface).encode(body)?;
+            },
+            Icmpv6Message::ExtendedEchoReply { identifier, sequence, ref status } => {
+                header[..2].copy_from_slice(&identifier.to_be_bytes());
+                header[2..].copy_from_slice(&[sequence, probe::encode_status(status)]);
+            },
+            Icmpv6Message::Unknown { header: rest, ref payload, .. } => {
+                header.copy_from_slice(&rest);
+                body.copy_from_slice(payload);
+            },
+        }
+
+        Ok(len)
+    }
+
+    /// Encodes this message into `buf`, computing the checksum for a message sent from
+    /// `source` to `destination`.
+    pub fn encode_with_checksum(&self, buf: &mut [u8], source: &Ipv6Addr, destination: &Ipv6Addr) -> Result<usize> {
+        let len = self.encode(buf)?;
+        IcmpPacketMut::new(&mut buf[..len])?.fill_checksum_v6(source, destination);
+
+        Ok(len)
+    }
+
+    /// Encodes this message into a newly allocated buffer, leaving the checksum zeroed
+    /// like [`encode`][encode] does.
+    ///
+    /// Fails like [`encode`][encode] does for contents that cannot be encoded.
+    ///
+    /// [encode]: #method.encode
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        let mut buf = vec![0; self.encoded_len()];
+        self.encode(&mut buf)?;
+        Ok(buf)
+    }
+
+    /// Decodes a message from `buf`, without verifying its checksum.
+    ///
+    /// Raw ICMPv6 sockets never deliver the IPv6 header, so `buf` is exactly what
+    /// `IcmpSocket::recv` returns. Neither do they deliver messages with a wrong checksum:
+    /// the kernel verifies it before. Use [`decode_with_checksum`][with_checksum] for
+    /// messages received any other way.
+    ///
+    /// [with_checksum]: #method.decode_with_checksum
+    pub fn decode(buf: &[u8]) -> Result<Icmpv6Message> {
+        Icmpv6Message::from_packet(&IcmpPacket::new(buf)?)
+    }
+
+    /// Decodes a message from a borrowed view.
+    pub fn from_packet(packet: &IcmpPacket<'_>) -> Result<Icmpv6Message> {
+        let code = packet.code();
+        let header = packet.rest_of_header();
+        let body = packet.payload();
+
+        let message = match packet.message_type() {
+            DESTINATION_UNREACHABLE => {
+                let (datagram, extensions) = extension::split(body, usize::from(header[0]) * 8);
+                Icmpv6Message::DestinationUnreachable {
+                    code: code.into(),
+                    datagram: datagram.to_vec(),
+                    extensions,
+                }
+            },
+            PACKET_TOO_BIG => Icmpv6Message::PacketTooBig {
+                mtu: u32::from_be_bytes(header),
+                datagram: body.to_vec(),
+            },
+            TIME_EXCEEDED => {
+                let (datagram, extensions) = extension::split(body, usize::from(header[0]) * 8);
+                Icmpv6Message::TimeExceeded {
+                    code: code.into(),
+                    datagram: datagram.to_vec(),
+                    extensions,
+                }
+            },
+            PARAMETER_PROBLEM => Icmpv6Message::ParameterProblem {
+                code: code.into(),
+                pointer: u32::from_be_bytes(header),
+                datagram: body.to_vec(),
+            },
+            ECHO_REQUEST => Icmpv6Message::EchoRequest {
+                identifier: u16::from_be_bytes([header[0], header[1]]),
+                sequence: u16::from_be_bytes([header[2], header[3]]),
+                payload: body.to_vec(),
+            },
+            ECHO_REPLY => Icmpv6Message::EchoReply {
+                identifier: u16::from_be_bytes([header[0], header[1]]),
+                sequence: u16::from_be_bytes([header[2], header[3]]),
+                payload: body.to_vec(),
+            },
+            MULTICAST_LISTENER_QUERY => {
+                let (multicast_address, v2) = mld::decode_query(body)?;
+                Icmpv6Message::MulticastListenerQuery {
+                    max_response_code: u16::from_be_bytes([header[0], header[1]]),
+                    multicast_address,
+                    v2,
+                }
+            },
+            message_type @ MULTICAST_LISTENER_REPORT | message_type @ MULTICAST_LISTENER_DONE => {
+                if body.len() < 16 {
+                    return Err(Error::new(ErrorKind::InvalidData, "multicast listener message is too short"));
+                }
+                let multicast_address = ndp::ipv6_addr(&body[..16]);
+                if message_type == MULTICAST_LISTENER_REPORT {
+                    Icmpv6Message::MulticastListenerReport { multicast_address }
+                } else {
+                    Icmpv6Message::MulticastListenerDone { multicast_address }
+                }
+            },
+            MULTICAST_LISTENER_REPORT_V2 => Icmpv6Message::MulticastListenerReportV2 {
+                records: mld::decode_records(body, u16::from_be_bytes([header[2], header[3]]))?,
+            },
+            ROUTER_SOLICITATION => Icmpv6Message::RouterSolicitation {
+                options: ndp::decode_options(body)?,
+            },
+            ROUTER_ADVERTISEMENT => {
+                if body.len() < 8 {
+                    return Err(Error::new(ErrorKind::InvalidData, "router advertisement is too short"));
+                }
+                Icmpv6Message::RouterAdvertisement {
+                    hop_limit: header[0],
+                    managed: header[1] & MANAGED != 0,
+                    other_config: header[1] & OTHER_CONFIG != 0,
+                    preference: RoutePreference::from_bits(header[1] >> 3),
+                    router_lifetime: u16::from_be_bytes([header[2], header[3]]),
+                    reachable_time: u32::from_be_bytes([body[0], body[1], body[2], body[3]]),
+                    retrans_timer: u32::from_be_bytes([body[4], body[5], body[6], body[7]]),
+                    options: ndp::decode_options(&body[8..])?,
+                }
+            },
+            NEIGHBOR_SOLICITATION => {
+                if body.len() < 16 {
+                    return Err(Error::new(ErrorKind::InvalidData, "neighbor solicitation is too short"));
+                }
+                Icmpv6Message::NeighborSolicitation {
+                    target: ndp::ipv6_addr(&body[..16]),
+                    options: ndp::decode_options(&body[16..])?,
+                }
+            },
+            NEIGHBOR_ADVERTISEMENT => {
+                if body.len() < 16 {
+                    return Err(Error::new(ErrorKind::InvalidData, "neighbor advertisement is too short"));
+                }
+                Icmpv6Message::NeighborAdvertisement {
+                    router: header[0] & ROUTER != 0,
+                    solicited: header[0] & SOLICITED != 0,
+                    override_flag: header[0] & OVERRIDE != 0,
+                    target: ndp::ipv6_addr(&body[..16]),
+                    options: ndp::decode_options(&body[16..])?,
+                }
+            },
+            REDIRECT => {
+                if body.len() < 32 {
+                    return Err(Error::new(ErrorKind::InvalidData, "redirect is too short"));
+                }
+                Icmpv6Message::Redirect {
+                    target: ndp::ipv6_addr(&body[..16]),
+                    destination: ndp::ipv6_addr(&body[16..32]),
+                    options: ndp::decode_options(&body[32..])?,
+                }
+            },
+            EXTENDED_ECHO_REQUEST => Icmpv6Message::ExtendedEchoRequest {
+                identifier: u16::from_be_bytes([header[0], header[1]]),
+                sequence: header[2],
+                local: header[3] & 0x01 != 0,
+                interface: probe::decode_request(body)?,
+            },
+            EXTENDED_ECHO_REPLY => Icmpv6Message::ExtendedEchoReply {
+                identifier: u16::from_be_bytes([header[0], header[1]]),
+                sequence: header[2],
+                status: probe::decode_status(code, header[3]),
+            },
+            message_type => Icmpv6Message::Unknown {
+                message_type,
+                code,
+                header,
+                payload: body.to_vec(),
+            },
+        };
+
+        Ok(message)
+    }
+
+    /// Decodes a message from `buf`, verifying its checksum for a message sent from `source`
+    /// to `destination`.
+    pub fn decode_with_checksum(buf: &[u8], source: &Ipv6Addr, destination: &Ipv6Addr) -> Result<Icmpv6Message> {
+        let packet = IcmpPacket::new(buf)?;
+        if !packet.verify_checksum_v6(source, destination) {
+            return Err(Error::new(ErrorKind::InvalidData, "ICMPv6 checksum mismatch"));
+        }
+
+        Icmpv6Message::from_packet(&packet)
+    }
+}